@@ -0,0 +1,54 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::config::CFG;
+use crate::errors::{Error, Result};
+use crate::pass::agent::{self, Request, Response};
+
+/// Start the agent, which keeps the decrypted index in memory until it is locked, or until it
+/// was idle for `timeout` seconds.
+pub fn agent(timeout: Option<u64>) -> Result<()> {
+    let socket = agent::default_socket().ok_or_else(|| {
+        Error::Agent("XDG_RUNTIME_DIR is not set, set agent_socket in the config".to_string())
+    })?;
+    if socket.exists() {
+        if UnixStream::connect(&socket).is_ok() {
+            return Err(Error::Agent(format!(
+                "already running on {}",
+                socket.display()
+            )));
+        }
+        // the socket of an agent which was killed
+        std::fs::remove_file(&socket)?;
+    }
+
+    let listener = agent::bind(&socket)?;
+    println!("rpass agent listening on {}", socket.display());
+    let timeout = Duration::from_secs(timeout.unwrap_or(CFG.main.agent_timeout));
+    agent::serve(listener, &socket, timeout)
+}
+
+/// Make the agent forget all decrypted content, and stop it.
+pub fn lock() -> Result<()> {
+    match agent::request(&Request::Lock) {
+        Some(Response::Done) => println!("Agent locked"),
+        _ => println!("No agent is running"),
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use crate::commands::utils::choose_entry;
+use crate::errors::{Error, Result};
+use crate::pass::index::{self, Transaction};
+
+/// Add `alias` as a further path of the entry. The alias must not be used by any other entry.
+pub fn add(path: Option<&str>, id: Option<&str>, alias: &str) -> Result<()> {
+    let alias = alias.trim_matches('/');
+    if alias.is_empty() {
+        return Err(Error::InvalidInput("Alias cannot be empty"));
+    }
+    let entry = choose_entry(path, id, false)?;
+    let mut tx = Transaction::begin(format!("Add alias {} to {}.", alias, entry.uuid))?;
+    tx.update_metadata(entry.uuid, |record| {
+        if !record.paths().any(|p| p == alias) {
+            record.aliases.push(alias.to_string());
+        }
+    });
+    tx.commit()?;
+    println!(
+        "{} is now an alias of {}",
+        alias,
+        entry.path.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Remove `alias` from the entry it belongs to. The entry itself is kept.
+pub fn remove(alias: &str) -> Result<()> {
+    let alias = alias.trim_matches('/');
+    let mut tx = Transaction::begin(format!("Remove alias {}.", alias))?;
+    let owners: Vec<_> = tx
+        .records()
+        .iter()
+        .filter(|r| r.aliases.iter().any(|a| a == alias))
+        .map(|r| r.uuid)
+        .collect();
+    if owners.is_empty() {
+        return Err(Error::UnknownPath(alias.to_string()));
+    }
+    for id in owners {
+        tx.update_metadata(id, |record| record.aliases.retain(|a| a != alias));
+    }
+    tx.commit()
+}
+
+/// Print all aliases of the entry.
+pub fn list(path: Option<&str>, id: Option<&str>) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    if let Some(record) = index::get_records()?
+        .into_iter()
+        .find(|r| r.uuid == entry.uuid)
+    {
+        for alias in record.aliases.iter() {
+            println!("{}", alias);
+        }
+    }
+    Ok(())
+}
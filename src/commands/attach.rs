@@ -0,0 +1,79 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use crate::commands::utils::choose_entry;
+use crate::errors::{Error, Result};
+
+/// Attach the file `file` to the entry.
+pub fn attach(path: Option<&str>, id: Option<&str>, file: &str, name: Option<&str>) -> Result<()> {
+    let mut entry = choose_entry(path, id, false)?;
+    let file_name = match name {
+        Some(name) => name.to_string(),
+        None => Path::new(file)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .ok_or_else(|| Error::Attachment(format!("Invalid file: {}", file)))?,
+    };
+    let content = fs::read(file)?;
+    entry.attach(&file_name, &content)?;
+    println!(
+        "Attached {} to {}",
+        file_name,
+        entry.path.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Print the names of all files attached to the entry.
+pub fn attachments(path: Option<&str>, id: Option<&str>) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    for name in entry.attachments() {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+/// Write the attachment `name` of the entry to `output`, or to `name` in the current folder.
+/// Existing files are never overwritten.
+pub fn extract(
+    path: Option<&str>,
+    id: Option<&str>,
+    name: &str,
+    output: Option<&str>,
+) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    let output = Path::new(output.unwrap_or(name));
+    let content = entry.extract(name)?;
+    // only the user may read the decrypted file
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(output)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => {
+                Error::Attachment(format!("{} already exists", output.display()))
+            }
+            _ => e.into(),
+        })?;
+    file.write_all(&content)?;
+    Ok(())
+}
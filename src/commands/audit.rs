@@ -0,0 +1,337 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::collections::HashMap;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::commands::fix_index::{check_entry, Problem};
+use crate::config::CFG;
+use crate::def;
+use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
+use crate::pass::entry::Entry;
+use crate::pass::expiry::{self, parse_duration};
+use crate::pass::hibp::Breaches;
+use crate::pass::index;
+use crate::pass::strength::Strength;
+use crate::Loading;
+
+/// Entry with a password below the `min_strength` from the config.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeakPassword {
+    pub path: String,
+    pub score: u8,
+    pub warning: Option<&'static str>,
+}
+
+/// Entry whose password appeared in data breaches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Breached {
+    pub path: String,
+    /// Number of breaches the password appeared in.
+    pub count: u64,
+}
+
+/// Entry whose password could not be looked up in the breach dump.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Unchecked {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Entry without a value which is part of its type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Incomplete {
+    pub path: String,
+    pub missing: Vec<&'static str>,
+}
+
+/// Entry whose password was not changed in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotRotated {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Inconsistency between the index and an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexProblem {
+    pub path: String,
+    pub problem: String,
+}
+
+/// Result of auditing all entries of the store.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditReport {
+    /// Number of audited entries.
+    pub entries: usize,
+    /// Groups of entries sharing the same password.
+    pub reused: Vec<Vec<String>>,
+    pub weak: Vec<WeakPassword>,
+    /// Entries with breached passwords, or `None` without a breach dump.
+    pub breached: Option<Vec<Breached>>,
+    pub unchecked: Vec<Unchecked>,
+    pub incomplete: Vec<Incomplete>,
+    pub not_rotated: Vec<NotRotated>,
+    pub index: Vec<IndexProblem>,
+}
+
+impl AuditReport {
+    /// Number of problems found.
+    pub fn problems(&self) -> usize {
+        self.reused.len()
+            + self.weak.len()
+            + self.breached.as_ref().map_or(0, Vec::len)
+            + self.unchecked.len()
+            + self.incomplete.len()
+            + self.not_rotated.len()
+            + self.index.len()
+    }
+}
+
+/// Audit all entries of the store, and print the report as a table, or as JSON. Passwords which
+/// were not changed for `max_age` (e.g., `365d`) are reported, unless they expire explicitly.
+/// Passwords are looked up in the breach dump `hibp`, or in the configured `hibp_file`. Fails
+/// with `Error::AuditFailed` if any problem was found.
+pub fn audit(json: bool, max_age: Option<&str>, hibp: Option<&str>) -> Result<()> {
+    let max_age_days = match max_age {
+        Some(duration) => (parse_duration(duration)? / (24 * 60 * 60)) as i64,
+        None => def::AUDIT_MAX_AGE_DAYS,
+    };
+    let breaches = match hibp {
+        Some(file) => Some(Breaches::open(file)?),
+        None => Breaches::configured()?,
+    };
+    let report = audit_report(max_age_days, breaches)?;
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        false => print_table(&report),
+    }
+    match report.problems() {
+        0 => Ok(()),
+        n => Err(Error::AuditFailed(n)),
+    }
+}
+
+/// Decrypt all entries in the index (in parallel, if the backend supports it), and check them.
+pub fn audit_report(max_age_days: i64, mut breaches: Option<Breaches>) -> Result<AuditReport> {
+    let records = index::get_records()?;
+    let names: Vec<String> = records
+        .iter()
+        .map(|r| format!("{}/{}", CFG.main.uuid_folder, r.uuid))
+        .collect();
+    let contents = {
+        let _loading = Loading::new("Decrypting all entries...")?;
+        get_backend().read_all(&names)
+    };
+
+    let index_list: Vec<_> = records.iter().map(|r| (r.uuid, r.path.clone())).collect();
+    let path_lookup = index::to_hashmap(&index_list);
+    let mut report = AuditReport {
+        entries: records.len(),
+        reused: Vec::new(),
+        weak: Vec::new(),
+        breached: breaches.as_ref().map(|_| Vec::new()),
+        unchecked: Vec::new(),
+        incomplete: Vec::new(),
+        not_rotated: Vec::new(),
+        index: Vec::new(),
+    };
+    for (path, first, second) in index::get_conflicts()? {
+        report.index.push(IndexProblem {
+            problem: format!("claimed by the entries {} and {}", first, second),
+            path,
+        });
+    }
+
+    // passwords are only compared by their hash, salted for this audit
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let mut passwords: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+    let max_age = format!("{}d", max_age_days);
+
+    for ((record, name), content) in records.iter().zip(names.iter()).zip(contents) {
+        let path = record.path.clone();
+        let mut entry = match content.and_then(|c| Entry::parse(name, &c)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.index.push(IndexProblem {
+                    path,
+                    problem: format!("cannot be read: {}", e),
+                });
+                continue;
+            }
+        };
+        entry.uuid = record.uuid;
+
+        if let Some(Problem::PathMismatch { .. }) = check_entry(&entry, &path_lookup) {
+            report.index.push(IndexProblem {
+                path: path.clone(),
+                problem: format!(
+                    "path in entry is {}",
+                    entry.path.as_deref().unwrap_or(def::DISPLAY_EMPTY)
+                ),
+            });
+        }
+
+        if entry.password.is_empty() {
+            continue;
+        }
+        let hash = Sha256::new()
+            .chain_update(salt)
+            .chain_update(entry.password.as_bytes())
+            .finalize()
+            .to_vec();
+        passwords.entry(hash).or_default().push(path.clone());
+
+        let template = entry.template();
+        let generated = template.as_ref().is_none_or(|t| t.generate);
+        let strength = Strength::of(&entry.password);
+        if generated && strength.score < CFG.main.min_strength {
+            report.weak.push(WeakPassword {
+                path: path.clone(),
+                score: strength.score,
+                warning: strength.warning(),
+            });
+        }
+
+        if let (Some(breaches), Some(breached)) = (breaches.as_mut(), report.breached.as_mut()) {
+            match breaches.count(&entry.password) {
+                Ok(0) => (),
+                Ok(count) => breached.push(Breached {
+                    path: path.clone(),
+                    count,
+                }),
+                Err(e) => report.unchecked.push(Unchecked {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        let (needs_username, needs_url) = template.map_or((true, true), |t| (t.username, t.url));
+        let mut missing = Vec::new();
+        if needs_username && entry.username.is_none() {
+            missing.push("username");
+        }
+        if needs_url && entry.url.is_none() {
+            missing.push("url");
+        }
+        if !missing.is_empty() {
+            report.incomplete.push(Incomplete {
+                path: path.clone(),
+                missing,
+            });
+        }
+
+        // without a rotation of its own, a password is due once it reaches the maximum age
+        let expires = entry.field_value(def::EXPIRES_FIELD);
+        let rotate_every = entry.field_value(def::ROTATE_FIELD);
+        let since = record.password_since();
+        let due = expiry::due_date(expires, Some(rotate_every.unwrap_or(&max_age)), since);
+        let reason = match (due, expires.or(rotate_every)) {
+            // e.g., migrated entries, whose password may be arbitrarily old
+            (None, _) if since.is_none() && expires.is_none() => {
+                Some("password age is unknown".to_string())
+            }
+            (Some(due), Some(_)) if due < expiry::today() => {
+                Some(format!("password {}", expiry::describe(due)))
+            }
+            (Some(due), None) if due < expiry::today() => Some(format!(
+                "password not changed for more than {} days",
+                max_age_days
+            )),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            report.not_rotated.push(NotRotated { path, reason });
+        }
+    }
+
+    report.reused = passwords
+        .into_values()
+        .map(|mut paths| {
+            paths.sort();
+            paths
+        })
+        .filter(|paths| paths.len() > 1)
+        .collect();
+    report.reused.sort();
+    Ok(report)
+}
+
+fn print_table(report: &AuditReport) {
+    let width = report
+        .weak
+        .iter()
+        .map(|w| w.path.len())
+        .chain(report.breached.iter().flatten().map(|b| b.path.len()))
+        .chain(report.unchecked.iter().map(|u| u.path.len()))
+        .chain(report.incomplete.iter().map(|i| i.path.len()))
+        .chain(report.not_rotated.iter().map(|n| n.path.len()))
+        .chain(report.index.iter().map(|i| i.path.len()))
+        .max()
+        .unwrap_or(0);
+    let row = |path: &str, text: &str| println!("    {:<width$}  {}", path, text, width = width);
+
+    println!("Audited {} entries", report.entries);
+    println!("\nReused passwords: {}", report.reused.len());
+    for paths in report.reused.iter() {
+        println!("    {}", paths.join(", "));
+    }
+    println!("\nWeak passwords: {}", report.weak.len());
+    for weak in report.weak.iter() {
+        let label = format!("{}/4", weak.score);
+        match weak.warning {
+            Some(warning) => row(&weak.path, &format!("{}, {}", label, warning)),
+            None => row(&weak.path, &label),
+        }
+    }
+    if let Some(breached) = report.breached.as_ref() {
+        println!("\nBreached passwords: {}", breached.len());
+        for breached in breached.iter() {
+            row(
+                &breached.path,
+                &format!("appeared in {} data breaches", breached.count),
+            );
+        }
+    }
+    if !report.unchecked.is_empty() {
+        println!("\nNot checked for breaches: {}", report.unchecked.len());
+        for unchecked in report.unchecked.iter() {
+            row(&unchecked.path, &unchecked.reason);
+        }
+    }
+    println!("\nIncomplete entries: {}", report.incomplete.len());
+    for incomplete in report.incomplete.iter() {
+        row(
+            &incomplete.path,
+            &format!("missing {}", incomplete.missing.join(" and ")),
+        );
+    }
+    println!("\nNot rotated: {}", report.not_rotated.len());
+    for not_rotated in report.not_rotated.iter() {
+        row(&not_rotated.path, &not_rotated.reason);
+    }
+    println!("\nIndex problems: {}", report.index.len());
+    for problem in report.index.iter() {
+        row(&problem.path, &problem.problem);
+    }
+}
@@ -16,67 +16,48 @@
 
 use crate::commands::utils;
 use crate::config::CFG;
-use crate::def;
 use crate::errors::Result;
+use crate::pass::backend::get_backend;
 use crate::pass::entry::Entry;
+use crate::pass::index::{self, Transaction};
 
-use dirs::home_dir;
+use itertools::Itertools;
 use uuid::Uuid;
 
 use std::collections::HashMap;
-use std::fs::copy;
 use std::io::{self, Write};
-use std::process::Command;
 use std::str::FromStr;
 
 pub fn bulk_rename() -> Result<()> {
-    // extract the correct file names
-    let mut uuid_folder = home_dir().unwrap();
-    uuid_folder.push(def::ROOT_FOLDER);
-    uuid_folder.push(CFG.main.uuid_folder);
-
-    let mut index_file = uuid_folder.clone();
-    index_file.push(CFG.main.index_file);
-    let index_entry = format!("{}/{}", CFG.main.uuid_folder, CFG.main.index_entry);
-
-    let mut shadow_file = uuid_folder;
-    shadow_file.push(".shadow.gpg");
+    // extract the correct entry names
+    let backend = get_backend();
     let shadow_entry = format!("{}/.shadow", CFG.main.uuid_folder);
 
-    // first of all, copy the index file to index_clone
-    copy(index_file, shadow_file)?;
+    // first of all, write all uuids and paths to the shadow entry
+    // aliases are not renamed, only the entries themselves
+    let index = index::get_records()?
+        .into_iter()
+        .sorted_by(|a, b| a.path.cmp(&b.path))
+        .map(|r| format!("{} {}\n", r.uuid, r.path))
+        .collect::<String>();
+    backend.write(&shadow_entry, &index)?;
 
-    // then, start the editor to edit the shadow file
-    Command::new("pass")
-        .arg("edit")
-        .arg(format!("{}/{}", CFG.main.uuid_folder, ".shadow"))
-        .spawn()?
-        .wait()?;
+    // then, start the editor to edit the shadow entry
+    backend.edit(&shadow_entry)?;
 
     // now, we can compare both files and get all keys that have changed
-    let index = String::from_utf8(Command::new("pass").arg(index_entry).output()?.stdout)?;
-    let shadow = String::from_utf8(Command::new("pass").arg(shadow_entry).output()?.stdout)?;
-    let index: HashMap<Uuid, String> = parse_index_file(&index);
-    let mut shadow: HashMap<Uuid, String> = parse_index_file(&shadow);
-
-    let modified_uuids: Vec<Uuid> = index
-        .iter()
-        .filter(|(k, v)| shadow.contains_key(k) && shadow.get(k) != Some(v))
-        .map(|(k, _)| *k)
-        .collect();
+    let shadow = backend.read(&shadow_entry)?;
+    backend.delete(&shadow_entry)?;
+    let renames = find_renames(&index, &shadow);
 
-    if modified_uuids.is_empty() {
+    if renames.is_empty() {
         println!("\nNo keys are renamed!");
         return Ok(());
     }
 
     println!("\nThe following modifications will be performed:\n");
-    for uuid in modified_uuids.iter() {
-        println!(
-            "    {} --> {}",
-            index.get(uuid).unwrap(),
-            shadow.get(uuid).unwrap()
-        );
+    for (_, old_path, new_path) in renames.iter() {
+        println!("    {} --> {}", old_path, new_path);
     }
 
     if !utils::confirm("\nDo you want to continue?", false) {
@@ -84,14 +65,37 @@ pub fn bulk_rename() -> Result<()> {
         return Ok(());
     }
 
-    // perform the renaming
+    apply_renames(renames)
+}
+
+/// Compare the original index with the edited one. Returns the uuid, the old and the new path of
+/// all entries for which the path was changed, sorted by the old path.
+pub fn find_renames(index: &str, edited: &str) -> Vec<(Uuid, String, String)> {
+    let index: HashMap<Uuid, String> = parse_index_file(index);
+    let mut edited: HashMap<Uuid, String> = parse_index_file(edited);
+
+    let mut renames: Vec<(Uuid, String, String)> = index
+        .into_iter()
+        .filter_map(|(k, v)| match edited.remove(&k) {
+            Some(new_path) if new_path != v => Some((k, v, new_path)),
+            _ => None,
+        })
+        .collect();
+    renames.sort_by(|a, b| a.1.cmp(&b.1));
+    renames
+}
+
+/// Move all entries to their new path. Either all or none of the entries are moved.
+pub fn apply_renames(renames: Vec<(Uuid, String, String)>) -> Result<()> {
     print!("working");
     io::stdout().flush()?;
-    for uuid in modified_uuids {
-        Entry::get(uuid)?.change_path(shadow.remove(&uuid).unwrap())?;
+    let mut tx = Transaction::begin(format!("Rename {} entries.", renames.len()))?;
+    for (uuid, _, new_path) in renames {
+        Entry::get(uuid)?.change_path_in(new_path, &mut tx);
         print!(".");
         io::stdout().flush()?;
     }
+    tx.commit()?;
     println!(" done!");
 
     Ok(())
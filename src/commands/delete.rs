@@ -18,18 +18,29 @@ use crate::commands::utils::{choose_entry, confirm};
 use crate::errors::{Error, Result};
 use crate::pass;
 
-pub fn delete(path: Option<&str>, id: Option<&str>, force: bool, use_rofi: bool) -> Result<()> {
+/// Move the entry to the trash, or delete it permanently.
+pub fn delete(
+    path: Option<&str>,
+    id: Option<&str>,
+    force: bool,
+    permanent: bool,
+    use_rofi: bool,
+) -> Result<()> {
     let entry = choose_entry(path, id, use_rofi)?;
+    let remove = |id| match permanent {
+        true => pass::index::remove(id),
+        false => pass::trash::trash(id),
+    };
 
     if !force {
         if !use_rofi {
             println!("{}", entry);
         }
         match confirm("Are you sure to delete this entry?", use_rofi) {
-            true => pass::index::remove(entry.uuid),
+            true => remove(entry.uuid),
             false => Err(Error::Interrupted),
         }
     } else {
-        pass::index::remove(entry.uuid)
+        remove(entry.uuid)
     }
 }
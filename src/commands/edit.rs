@@ -17,15 +17,22 @@
 use notify_rust::{Notification, NotificationUrgency, Timeout};
 use rofi::{Format, Rofi};
 
-use crate::commands::utils::{choose_entry, confirm, notify_action, notify_error, question_rofi};
+use crate::commands::utils::{
+    choose_entry, confirm, notify_action, notify_error, notify_trashed, question_rofi,
+};
 use crate::commands::{delete, mv, passwd};
 use crate::config::CFG;
 use crate::def;
 use crate::errors::Result;
 use crate::pass::entry::Entry;
+use crate::pass::strength::Strength;
 
-pub fn edit(path: Option<&str>, id: Option<&str>, use_rofi: bool) -> Result<()> {
-    if use_rofi {
+/// Edit the entry, or only its notes if `notes` is set.
+pub fn edit(path: Option<&str>, id: Option<&str>, use_rofi: bool, notes: bool) -> Result<()> {
+    if notes {
+        let mut entry = choose_entry(path, id, use_rofi)?;
+        entry.edit_note(def::NOTES_FIELD, use_rofi)
+    } else if use_rofi {
         edit_interactive(path, id)
     } else {
         let mut entry = choose_entry(path, id, use_rofi)?;
@@ -40,6 +47,7 @@ fn edit_interactive(path: Option<&str>, id: Option<&str>) -> Result<()> {
     loop {
         let mut lines: Vec<String> = entry.get_rofi_lines();
         lines.push(def::format_button(def::DISPLAY_BTN_NEW_RAW));
+        lines.push(def::format_button(def::DISPLAY_BTN_EDIT_NOTES));
         lines.push(String::new());
         lines.push(def::format_button(def::DISPLAY_BTN_DELETE));
         lines.push(def::format_button(def::DISPLAY_BTN_MAIN_MENU));
@@ -80,20 +88,26 @@ fn edit_interactive(path: Option<&str>, id: Option<&str>) -> Result<()> {
                     };
                 }
                 EditMenuAction::EditPassword => {
-                    let random_pw = match confirm("Generate a random password?", true) {
-                        true => Some(20),
-                        false => None,
+                    // the generated password follows the policy and the rules of the entry
+                    let question = match entry.field_value(def::PWRULE_FIELD) {
+                        Some(rule) => format!("Generate a random password ({})?", rule),
+                        None => "Generate a random password?".to_string(),
                     };
+                    let random_pw = confirm(&question, true);
                     match passwd(
                         None,
                         Some(format!("{}", entry_id).as_str()),
                         None,
                         random_pw,
+                        None,
                         true,
                     ) {
                         Ok(()) => {
                             entry = Entry::get(entry_id)?;
-                            notify_action("Changed password");
+                            notify_action(format!(
+                                "Changed password, {}",
+                                Strength::of(&entry.password).label()
+                            ));
                         }
                         Err(e) => notify_error(e),
                     }
@@ -102,6 +116,17 @@ fn edit_interactive(path: Option<&str>, id: Option<&str>) -> Result<()> {
                     Ok(new_url) => entry.change_url(new_url)?,
                     Err(e) => notify_error(e),
                 },
+                EditMenuAction::EditNotes => match entry.edit_note(def::NOTES_FIELD, true) {
+                    Ok(()) => notify_action("Changed notes"),
+                    Err(e) => notify_error(e),
+                },
+                EditMenuAction::EditOther(s) if entry.note_at_line(&s).is_some() => {
+                    let name = entry.note_at_line(&s).unwrap();
+                    match entry.edit_note(&name, true) {
+                        Ok(()) => notify_action(format!("Changed {}", name)),
+                        Err(e) => notify_error(e),
+                    }
+                }
                 EditMenuAction::EditOther(s) => match question_rofi("Edit Raw line") {
                     Ok(new_line) => match entry.change_raw_line(Some(s), new_line) {
                         Ok(()) => notify_action("Changed raw line"),
@@ -117,8 +142,20 @@ fn edit_interactive(path: Option<&str>, id: Option<&str>) -> Result<()> {
                     Err(e) => notify_error(e),
                 },
                 EditMenuAction::Delete => {
-                    match delete(None, Some(format!("{}", entry_id).as_str()), false, true) {
-                        Ok(()) => break,
+                    match delete(
+                        None,
+                        Some(format!("{}", entry_id).as_str()),
+                        false,
+                        false,
+                        true,
+                    ) {
+                        Ok(()) => {
+                            let path = entry.path.clone().unwrap_or_default();
+                            if let Err(e) = notify_trashed(entry_id, &path) {
+                                notify_error(e);
+                            }
+                            break;
+                        }
                         Err(e) => notify_error(e),
                     }
                 }
@@ -140,6 +177,7 @@ enum EditMenuAction {
     EditUrl,
     EditOther(String),
     AddOther,
+    EditNotes,
     Delete,
     DoNothing,
     Exit,
@@ -156,8 +194,12 @@ fn get_menu_action(s: String) -> EditMenuAction {
         EditMenuAction::EditPassword
     } else if s.starts_with(def::DISPLAY_URL) {
         EditMenuAction::EditUrl
+    } else if s.starts_with(def::DISPLAY_DUE) || s.starts_with(def::DISPLAY_STRENGTH) {
+        EditMenuAction::DoNothing
     } else if s == def::DISPLAY_BTN_NEW_RAW {
         EditMenuAction::AddOther
+    } else if s == def::DISPLAY_BTN_EDIT_NOTES {
+        EditMenuAction::EditNotes
     } else if s == def::DISPLAY_BTN_DELETE {
         EditMenuAction::Delete
     } else if s == def::DISPLAY_RAW {
@@ -0,0 +1,51 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use chrono::NaiveDate;
+
+use crate::commands::find::get_searchable_records;
+use crate::def;
+use crate::errors::Result;
+use crate::pass::expiry::{self, parse_duration};
+
+/// Print all entries whose password is due within `within` (e.g., `14d`), starting with the one
+/// due first. Without `within`, entries are listed two weeks before they are due.
+pub fn expiring(within: Option<&str>) -> Result<()> {
+    let days = match within {
+        Some(duration) => (parse_duration(duration)? / (24 * 60 * 60)) as i64,
+        None => def::EXPIRY_WARNING_DAYS,
+    };
+    for (due, path) in due_entries(days)? {
+        println!(
+            "{}  {}  ({})",
+            expiry::format_date(due),
+            path,
+            expiry::describe(due)
+        );
+    }
+    Ok(())
+}
+
+/// Due date and path of all entries which are due within `days` days, sorted by the due date.
+pub fn due_entries(days: i64) -> Result<Vec<(NaiveDate, String)>> {
+    let mut due: Vec<(NaiveDate, String)> = get_searchable_records()?
+        .into_iter()
+        .filter_map(|r| expiry::record_due_date(&r).map(|d| (d, r.path)))
+        .filter(|(d, _)| expiry::is_due(*d, days))
+        .collect();
+    due.sort();
+    Ok(due)
+}
@@ -0,0 +1,213 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use regex::{Regex, RegexBuilder};
+use uuid::Uuid;
+
+use crate::errors::Result;
+use crate::pass::entry::Entry;
+use crate::pass::index::{self, IndexRecord, Transaction};
+use crate::pass::store;
+use crate::Loading;
+
+/// Fields of every record, which can be searched besides the fields of the entries.
+const RECORD_FIELDS: [&str; 8] = [
+    "path", "user", "username", "url", "tag", "tags", "type", "uuid",
+];
+
+/// Search term, matching a single field or any field of the entry.
+pub struct Term {
+    field: Option<String>,
+    pattern: Regex,
+}
+
+impl Term {
+    /// Parse a term like `user:alice`, or a pattern without a field. The prefix is only used as
+    /// a field if it names a field of the index or of one of the `records`, such that a term like
+    /// `https://` searches all fields. Patterns are case insensitive regular expressions.
+    pub fn parse(term: &str, records: &[IndexRecord]) -> Result<Self> {
+        let is_field = |field: &str| {
+            RECORD_FIELDS.contains(&field)
+                || records
+                    .iter()
+                    .filter_map(|r| r.fields.as_ref())
+                    .any(|f| f.contains_key(field))
+        };
+        let (field, pattern) = match term.split_once(':') {
+            Some((field, pattern)) if is_field(&field.to_lowercase()) => {
+                (Some(field.to_lowercase()), pattern)
+            }
+            _ => (None, term),
+        };
+        Ok(Self {
+            field,
+            pattern: RegexBuilder::new(pattern).case_insensitive(true).build()?,
+        })
+    }
+
+    pub fn matches(&self, record: &IndexRecord) -> bool {
+        values(record, self.field.as_deref())
+            .iter()
+            .any(|v| self.pattern.is_match(v))
+    }
+}
+
+/// Values of `field` in the record, or all values if no field is given. The aliases of an entry
+/// are searched like its path.
+fn values(record: &IndexRecord, field: Option<&str>) -> Vec<String> {
+    let fields = record.fields.as_ref();
+    let mut values = Vec::new();
+    let all = field.is_none();
+    let field = field.unwrap_or_default();
+    if all || field == "path" {
+        values.extend(record.paths().map(str::to_string));
+    }
+    if all || field == "user" || field == "username" {
+        values.extend(record.username.clone());
+    }
+    if all || field == "url" {
+        values.extend(record.url.clone());
+    }
+    if all || field == "tag" || field == "tags" {
+        values.extend(record.tags.iter().cloned());
+    }
+    if all || field == "type" {
+        values.extend(record.entry_type.clone());
+    }
+    if field == "uuid" {
+        values.push(record.uuid.to_string());
+    }
+    match all {
+        true => values.extend(fields.into_iter().flat_map(|f| f.values().cloned())),
+        false => values.extend(fields.and_then(|f| f.get(field)).cloned()),
+    }
+    values
+}
+
+/// All lines of the record, as they appear in the entry.
+fn lines(record: &IndexRecord) -> Vec<String> {
+    let mut lines: Vec<String> = record.paths().map(|p| format!("path: {}", p)).collect();
+    lines.extend(record.username.iter().map(|u| format!("user: {}", u)));
+    lines.extend(record.url.iter().map(|u| format!("url: {}", u)));
+    // every line of a note is searched on its own
+    lines.extend(
+        record
+            .fields
+            .iter()
+            .flatten()
+            .flat_map(|(k, v)| v.lines().map(move |l| format!("{}: {}", k, l))),
+    );
+    lines
+}
+
+/// Print the path and uuid of all entries matching all `terms`, e.g., `user:alice github`.
+pub fn find(terms: &[&str]) -> Result<()> {
+    print_matches(find_matches(terms)?);
+    Ok(())
+}
+
+/// Print the path and uuid of all entries with a line matching `pattern`, e.g.,
+/// `^email: .*@corp`. The password is never searched.
+pub fn grep(pattern: &str) -> Result<()> {
+    print_matches(grep_matches(pattern)?);
+    Ok(())
+}
+
+/// Path and uuid of all entries matching all `terms`, sorted by the path.
+pub fn find_matches(terms: &[&str]) -> Result<Vec<(String, Uuid)>> {
+    let records = get_searchable_records()?;
+    let terms = terms
+        .iter()
+        .map(|t| Term::parse(t, &records))
+        .collect::<Result<Vec<_>>>()?;
+    matches(records, |record| terms.iter().all(|t| t.matches(record)))
+}
+
+/// Path and uuid of all entries with a line matching `pattern`, sorted by the path.
+pub fn grep_matches(pattern: &str) -> Result<Vec<(String, Uuid)>> {
+    let pattern = RegexBuilder::new(pattern).case_insensitive(true).build()?;
+    matches(get_searchable_records()?, |record| {
+        lines(record).iter().any(|l| pattern.is_match(l))
+    })
+}
+
+fn matches(
+    records: Vec<IndexRecord>,
+    filter: impl Fn(&IndexRecord) -> bool,
+) -> Result<Vec<(String, Uuid)>> {
+    let mut matches: Vec<(String, Uuid)> = records
+        .into_iter()
+        .filter(|r| filter(r))
+        .map(|r| (r.path, r.uuid))
+        .collect();
+    matches.sort();
+    Ok(matches)
+}
+
+fn print_matches(matches: Vec<(String, Uuid)>) {
+    for (path, uuid) in matches {
+        println!("{}  {}", path, uuid);
+    }
+}
+
+/// Records of all entries the user can search. If multiple stores are configured, the paths are
+/// prefixed with the name of the store.
+pub fn get_searchable_records() -> Result<Vec<IndexRecord>> {
+    match store::is_combined() {
+        true => store::map_stores(|store| {
+            Ok(searchable_records()?
+                .into_iter()
+                .map(|r| IndexRecord {
+                    path: format!("{}/{}", store.name, r.path),
+                    aliases: r
+                        .aliases
+                        .iter()
+                        .map(|a| format!("{}/{}", store.name, a))
+                        .collect(),
+                    ..r
+                })
+                .collect())
+        }),
+        false => searchable_records(),
+    }
+}
+
+/// Records of the current store. The metadata of entries which was not collected yet is read
+/// from the entries once, and then stored in the index.
+fn searchable_records() -> Result<Vec<IndexRecord>> {
+    let records = index::get_records()?;
+    if records.iter().all(|r| r.fields.is_some()) {
+        return Ok(records);
+    }
+
+    let loading = Loading::new("Collecting search metadata...")?;
+    let mut tx = Transaction::begin("Collect search metadata.")?;
+    let missing: Vec<Uuid> = tx
+        .records()
+        .iter()
+        .filter(|r| r.fields.is_none())
+        .map(|r| r.uuid)
+        .collect();
+    for id in missing {
+        match Entry::get(id) {
+            Ok(entry) => tx.update_metadata(id, |r| entry.update_record(r)),
+            Err(e) => eprintln!("[Warning] cannot read entry {}: {}", id, e),
+        }
+    }
+    tx.commit()?;
+    loading.done()?;
+    index::get_records()
+}
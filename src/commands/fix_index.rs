@@ -15,36 +15,112 @@
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
 use std::collections::HashMap;
-use std::fs;
 
-use dirs::home_dir;
 use uuid::Uuid;
 
 use crate::commands::utils::{confirm, gen_path_interactive, two_options};
 use crate::config::CFG;
 use crate::def;
 use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
 use crate::pass::entry::Entry;
 use crate::pass::index;
 
+/// Inconsistency between an entry and the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The path stored in the entry differs from the one stored in the index.
+    PathMismatch { index_path: String },
+    /// The entry is not present in the index, but it knows its path.
+    NotIndexed,
+    /// The entry is not present in the index, and it has no path information.
+    Orphaned,
+}
+
 pub fn fix_index() -> Result<()> {
+    for (path, first, second) in index::get_conflicts()? {
+        println!("\n{}", Error::PathConflict(path, first, second));
+        println!("Move one of the entries with `rpass mv --uuid`, or remove the alias with `rpass alias rm`.");
+    }
+
+    for (mut entry, problem) in check_index()? {
+        match problem {
+            None => println!("Entry at {} is correct!", entry.path.as_ref().unwrap()),
+            Some(Problem::PathMismatch { index_path }) => {
+                println!("\nPath in entry and in index does not match!\n{}", entry);
+                println!("1: Path in index: {}", index_path);
+                match entry.path.clone() {
+                    Some(entry_path) => {
+                        println!("2: Path in entry: {}", entry_path);
+                        print!("Choose path from: ");
+                        if two_options("index", "entry") {
+                            // use path from index
+                            entry.change_path_keep_index(index_path)?;
+                        } else {
+                            // use path from entry
+                            index::mv(entry.uuid, entry_path)?;
+                        }
+                    }
+                    None => {
+                        println!("2: Entry has no path information, using the path in index");
+                        entry.change_path_keep_index(index_path)?;
+                    }
+                }
+            }
+            Some(Problem::NotIndexed) => {
+                // generate index entry to the stored path
+                let path = entry.path.clone().unwrap();
+                println!("\nEntry is not present in the index!\n{}", entry);
+                if confirm(format!("Create index at {}", path), false) {
+                    index::insert(entry.uuid, &path)?;
+                }
+            }
+            Some(Problem::Orphaned) => {
+                // no path can be found
+                println!(
+                    "\nEntry is not present in the index and has no path information!\n{}",
+                    entry
+                );
+                if confirm("Create index and move entry to new location?", false) {
+                    match gen_path_interactive() {
+                        Ok(path) => {
+                            println!("Move entry to {}", path);
+                            entry.change_path(path)?;
+                        }
+                        _ => println!("Skipped!"),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read all entries in the managed folder and compare them with the index. For each entry, the
+/// problem is returned, or `None` if the entry is consistent with the index. Entries which cannot
+/// be read are reported and skipped.
+pub fn check_index() -> Result<Vec<(Entry, Option<Problem>)>> {
     let index_file = index::get_index()?;
     let path_lookup = index::to_hashmap(&index_file);
 
-    let mut uuid_folder = home_dir().unwrap();
-    uuid_folder.push(def::ROOT_FOLDER);
-    uuid_folder.push(CFG.main.uuid_folder);
-
-    if !uuid_folder.is_dir() {
+    let backend = get_backend();
+    if !backend.root().join(CFG.main.uuid_folder).is_dir() {
         return Err(Error::ManagedFolderNotFound);
     }
 
-    for key_file in fs::read_dir(uuid_folder)? {
-        let key_file = key_file?;
-        let key_path = key_file.path();
-        let key_name = key_file.file_name().into_string().unwrap();
+    let mut result = Vec::new();
+    for name in backend.list(CFG.main.uuid_folder)? {
+        let key_name = &name[CFG.main.uuid_folder.len() + 1..];
+
+        if key_name.starts_with(&format!("{}/", def::ATTACHMENTS_FOLDER))
+            || key_name.starts_with(&format!("{}/", def::TRASH_FOLDER))
+        {
+            // attachments and trashed entries are stored in their own folders
+            continue;
+        }
 
-        if key_path.is_dir() {
+        if key_name.contains('/') {
             println!(
                 "[Warning] uuids folder should not contain any folders: {}",
                 key_name
@@ -52,19 +128,12 @@ pub fn fix_index() -> Result<()> {
             continue;
         }
 
-        let name_parts: Vec<&str> = key_name.split('.').collect();
-
-        if name_parts.len() != 2 || name_parts[1] != def::ENTRY_EXTENSION {
-            println!("[Warning] unrecognized file: {}", key_name);
-            continue;
-        }
-
-        if key_name == CFG.main.index_file {
-            // skip index cile
+        if key_name == CFG.main.index_entry {
+            // skip index file
             continue;
         }
 
-        let entry_id = match Uuid::parse_str(name_parts[0]) {
+        let entry_id = match Uuid::parse_str(key_name) {
             Ok(x) => x,
             Err(_) => {
                 println!("[Warning] invalid uuid: {}", key_name);
@@ -72,68 +141,29 @@ pub fn fix_index() -> Result<()> {
             }
         };
 
-        check_fix_entry(entry_id, &path_lookup)?;
+        // a broken entry must not keep the others from being checked
+        let entry = match Entry::get(entry_id) {
+            Ok(entry) => entry,
+            Err(e) => {
+                println!("[Warning] cannot read entry {}: {}", entry_id, e);
+                continue;
+            }
+        };
+        let problem = check_entry(&entry, &path_lookup);
+        result.push((entry, problem));
     }
 
-    Ok(())
+    Ok(result)
 }
 
-fn check_fix_entry(entry_id: Uuid, path_lookup: &HashMap<Uuid, &str>) -> Result<()> {
-    let mut entry = Entry::get(entry_id)?;
-
-    match path_lookup.get(&entry.uuid) {
-        Some(stored_path) => {
-            // uuid is found in index file
-            match stored_path == entry.path.as_ref().unwrap() {
-                true => {
-                    println!("Entry at {} is correct!", stored_path);
-                } // nothing to do
-                false => {
-                    // different paths stored
-                    println!("\nPath in entry and in index does not match!\n{}", entry);
-                    println!("1: Path in index: {}", stored_path);
-                    println!("2: Path in entry: {}", entry.path.as_ref().unwrap());
-                    print!("Choose path from: ");
-                    if two_options("index", "entry") {
-                        // use path from index
-                        entry.change_path_keep_index(stored_path.to_string())?;
-                    } else {
-                        // use path from entry
-                        index::mv(entry.uuid, entry.path.unwrap())?;
-                    }
-                }
-            }
-        }
-        None => {
-            // uuid is not found in the index file
-            // check if entry has a path stored
-            match entry.path.clone() {
-                Some(path) => {
-                    // generate index entry to the stored path
-                    println!("\nEntry is not present in the index!\n{}", entry);
-                    if confirm(format!("Create index at {}", path), false) {
-                        index::insert(entry.uuid, &path)?;
-                    }
-                }
-                None => {
-                    // no path can be found
-                    println!(
-                        "\nEntry is not present in the index and has no path information!\n{}",
-                        entry
-                    );
-                    if confirm("Create index and move entry to new location?", false) {
-                        match gen_path_interactive() {
-                            Ok(path) => {
-                                println!("Move entry to {}", path);
-                                entry.change_path(path)?;
-                            }
-                            _ => println!("Skipped!"),
-                        }
-                    }
-                }
-            }
-        }
+/// Compare the path of `entry` with the one stored in the index.
+pub fn check_entry(entry: &Entry, path_lookup: &HashMap<Uuid, &str>) -> Option<Problem> {
+    match (path_lookup.get(&entry.uuid), entry.path.as_ref()) {
+        (Some(stored_path), Some(path)) if stored_path == path => None,
+        (Some(stored_path), _) => Some(Problem::PathMismatch {
+            index_path: stored_path.to_string(),
+        }),
+        (None, Some(_)) => Some(Problem::NotIndexed),
+        (None, None) => Some(Problem::Orphaned),
     }
-
-    Ok(())
 }
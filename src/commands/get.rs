@@ -15,7 +15,7 @@
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
 use crate::commands::edit;
-use crate::commands::utils::{choose_entry, copy_to_clipboard};
+use crate::commands::utils::{choose_entry, copy_to_clipboard, notify_due, type_to_x11};
 use crate::config::CFG;
 use crate::def;
 use crate::errors::Result;
@@ -29,9 +29,11 @@ pub fn get(
     use_rofi: bool,
     only_password: bool,
     only_username: bool,
+    reveal: bool,
 ) -> Result<()> {
     let mut entry = choose_entry(path, id, use_rofi)?;
     if use_rofi {
+        notify_due(&entry);
         get_rofi_menu(&mut entry)
     } else if only_password {
         println!("{}", entry.password);
@@ -47,6 +49,7 @@ pub fn get(
         }
     } else {
         entry.hidden = false;
+        entry.mask_secrets = !reveal;
         println!("{:?}", entry);
         Ok(())
     }
@@ -61,6 +64,10 @@ pub(super) fn get_rofi_menu(entry: &mut Entry) -> Result<()> {
         } else {
             lines.push(def::format_button(def::DISPLAY_BTN_HIDE_PWD));
         }
+        if entry.otp.is_some() {
+            lines.push(def::format_button(def::DISPLAY_BTN_COPY_OTP));
+            lines.push(def::format_button(def::DISPLAY_BTN_TYPE_OTP));
+        }
         lines.push(def::format_button(def::DISPLAY_BTN_EDIT_ENTRY));
         lines.push(def::format_small(def::DISPLAY_BTN_MAIN_MENU));
 
@@ -88,15 +95,25 @@ pub(super) fn get_rofi_menu(entry: &mut Entry) -> Result<()> {
                 GetMenuAction::CopyUrl => {
                     copy_to_clipboard(entry.url.clone().unwrap(), "URL", Some(5000))?
                 }
+                GetMenuAction::CopyOtp => copy_to_clipboard(entry.otp_code()?, "OTP", Some(5000))?,
+                GetMenuAction::TypeOtp => {
+                    type_to_x11(entry.otp_code()?)?;
+                    break;
+                }
                 GetMenuAction::CopyOther(s) => {
-                    copy_to_clipboard(prepare_raw_line(&s).to_string(), "Custom entry", Some(5000))?
+                    let value = match entry.field_at_line(&s) {
+                        Some(i) => entry.fields[i].value.clone(),
+                        None => prepare_raw_line(&s).to_string(),
+                    };
+                    copy_to_clipboard(value, "Custom entry", Some(5000))?
                 }
                 GetMenuAction::ShowPassword => entry.hidden = false,
                 GetMenuAction::HidePassword => entry.hidden = true,
                 GetMenuAction::EditEntry => {
-                    edit(None, Some(format!("{}", entry.uuid).as_str()), true)?;
+                    edit(None, Some(format!("{}", entry.uuid).as_str()), true, false)?;
                     break;
                 }
+                GetMenuAction::DoNothing => {}
                 GetMenuAction::Exit => break,
             },
             Err(_) => break,
@@ -111,10 +128,13 @@ enum GetMenuAction {
     CopyUsername,
     CopyPassword,
     CopyUrl,
+    CopyOtp,
+    TypeOtp,
     CopyOther(String),
     ShowPassword,
     HidePassword,
     EditEntry,
+    DoNothing,
     Exit,
 }
 
@@ -123,6 +143,10 @@ fn get_menu_action(s: String) -> GetMenuAction {
         GetMenuAction::ShowPassword
     } else if s == def::DISPLAY_BTN_HIDE_PWD {
         GetMenuAction::HidePassword
+    } else if s == def::DISPLAY_BTN_COPY_OTP || s.starts_with(def::DISPLAY_OTP) {
+        GetMenuAction::CopyOtp
+    } else if s == def::DISPLAY_BTN_TYPE_OTP {
+        GetMenuAction::TypeOtp
     } else if s == def::DISPLAY_BTN_EDIT_ENTRY {
         GetMenuAction::EditEntry
     } else if s.starts_with(def::DISPLAY_PATH) {
@@ -135,6 +159,8 @@ fn get_menu_action(s: String) -> GetMenuAction {
         GetMenuAction::CopyPassword
     } else if s.starts_with(def::DISPLAY_URL) {
         GetMenuAction::CopyUrl
+    } else if s.starts_with(def::DISPLAY_DUE) || s.starts_with(def::DISPLAY_STRENGTH) {
+        GetMenuAction::DoNothing
     } else if !s.is_empty() && s != def::DISPLAY_BTN_MAIN_MENU {
         GetMenuAction::CopyOther(s)
     } else {
@@ -0,0 +1,91 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use crate::commands::utils::choose_entry;
+use crate::errors::{Error, Result};
+use crate::pass::entry::Entry;
+use crate::pass::history;
+use crate::pass::index::Transaction;
+
+/// Print all versions of the entry, starting with the newest one, together with the values
+/// changed by each version. Secrets are masked unless `reveal` is set.
+pub fn history(path: Option<&str>, id: Option<&str>, reveal: bool) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    let mut previous: Option<Entry> = None;
+    let mut output: Vec<String> = Vec::new();
+    for version in history::versions(entry.uuid)? {
+        let mut text = format!(
+            "Version {}  {}  {}\n",
+            version.number, version.date, version.message
+        );
+        match history::entry(entry.uuid, &version) {
+            Ok(mut current) => {
+                current.hidden = !reveal;
+                for (name, old, new) in history::changes(previous.as_ref(), &current) {
+                    text.push_str(&match (old, new) {
+                        (Some(old), Some(new)) => format!("    ~ {}: {} -> {}\n", name, old, new),
+                        (None, Some(new)) => format!("    + {}: {}\n", name, new),
+                        (Some(old), None) => format!("    - {}: {}\n", name, old),
+                        (None, None) => continue,
+                    });
+                }
+                previous = Some(current);
+            }
+            Err(e) => text.push_str(&format!("    could not be read: {}\n", e)),
+        }
+        output.push(text);
+    }
+    for text in output.iter().rev() {
+        print!("{}", text);
+    }
+    Ok(())
+}
+
+/// Replace the entry by one of its previous versions. The entry keeps its current path, such
+/// that it stays consistent with the index.
+pub fn restore(path: Option<&str>, id: Option<&str>, version: usize) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    let versions = history::versions(entry.uuid)?;
+    let version = versions
+        .iter()
+        .find(|v| v.number == version)
+        .ok_or(Error::InvalidInput("Unknown version"))?;
+    let mut restored = history::entry(entry.uuid, version)?;
+    restored.uuid = entry.uuid;
+    restored.path = entry.path.clone();
+    // the restored password keeps its age
+    let password_since = match restored.password == entry.password {
+        true => None,
+        false => Some(history::password_since(entry.uuid, &versions, version)?),
+    };
+
+    let mut tx = Transaction::begin(format!(
+        "Restore version {} of {}.",
+        version.number, entry.uuid
+    ))?;
+    restored.write_in(&mut tx);
+    if let Some(since) = password_since {
+        tx.update_metadata(entry.uuid, |r| r.password_changed = Some(since));
+    }
+    tx.commit()?;
+    println!(
+        "Restored version {} of {} ({})",
+        version.number,
+        entry.path.unwrap_or_default(),
+        version.date
+    );
+    Ok(())
+}
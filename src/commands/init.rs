@@ -14,78 +14,81 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
-use std::fs;
+use std::collections::{BTreeSet, HashSet};
 use std::io;
 use std::io::prelude::*;
-use std::path::Path;
 
-use dirs::home_dir;
 use text_io::read;
 use uuid::Uuid;
 
 use crate::commands::utils;
 use crate::config::CFG;
-use crate::def;
 use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
 use crate::pass::entry::Entry;
-use crate::pass::index;
+use crate::pass::index::{self, IndexRecord, Transaction};
 
 pub fn init(force: bool) -> Result<()> {
-    let mut root_folder = home_dir().unwrap();
-    root_folder.push(def::ROOT_FOLDER);
-    let root_folder_len: usize = root_folder.as_path().to_str().unwrap().len() + 1;
+    init_with(force, |count| {
+        utils::confirm(
+            format!(
+                "\nGenerating index for {} keys! Do you wish to continue?",
+                count
+            ),
+            false,
+        )
+    })
+}
+
+/// Index all entries which are not indexed yet. `confirm` is asked with the number of entries
+/// before anything is changed.
+pub fn init_with(force: bool, confirm: impl FnOnce(usize) -> bool) -> Result<()> {
+    let backend = get_backend();
 
     // check if the password store folder exists
-    if !root_folder.is_dir() {
+    if !backend.root().is_dir() {
         // pass is not yet initialized!
         println!("[Error] Pass is not yet initialized! Initialize it by running \"pass init\"!");
         return Err(Error::Other("pass is not yet initialized".to_string()));
     }
 
     // load the index if already exists
-    let mut index_list: Vec<(Uuid, String)> = index::get_index().unwrap_or_else(|_| Vec::new());
-
-    // from the root folder, recursively walk all files and ask for the indices.
-    let to_index = walk_recursively(root_folder.as_ref(), force)?;
+    let records: Vec<IndexRecord> = index::get_records().unwrap_or_else(|_| Vec::new());
+
+    // from the root folder, recursively walk all files that are not yet indexed and ask for the
+    // indices.
+    let indexed: HashSet<&str> = records.iter().map(|r| r.path.as_str()).collect();
+    let names: Vec<String> = backend
+        .list("")?
+        .into_iter()
+        .filter(|name| !indexed.contains(name.as_str()))
+        .collect();
+    let to_index = walk_recursively("", &names, force)?;
 
     if to_index.is_empty() {
         // no keys to index! check if the index file exists
-        if index_list.is_empty() {
+        if records.is_empty() {
             println!("Generating an empty index file!");
-            index::write(&index_list)?;
+            index::write_records(&records)?;
         } else {
             println!("Nothing to do!");
         }
         return Ok(());
     }
 
-    if !utils::confirm(
-        format!(
-            "\nGenerating index for {} keys! Do you wish to continue?",
-            to_index.len()
-        ),
-        false,
-    ) { return Err(Error::Interrupted) }
-
-    for key_filename in to_index {
-        let key_name = key_filename[root_folder_len..]
-            .trim_end_matches(".gpg")
-            .to_string();
+    if !confirm(to_index.len()) {
+        return Err(Error::Interrupted);
+    }
+
+    let mut tx = Transaction::begin(format!("Add {} entries to the index.", to_index.len()))?;
+    for key_name in to_index {
         println!("Indexing {}", key_name);
 
         // get the entry
         let mut e: Entry = Entry::from_path(&key_name)?;
 
-        // check if the entry does already end with a newline
-        if !e.raw.ends_with('\n') {
-            e.raw.push('\n');
-        }
-
         // check if the path is already set correctly
-        if match &e.path {
-            Some(p) => key_name == *p,
-            None => false,
-        } {
+        if e.path.as_ref() != Some(&key_name) {
             e.path = Some(key_name.clone());
         }
 
@@ -94,59 +97,58 @@ pub fn init(force: bool) -> Result<()> {
             e.uuid = Uuid::new_v4();
         }
 
-        // write the new entry
-        e.write()?;
-
-        // add the entry to the index file
-        index_list.push((e.uuid, key_name));
+        // write the new entry, and add it to the index file
+        e.create_in(&mut tx)?;
     }
 
-    // update the index list
-    index::write(&index_list)
+    // write all entries and the index at once
+    tx.commit()
 }
 
-fn walk_recursively(dir: &Path, force: bool) -> Result<Vec<String>> {
+fn walk_recursively(dir: &str, names: &[String], force: bool) -> Result<Vec<String>> {
+    let prefix = match dir {
+        "" => String::new(),
+        _ => format!("{}/", dir),
+    };
+
+    // collect all direct children of dir, and whether they are a directory
+    let children: BTreeSet<(&str, bool)> = names
+        .iter()
+        .filter_map(|name| name.strip_prefix(prefix.as_str()))
+        .map(|rest| match rest.split_once('/') {
+            Some((child, _)) => (child, true),
+            None => (rest, false),
+        })
+        .collect();
+
     let mut res: Vec<String> = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.is_dir() {
-                // handle directory
-
-                // skip git folder
-                if path.ends_with(def::GIT_FOLDER) {
-                    continue;
-                }
-                if path.ends_with(CFG.main.uuid_folder) {
-                    continue;
-                }
-                if path.ends_with(CFG.main.sync_folder) {
-                    continue;
-                }
-
-                // ask to change to add all, to ask again or to skip the directory
-                let force_child = match force {
-                    true => true,
-                    false => match skip_ask_all(&path) {
-                        Some(true) => true,
-                        Some(false) => false,
-                        None => continue,
-                    },
-                };
-
-                // call calk_recursive recursively
-                res.append(&mut walk_recursively(&path, force_child)?);
-            } else {
-                // handle files
-                if let Some(ext) = path.extension() {
-                    if ext == "gpg"
-                        && (force || utils::confirm(format!("Index {}:", path.display()), false))
-                    {
-                        res.push(path.to_str().unwrap().to_string());
-                    }
-                }
+    for (child, is_dir) in children {
+        let path = format!("{}{}", prefix, child);
+
+        if is_dir {
+            // handle directory
+
+            // skip the managed and the sync folder
+            if child == CFG.main.uuid_folder || child == CFG.main.sync_folder {
+                continue;
+            }
+
+            // ask to change to add all, to ask again or to skip the directory
+            let force_child = match force {
+                true => true,
+                false => match skip_ask_all(&path) {
+                    Some(true) => true,
+                    Some(false) => false,
+                    None => continue,
+                },
+            };
+
+            // call calk_recursive recursively
+            res.append(&mut walk_recursively(&path, names, force_child)?);
+        } else {
+            // handle files
+            if force || utils::confirm(format!("Index {}:", path), false) {
+                res.push(path);
             }
         }
     }
@@ -154,8 +156,8 @@ fn walk_recursively(dir: &Path, force: bool) -> Result<Vec<String>> {
     Ok(res)
 }
 
-fn skip_ask_all(path: &Path) -> Option<bool> {
-    print!("Index {}: [f]orce, [s]kip or [A]sk: ", path.display());
+fn skip_ask_all(path: &str) -> Option<bool> {
+    print!("Index {}: [f]orce, [s]kip or [A]sk: ", path);
     io::stdout().flush().expect("Could not flush stdout");
     let answer: String = read!("{}\n");
     if answer == "f" || answer == "F" {
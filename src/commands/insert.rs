@@ -15,19 +15,49 @@
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
 use crate::errors::{Error, Result};
-use fake::{faker, Fake};
 
 use crate::commands::{get, utils};
+use crate::config::FieldFormat;
+use crate::def;
+use crate::pass::backend::get_backend;
 use crate::pass::entry::Entry;
+use crate::pass::expiry;
+use crate::pass::generator::{Policy, DEFAULT_POLICY};
+use crate::pass::strength::Strength;
+use crate::pass::template::{Template, TemplateField, DEFAULT_TYPE};
 
+/// Insert a new entry of the type `entry_type`. Values of custom fields can be given as
+/// `NAME=VALUE`, all other values of the type are asked for. A generated password follows the
+/// policy `policy`, which is then stored in the entry, or otherwise the policy of its folder.
+#[allow(clippy::too_many_arguments)]
 pub fn insert(
     path: Option<&str>,
     username: Option<&str>,
     password: Option<&str>,
     url: Option<&str>,
-    generate: Option<usize>,
+    generate: bool,
+    policy: Option<&str>,
     use_rofi: bool,
+    entry_type: Option<&str>,
+    fields: &[&str],
 ) -> Result<()> {
+    let template = Template::get(entry_type.unwrap_or(DEFAULT_TYPE))?;
+    let mut given = fields
+        .iter()
+        .map(|f| match f.split_once('=') {
+            Some((name, value)) => Ok((name.trim(), value)),
+            None => Err(Error::InvalidInput("Fields must be given as NAME=VALUE")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (name, value) in given.iter() {
+        check_policy(name, value)?;
+    }
+    if let Some(policy) = policy {
+        Policy::get(policy)?.validate()?;
+        given.retain(|(name, _)| !name.eq_ignore_ascii_case(def::POLICY_FIELD));
+        given.push((def::POLICY_FIELD, policy));
+    }
+
     let path = match path {
         Some(s) => s.to_string(),
         None => match use_rofi {
@@ -41,42 +71,57 @@ pub fn insert(
 
     let username = match username {
         Some(s) => Some(s.to_string()),
-        None => utils::question("username", use_rofi)?,
+        None if template.username => utils::question("username", use_rofi)?,
+        None => None,
     };
 
-    let password = match generate {
-        Some(x) => generate_password(x),
-        None => match password {
-            Some(s) => s.to_string(),
-            None => {
-                if use_rofi {
-                    match utils::question("password", use_rofi)? {
-                        Some(pw) => pw,
-                        None => return Err(Error::InvalidInput("Password is required!")),
-                    }
-                } else {
-                    let mut passwd: String;
-                    loop {
-                        passwd = rpassword::prompt_password_stdout("Enter a password: ")?;
-                        let rp = rpassword::prompt_password_stdout("Repeat the password: ")?;
-                        if passwd == rp {
-                            break;
-                        } else {
-                            println!("The two passwords don't match. try again!");
-                        }
-                    }
-                    passwd
-                }
+    let password = match generate || policy.is_some() {
+        true => {
+            let given_value = |key: &str| {
+                given
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                    .map(|(_, value)| *value)
+            };
+            let policy = Policy::select(given_value(def::POLICY_FIELD), &path)?;
+            match given_value(def::PWRULE_FIELD) {
+                Some(rule) => policy.with_rule(rule)?.generate()?,
+                None => policy.generate()?,
             }
+        }
+        false => match password {
+            Some(s) => s.to_string(),
+            // only passwords which could be generated are checked, PINs are always weak
+            None => utils::ask_password(&template.password, template.generate, use_rofi)?,
         },
     };
 
     let url = match url {
         Some(s) => Some(s.to_string()),
-        None => utils::question("url", use_rofi)?,
+        None if template.url => utils::question("url", use_rofi)?,
+        None => None,
     };
 
-    let e = Entry::new(username, password, url, path);
+    let mut e = Entry::new(username, password, url, path);
+    if template.name != DEFAULT_TYPE {
+        e.push_field(def::TYPE_FIELD, &template.name);
+    }
+    for field in template.fields.iter() {
+        let value = match given
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(&field.name))
+        {
+            Some(i) => check_field(field, given.remove(i).1)?,
+            None => ask_field(field, use_rofi)?,
+        };
+        if let Some(value) = value {
+            e.push_field(&field.name, &value);
+        }
+    }
+    for (name, value) in given {
+        check_expiry(name, value)?;
+        e.push_field(name, value);
+    }
 
     e.create()?;
 
@@ -87,15 +132,68 @@ pub fn insert(
             use_rofi,
             false,
             false,
+            false,
         )
     } else {
         println!("Created {}", e);
+        println!("Password strength: {}", Strength::of(&e.password));
+        Ok(())
+    }
+}
+
+/// Validate the value of a field given by the user. Empty values of optional fields are skipped.
+fn check_field(field: &TemplateField, value: &str) -> Result<Option<String>> {
+    match field.format.validate(value) {
+        Ok(value) if value.is_empty() && field.optional => Ok(None),
+        Ok(value) if value.is_empty() => {
+            Err(Error::InvalidField(field.name.clone(), "is required"))
+        }
+        Ok(value) => Ok(Some(value)),
+        Err(e) => Err(Error::InvalidField(field.name.clone(), e)),
+    }
+}
+
+/// Validate the fields which control when the password expires.
+fn check_expiry(name: &str, value: &str) -> Result<()> {
+    let key = name.to_lowercase();
+    if key == def::EXPIRES_FIELD && expiry::parse_date(value).is_err() {
+        Err(Error::InvalidField(
+            name.to_string(),
+            "must be a date like 2024-12-31",
+        ))
+    } else if key == def::ROTATE_FIELD && expiry::parse_duration(value).is_err() {
+        Err(Error::InvalidField(
+            name.to_string(),
+            "must be a duration like 90d",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Validate the policy stored in the `policy:` field, and the rules in the `pwrule:` field.
+fn check_policy(name: &str, value: &str) -> Result<()> {
+    if name.eq_ignore_ascii_case(def::POLICY_FIELD) {
+        Policy::get(value)?.validate()
+    } else if name.eq_ignore_ascii_case(def::PWRULE_FIELD) {
+        Policy::get(DEFAULT_POLICY)?.with_rule(value).map(|_| ())
+    } else {
         Ok(())
     }
 }
 
-fn generate_password(x: usize) -> String {
-    let pw: String = faker::internet::en::Password(x..x + 1).fake();
-    println!("Password: {}", pw);
-    pw
+/// Ask for the value of a field until it is valid. Notes are entered in the editor.
+fn ask_field(field: &TemplateField, use_rofi: bool) -> Result<Option<String>> {
+    if field.format == FieldFormat::Note {
+        let value = get_backend().edit_text("", use_rofi)?;
+        return check_field(field, &value);
+    }
+    let mut prompt = field.name.clone();
+    loop {
+        let answer = utils::question(&prompt, use_rofi)?.unwrap_or_default();
+        match check_field(field, &answer) {
+            Err(Error::InvalidField(name, e)) => prompt = format!("{} ({})", name, e),
+            result => return result,
+        }
+    }
 }
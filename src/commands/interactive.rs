@@ -54,15 +54,20 @@ pub fn interactive() -> Result<()> {
 
     // choose the entry
     let mut entry = utils::choose_entry(None, None, true)?;
+    utils::notify_due(&entry);
 
-    let lines: Vec<String> = vec![
+    let mut lines: Vec<String> = vec![
         def::format_button(def::DISPLAY_BTN_TYPE_BOTH),
         def::format_button(def::DISPLAY_BTN_TYPE_ONE_AT_A_TIME),
         def::format_button(def::DISPLAY_BTN_TYPE_PASSWORD),
         def::format_button(def::DISPLAY_BTN_TYPE_USERNAME),
-        def::format_small(def::DISPLAY_BTN_SHOW),
-        def::format_small(def::DISPLAY_BTN_EXIT),
     ];
+    if entry.otp.is_some() {
+        lines.push(def::format_button(def::DISPLAY_BTN_TYPE_OTP));
+        lines.push(def::format_button(def::DISPLAY_BTN_COPY_OTP));
+    }
+    lines.push(def::format_small(def::DISPLAY_BTN_SHOW));
+    lines.push(def::format_small(def::DISPLAY_BTN_EXIT));
 
     match Rofi::new(&lines)
         .prompt("What to do?")
@@ -82,7 +87,11 @@ enum CopyAction {
     Both,
     OneAtATime,
     Password,
+    /// Type the password, and then the otp in the next invocation
+    PasswordThenOtp,
     Username,
+    Otp,
+    CopyOtp,
     Show,
     Exit,
 }
@@ -93,7 +102,10 @@ impl fmt::Display for CopyAction {
             CopyAction::Both => write!(f, "Both"),
             CopyAction::OneAtATime => write!(f, "Username, then Password"),
             CopyAction::Password => write!(f, "Only Password"),
+            CopyAction::PasswordThenOtp => write!(f, "Password, then OTP"),
             CopyAction::Username => write!(f, "Only Username"),
+            CopyAction::Otp => write!(f, "Type OTP"),
+            CopyAction::CopyOtp => write!(f, "Copy OTP"),
             CopyAction::Show => write!(f, "Show"),
             CopyAction::Exit => write!(f, "Exit"),
         }
@@ -109,6 +121,10 @@ fn get_copy_action(s: String) -> CopyAction {
         CopyAction::Both
     } else if s == def::DISPLAY_BTN_TYPE_ONE_AT_A_TIME {
         CopyAction::OneAtATime
+    } else if s == def::DISPLAY_BTN_TYPE_OTP {
+        CopyAction::Otp
+    } else if s == def::DISPLAY_BTN_COPY_OTP {
+        CopyAction::CopyOtp
     } else if s == def::DISPLAY_BTN_SHOW {
         CopyAction::Show
     } else {
@@ -129,13 +145,26 @@ fn action_copy_entry(entry: &mut Entry, action: CopyAction) -> Result<()> {
             // first, enter the username
             utils::type_to_x11(entry.username.clone().unwrap_or_default())?;
             // then, write the uuid to the temporary file, to remember that we want to enter the
-            // password of that uuid next
+            // password of that uuid next, followed by the otp if the entry has one.
+            write_last_action(LastAction {
+                timestamp: SystemTime::now(),
+                uuid: entry.uuid,
+                action: match entry.otp.is_some() {
+                    true => CopyAction::PasswordThenOtp,
+                    false => CopyAction::Password,
+                },
+            })
+        }
+        CopyAction::PasswordThenOtp => {
+            utils::type_to_x11(entry.password.clone())?;
             write_last_action(LastAction {
                 timestamp: SystemTime::now(),
                 uuid: entry.uuid,
-                action: CopyAction::Password,
+                action: CopyAction::Otp,
             })
         }
+        CopyAction::Otp => utils::type_to_x11(entry.otp_code()?),
+        CopyAction::CopyOtp => utils::copy_to_clipboard(entry.otp_code()?, "OTP", Some(5000)),
         CopyAction::Show => super::get::get_rofi_menu(entry),
         CopyAction::Exit => Err(Error::Interrupted),
     }
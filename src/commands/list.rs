@@ -18,12 +18,13 @@ use ansi_term::Colour::Blue;
 use petgraph::graph::{Graph, NodeIndex};
 use petgraph::Direction::Outgoing;
 
+use crate::commands::utils::get_searchable_index;
 use crate::errors::Result;
 use crate::pass;
 
 pub fn list() -> Result<()> {
-    let mut index_list = pass::index::get_index()?;
-    index_list.sort_by(|a, b| b.1.to_lowercase().cmp(&a.1.to_lowercase()));
+    let mut index_list = get_searchable_index()?;
+    index_list.sort_by_key(|x| std::cmp::Reverse(x.1.to_lowercase()));
     let (graph, root) = pass::index::to_graph(&index_list);
     let mut open: Vec<TreeFmtOpen> = Vec::new();
     recursive_tree_print(&graph, root, &mut open);
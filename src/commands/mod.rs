@@ -14,28 +14,46 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
-mod bulk_rename;
+mod agent;
+pub mod alias;
+mod attach;
+pub mod audit;
+pub mod bulk_rename;
 mod delete;
 mod edit;
-mod fix_index;
+pub mod expiring;
+pub mod find;
+pub mod fix_index;
 mod get;
-mod init;
+mod history;
+pub mod init;
 mod insert;
 mod interactive;
 mod list;
 mod mv;
+mod otp;
 mod passwd;
 pub mod sync;
+mod textconv;
+pub mod trash;
 pub mod utils;
 
+pub use agent::{agent, lock};
+pub use attach::{attach, attachments, extract};
+pub use audit::audit;
 pub use bulk_rename::bulk_rename;
 pub use delete::delete;
 pub use edit::edit;
+pub use expiring::expiring;
+pub use find::{find, grep};
 pub use fix_index::fix_index;
 pub use get::get;
+pub use history::{history, restore};
 pub use init::init;
 pub use insert::insert;
 pub use interactive::interactive;
 pub use list::list;
 pub use mv::mv;
+pub use otp::otp;
 pub use passwd::passwd;
+pub use textconv::textconv;
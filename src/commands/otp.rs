@@ -0,0 +1,31 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use crate::commands::utils::{choose_entry, copy_to_clipboard};
+use crate::errors::Result;
+
+/// Print the current otp code of the entry, or copy it to the clipboard.
+pub fn otp(path: Option<&str>, id: Option<&str>, copy: bool) -> Result<()> {
+    let entry = choose_entry(path, id, false)?;
+    let code = entry.otp_code()?;
+    match copy {
+        true => copy_to_clipboard(code, "OTP", Some(5000)),
+        false => {
+            println!("{}", code);
+            Ok(())
+        }
+    }
+}
@@ -14,54 +14,42 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
-use fake::{faker, Fake};
-
-use crate::commands::utils::{choose_entry, question_rofi};
+use crate::commands::utils::{ask_password, choose_entry};
 use crate::errors::{Error, Result};
+use crate::pass::strength::Strength;
 
 pub fn passwd(
     path: Option<&str>,
     id: Option<&str>,
     new_passwd: Option<&str>,
-    generate: Option<usize>,
+    generate: bool,
+    policy: Option<&str>,
     use_rofi: bool,
 ) -> Result<()> {
-    let passwd = match generate {
-        Some(x) => Some(faker::internet::en::Password(x..x + 1).fake()),
-        None => new_passwd.map(|s| s.to_string()),
-    };
-
     let mut entry = choose_entry(path, id, use_rofi)?;
 
+    // an explicit policy is only used for this password, it is not stored in the entry
+    let passwd = match generate || policy.is_some() {
+        true => Some(entry.policy(policy)?.generate()?),
+        false => new_passwd.map(|s| s.to_string()),
+    };
+
     if !use_rofi {
         println!("Cange password of {}", entry);
     }
 
     let passwd = match passwd {
         Some(x) => x,
-        None => match use_rofi {
-            true => match question_rofi("password")? {
-                Some(pw) => pw,
-                None => return Err(Error::InvalidInput("Password cannot be empty")),
-            },
-            false => {
-                let mut passwd: String;
-                loop {
-                    passwd = rpassword::prompt_password_stdout("Enter a password: ")?;
-                    let rp = rpassword::prompt_password_stdout("Repeat the password: ")?;
-                    if passwd == rp {
-                        break;
-                    } else {
-                        println!("The two passwords don't match. try again!");
-                    }
-                }
-                passwd
-            }
-        },
+        None => ask_password("password", true, use_rofi)?,
     };
 
-    match passwd.len() {
-        0 => Err(Error::InvalidInput("Password cannot be empty!")),
-        _ => entry.change_password(passwd),
+    if passwd.is_empty() {
+        return Err(Error::InvalidInput("Password cannot be empty!"));
+    }
+    let strength = Strength::of(&passwd);
+    entry.change_password(passwd)?;
+    if !use_rofi {
+        println!("Password strength: {}", strength);
     }
+    Ok(())
 }
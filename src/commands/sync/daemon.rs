@@ -20,11 +20,10 @@ use std::sync::Arc;
 use std::{thread, time};
 
 use ctrlc;
-use dirs::home_dir;
 
 use crate::config::CFG;
-use crate::def;
 use crate::errors::Result;
+use crate::pass::backend::get_backend;
 
 pub fn daemon() -> Result<()> {
     // start a handler for ctrlc
@@ -49,8 +48,7 @@ pub fn daemon() -> Result<()> {
     thread::sleep(time::Duration::from_millis(1000));
 
     // get sync path
-    let mut sync_path = home_dir().unwrap();
-    sync_path.push(def::ROOT_FOLDER);
+    let mut sync_path = get_backend().root().to_path_buf();
     sync_path.push(CFG.main.sync_folder);
 
     // pull and push changes to local repository
@@ -17,19 +17,23 @@
 use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::io::prelude::*;
+use std::path::Path;
 use std::process::Command;
 
-use dirs::home_dir;
-
 use crate::commands::sync::update_sync_commit_file;
 use crate::config::CFG;
 use crate::def;
 use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
 use crate::pass::index;
 
+const RECIPIENTS_FILES: [&str; 2] = [".gpg-id", ".age-recipients"];
+
 pub fn init() -> Result<()> {
     // setup gitignore in main pass repo
     init_gitignore()?;
+    // make sure that git can show the diff of the entries in the main pass repo
+    init_gitattributes()?;
     // setup .sync repo
     init_snyc_folder()?;
     // copy all keys over
@@ -76,8 +80,7 @@ fn init_gitignore() -> Result<()> {
     // check if .gitignore is created and contains the line .sync
     let mut git_changes = false;
 
-    let mut gitignore_path = home_dir().unwrap();
-    gitignore_path.push(def::ROOT_FOLDER);
+    let mut gitignore_path = get_backend().root().to_path_buf();
     gitignore_path.push(".gitignore");
     if gitignore_path.is_file() {
         // .gitignore file already exists, check if the line .sync exists
@@ -119,17 +122,18 @@ fn init_gitignore() -> Result<()> {
     if git_changes {
         // commit the change of the .gitignore
         println!("committing changes!");
-        Command::new("pass")
-            .arg("git")
+        let root = get_backend().root().to_path_buf();
+        Command::new("git")
             .arg("add")
             .arg(".gitignore")
+            .current_dir(&root)
             .spawn()?
             .wait()?;
-        Command::new("pass")
-            .arg("git")
+        Command::new("git")
             .arg("commit")
             .arg("-m")
             .arg("added gitignore for sync")
+            .current_dir(&root)
             .spawn()?
             .wait()?;
     }
@@ -137,11 +141,80 @@ fn init_gitignore() -> Result<()> {
     Ok(())
 }
 
+/// Line in `.gitattributes` which assigns the diff driver to all entries.
+fn diff_attribute() -> String {
+    let ext = get_backend().extension().to_string();
+    format!("*.{} diff={}", ext, ext)
+}
+
+/// Configure the diff driver of the git repository at `repo` to decrypt all entries.
+fn init_diff_driver(repo: &Path) -> Result<()> {
+    let backend = get_backend();
+    let textconv = match backend.textconv() {
+        Some(textconv) => textconv,
+        None => return Ok(()),
+    };
+    let driver = format!("diff.{}", backend.extension());
+    for (key, value) in [("binary", "true"), ("textconv", textconv.as_str())].iter() {
+        Command::new("git")
+            .arg("config")
+            .arg(format!("{}.{}", driver, key))
+            .arg(value)
+            .current_dir(repo)
+            .output()?;
+    }
+    Ok(())
+}
+
+fn init_gitattributes() -> Result<()> {
+    let backend = get_backend();
+    if backend.textconv().is_none() {
+        return Ok(());
+    }
+    let root = backend.root().to_path_buf();
+    let attribute = diff_attribute();
+
+    // pass already sets up the diff driver for gpg files
+    let attributes_path = root.join(".gitattributes");
+    if attributes_path.is_file()
+        && fs::read_to_string(&attributes_path)?
+            .lines()
+            .any(|l| l.trim() == attribute)
+    {
+        return Ok(());
+    }
+
+    println!("Adding {} to .gitattributes!", attribute);
+    let mut attributes_file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&attributes_path)?;
+    attributes_file.write_all(attribute.as_bytes())?;
+    attributes_file.write_all(b"\n")?;
+    init_diff_driver(&root)?;
+
+    println!("committing changes!");
+    Command::new("git")
+        .arg("add")
+        .arg(".gitattributes")
+        .current_dir(&root)
+        .spawn()?
+        .wait()?;
+    Command::new("git")
+        .arg("commit")
+        .arg("-m")
+        .arg("added gitattributes for sync")
+        .current_dir(&root)
+        .spawn()?
+        .wait()?;
+
+    Ok(())
+}
+
 fn init_snyc_folder() -> Result<()> {
     // generate .sync folder
     println!("Generating .sync folder!");
-    let mut working_path = home_dir().unwrap();
-    working_path.push(def::ROOT_FOLDER);
+    let mut working_path = get_backend().root().to_path_buf();
     working_path.push(CFG.main.sync_folder);
     if !working_path.is_dir() {
         fs::create_dir(&working_path)?;
@@ -157,21 +230,9 @@ fn init_snyc_folder() -> Result<()> {
         .current_dir(&working_path)
         .output()?;
 
-    // edit .git/config file
+    // setup the diff driver for the encrypted files
     println!("Edit gitconfig file");
-    working_path.push(".git");
-    working_path.push("config");
-    {
-        let mut config_file = OpenOptions::new()
-            .append(true)
-            .create(false)
-            .open(&working_path)?;
-        config_file.write_all(b"[diff \"gpg\"]\n")?;
-        config_file.write_all(b"\tbinary = true\n")?;
-        config_file.write_all(b"\ttextconv = gpg2 -d --quiet --yes --compress-algo=none --no-encrypt-to --batch --use-agent\n")?;
-    }
-    working_path.pop();
-    working_path.pop();
+    init_diff_driver(&working_path)?;
 
     // generate the gitignore file
     println!("generating .gitignore!");
@@ -196,18 +257,23 @@ fn init_snyc_folder() -> Result<()> {
             .create(true)
             .truncate(true)
             .open(&working_path)?;
-        attributes_file.write_all(b"*.gpg diff=gpg\n")?;
+        attributes_file.write_all(diff_attribute().as_bytes())?;
+        attributes_file.write_all(b"\n")?;
     }
     working_path.pop();
 
-    // copy the gpg-id file
-    println!("copying .gpg-id!");
-    let mut main_gpg_id_path = working_path.clone();
-    main_gpg_id_path.pop();
-    main_gpg_id_path.push(".gpg-id");
-    working_path.push(".gpg-id");
-    fs::copy(main_gpg_id_path, &working_path)?;
-    working_path.pop();
+    // copy the files containing the recipients
+    let mut main_path = working_path.clone();
+    main_path.pop();
+    for recipients_file in RECIPIENTS_FILES.iter() {
+        if main_path.join(recipients_file).is_file() {
+            println!("copying {}!", recipients_file);
+            fs::copy(
+                main_path.join(recipients_file),
+                working_path.join(recipients_file),
+            )?;
+        }
+    }
 
     // commit the change of the .gitignore
     println!("committing changes!");
@@ -231,30 +297,35 @@ fn init_snyc_folder() -> Result<()> {
 fn do_initial_sync() -> Result<()> {
     let index_list = index::get_index()?;
 
-    let mut sync_path = home_dir().unwrap();
-    sync_path.push(def::ROOT_FOLDER);
+    let backend = get_backend();
+    let mut sync_path = backend.root().to_path_buf();
     sync_path.push(CFG.main.sync_folder);
 
-    let mut uuid_path = home_dir().unwrap();
-    uuid_path.push(def::ROOT_FOLDER);
-    uuid_path.push(CFG.main.uuid_folder);
-
     let mut git_changes = false;
 
     for (id, path) in index_list {
         // prepare destination folder
         let mut dst_path = sync_path.clone();
-        dst_path.push(format!("{}.gpg", path));
+        dst_path.push(format!("{}.{}", path, backend.extension()));
         let parent = dst_path.parent().unwrap();
         if !parent.is_dir() {
             fs::create_dir_all(parent)?;
         }
 
         // copy the file over
-        uuid_path.push(format!("{:?}.gpg", id));
-        fs::copy(&uuid_path, dst_path)?;
-        // reset uuid_path
-        uuid_path.pop();
+        let src_path = backend.file(&format!("{}/{}", CFG.main.uuid_folder, id));
+        fs::copy(src_path, dst_path)?;
+
+        // copy the attachments next to the entry
+        for blob in index::attachment_blobs(id)? {
+            let file_name = &blob[blob.rfind('/').unwrap() + 1..];
+            let dst_folder = sync_path.join(format!("{}{}", path, def::ATTACHMENTS_SUFFIX));
+            fs::create_dir_all(&dst_folder)?;
+            fs::copy(
+                backend.file(&blob),
+                dst_folder.join(format!("{}.{}", file_name, backend.extension())),
+            )?;
+        }
 
         git_changes = true;
     }
@@ -14,16 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
-use std::env;
 use std::fs::{self, OpenOptions};
 use std::io::prelude::*;
 use std::process::Command;
 
-use dirs::home_dir;
-
 use crate::config::CFG;
-use crate::def;
 use crate::errors::Result;
+use crate::pass::backend::get_backend;
 
 mod daemon;
 mod init;
@@ -41,28 +38,24 @@ pub fn full() -> Result<()> {
 }
 
 fn update_sync_commit_file() -> Result<()> {
-    // current directory must be changed!
-    let old_dir = env::current_dir()?;
-
     // delete old file if it exists
-    let mut working_path = home_dir().unwrap();
-    working_path.push(def::ROOT_FOLDER);
+    let mut working_path = get_backend().root().to_path_buf();
 
     // get master commit
-    env::set_current_dir(&working_path)?;
     let master_commit = Command::new("git")
         .arg("rev-parse")
         .arg("HEAD")
+        .current_dir(&working_path)
         .output()?
         .stdout;
 
     working_path.push(CFG.main.sync_folder);
 
     // get slave commit
-    env::set_current_dir(&working_path)?;
     let slave_commit = Command::new("git")
         .arg("rev-parse")
         .arg("HEAD")
+        .current_dir(&working_path)
         .output()?
         .stdout;
 
@@ -83,8 +76,5 @@ fn update_sync_commit_file() -> Result<()> {
         sync_commit_file.write_all(&slave_commit)?;
     }
 
-    // set current directory back to the one before
-    env::set_current_dir(old_dir)?;
-
     Ok(())
 }
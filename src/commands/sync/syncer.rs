@@ -14,14 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io;
 use std::io::prelude::*;
 use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
 
-use dirs::home_dir;
 use unidiff::{self, PatchSet};
 use uuid::Uuid;
 
@@ -29,20 +30,43 @@ use crate::commands::sync::update_sync_commit_file;
 use crate::config::CFG;
 use crate::def;
 use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
 use crate::pass::entry::Entry;
-use crate::pass::index;
+use crate::pass::index::{self, IndexRecord, Transaction};
 
 // TODO also sync the other way!
 
 pub fn sync(apply: bool) -> Result<()> {
     let mut slave_changes = false;
+    let ext = format!(".{}", get_backend().extension());
+    let index_file = format!("{}{}", CFG.main.index_entry, ext);
 
     println!("Loading diffs...");
     let (master_patch, slave_patch) = parse_diffs()?;
 
-    let index_list = index::get_index()?;
+    // all changes to the master are applied at once, after all changes are checked. Until then,
+    // the slave is restored if anything fails.
+    let mut tx = Transaction::begin("rpass sync")?;
+    let mut backup = SlaveBackup::default();
+    let index_list: Vec<(Uuid, String)> = tx
+        .records()
+        .iter()
+        .flat_map(|r| r.paths().map(move |p| (r.uuid, p.to_string())))
+        .collect();
     let index_path_map = index::to_hashmap(&index_list);
-    let index_uuid_map = index::to_hashmap_reverse(&index_list);
+    let index_uuid_map = index::to_hashmap_reverse(&index_list)?;
+    let index_aliases: HashMap<Uuid, Vec<String>> = tx
+        .records()
+        .iter()
+        .map(|r| (r.uuid, r.aliases.clone()))
+        .collect();
+    // entries of the master which are missing in the index cannot be mirrored
+    let indexed_path = |uuid: &Uuid| {
+        index_path_map
+            .get(uuid)
+            .copied()
+            .ok_or(Error::Sync("The master entry does not exist in the index!"))
+    };
 
     //-----------------
     // MASTER ==> SLAVE
@@ -56,15 +80,21 @@ pub fn sync(apply: bool) -> Result<()> {
         if !new_file.target_file.starts_with("b/") {
             continue;
         }
-        if !new_file.target_file.ends_with(".gpg") {
+        if !new_file.target_file.ends_with(&ext) {
+            continue;
+        }
+        if !is_entry(&new_file.target_file) {
             continue;
         }
-        let uuid = uuid_from_diff_filename(&new_file.target_file)?;
-        let path = index_path_map[&uuid];
+        let uuid = uuid_from_diff_filename(&new_file.target_file, &ext)?;
+        let path = indexed_path(&uuid)?;
 
         println!("New entry    [M -> S]: {}", path);
         if apply {
-            move_entry_to_slave(uuid, path, false)?;
+            move_entry_to_slave(uuid, path, false, &mut backup)?;
+            for alias in index_aliases.get(&uuid).into_iter().flatten() {
+                copy_entry_to_slave(uuid, alias, &mut backup)?;
+            }
             slave_changes = true;
         }
     }
@@ -77,10 +107,13 @@ pub fn sync(apply: bool) -> Result<()> {
         if !old_file.source_file.starts_with("a/") {
             continue;
         }
-        if !old_file.source_file.ends_with(".gpg") {
+        if !old_file.source_file.ends_with(&ext) {
             continue;
         }
-        let uuid = uuid_from_diff_filename(&old_file.source_file)?;
+        if !is_entry(&old_file.source_file) {
+            continue;
+        }
+        let uuid = uuid_from_diff_filename(&old_file.source_file, &ext)?;
 
         // extract the path from the diff
         let path = match old_file
@@ -95,7 +128,7 @@ pub fn sync(apply: bool) -> Result<()> {
         println!("Remove entry [M -> S]: {}", path);
 
         if apply {
-            remove_slave_entry(&path)?;
+            remove_slave_entry(&path, &mut backup)?;
             slave_changes = true;
         }
     }
@@ -108,14 +141,17 @@ pub fn sync(apply: bool) -> Result<()> {
         if !mod_file.target_file.starts_with("b/") {
             continue;
         }
-        if !mod_file.target_file.ends_with(".gpg") {
+        if !mod_file.target_file.ends_with(&ext) {
+            continue;
+        }
+        if !is_entry(&mod_file.target_file) {
             continue;
         }
-        if mod_file.target_file.contains(CFG.main.index_file) {
+        if mod_file.target_file.ends_with(&index_file) {
             continue;
         }
-        let uuid = uuid_from_diff_filename(&mod_file.target_file)?;
-        let path = index_path_map[&uuid];
+        let uuid = uuid_from_diff_filename(&mod_file.target_file, &ext)?;
+        let path = indexed_path(&uuid)?;
 
         // Check wether the path line was changed
         if let Some(l) = mod_file
@@ -126,7 +162,7 @@ pub fn sync(apply: bool) -> Result<()> {
             let old_path = &l.value[CFG.pass.path_key.len()..];
             println!("Rename entry [M -> S]: {} -> {}", old_path, path);
             if apply {
-                rename_slave_entry(old_path, path)?;
+                rename_slave_entry(old_path, path, &mut backup)?;
                 slave_changes = true;
             }
         }
@@ -134,7 +170,79 @@ pub fn sync(apply: bool) -> Result<()> {
         // copy over the new file
         println!("Modify entry [M -> S]: {}", path);
         if apply {
-            move_entry_to_slave(uuid, path, true)?;
+            move_entry_to_slave(uuid, path, true, &mut backup)?;
+            for alias in index_aliases.get(&uuid).into_iter().flatten() {
+                copy_entry_to_slave(uuid, alias, &mut backup)?;
+            }
+            slave_changes = true;
+        }
+    }
+
+    // step 4: mirror the attachments of the entries on the slave
+    for new_file in master_patch
+        .added_files()
+        .into_iter()
+        .chain(master_patch.modified_files())
+    {
+        if !new_file.target_file.starts_with("b/") {
+            continue;
+        }
+        if !new_file.target_file.ends_with(&ext) || !is_attachment(&new_file.target_file) {
+            continue;
+        }
+        let (uuid, name) = attachment_from_diff_filename(&new_file.target_file, &ext)?;
+        let path = match index_path_map.get(&uuid) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        println!("Attach file  [M -> S]: {}/{}", path, name);
+        if apply {
+            copy_attachment_to_slave(uuid, path, &name, &mut backup)?;
+            slave_changes = true;
+        }
+    }
+    for old_file in master_patch.removed_files() {
+        if !old_file.source_file.starts_with("a/") {
+            continue;
+        }
+        if !old_file.source_file.ends_with(&ext) || !is_attachment(&old_file.source_file) {
+            continue;
+        }
+        let (uuid, name) = attachment_from_diff_filename(&old_file.source_file, &ext)?;
+        // attachments of removed entries are already removed together with the entry
+        let path = match index_path_map.get(&uuid) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        println!("Remove file  [M -> S]: {}/{}", path, name);
+        if apply {
+            remove_slave_attachment(path, &name, &mut backup)?;
+            slave_changes = true;
+        }
+    }
+
+    // step 5: mirror added and removed aliases on the slave
+    let (old_aliases, new_aliases) = alias_changes(&master_patch, &index_file);
+    for (_, alias) in old_aliases.iter().filter(|a| !new_aliases.contains(a)) {
+        // the path might be used by another entry by now
+        if index_uuid_map.contains_key(alias.as_str()) {
+            continue;
+        }
+        println!("Remove alias [M -> S]: {}", alias);
+        if apply {
+            remove_slave_entry(alias, &mut backup)?;
+            slave_changes = true;
+        }
+    }
+    for (uuid, alias) in new_aliases.iter().filter(|a| !old_aliases.contains(a)) {
+        if index_uuid_map.get(alias.as_str()) != Some(uuid) {
+            continue;
+        }
+        println!("New alias    [M -> S]: {}", alias);
+        if apply {
+            copy_entry_to_slave(*uuid, alias, &mut backup)?;
             slave_changes = true;
         }
     }
@@ -148,15 +256,17 @@ pub fn sync(apply: bool) -> Result<()> {
         if !old_file.source_file.starts_with("a/") {
             continue;
         }
-        if !old_file.source_file.ends_with(".gpg") {
+        if !old_file.source_file.ends_with(&ext) {
             continue;
         }
-        let path = path_from_slave_diff_filename(&old_file.source_file);
-
-        println!("Remove entry [M <- S]: {}", path);
+        if is_slave_attachment(&old_file.source_file) {
+            continue;
+        }
+        let path = path_from_slave_diff_filename(&old_file.source_file, &ext);
 
         // check if the uuid exists and is indexed
         if !index_uuid_map.contains_key(path.as_str()) {
+            println!("Remove entry [M <- S]: {}", path);
             eprintln!(
                 "The slave entry {} does not exist in the index! Ignoring it...",
                 path.as_str()
@@ -166,20 +276,34 @@ pub fn sync(apply: bool) -> Result<()> {
 
         let uuid = index_uuid_map[path.as_str()];
 
+        // removing an alias keeps the entry
+        if indexed_path(&uuid)? != path {
+            println!("Remove alias [M <- S]: {}", path);
+            if apply {
+                tx.update_metadata(uuid, |r| r.aliases.retain(|a| a != &path));
+            }
+            continue;
+        }
+
+        println!("Remove entry [M <- S]: {}", path);
         if apply {
-            index::remove(uuid)?;
+            tx.remove(uuid)?;
         }
     }
 
     // step 2: Add new entries to the master
+    let mut new_entries: Vec<(Uuid, String)> = Vec::new();
     for new_file in slave_patch.added_files() {
         if !new_file.target_file.starts_with("b/") {
             continue;
         }
-        if !new_file.target_file.ends_with(".gpg") {
+        if !new_file.target_file.ends_with(&ext) {
             continue;
         }
-        let path = path_from_slave_diff_filename(&new_file.target_file);
+        if is_slave_attachment(&new_file.target_file) {
+            continue;
+        }
+        let path = path_from_slave_diff_filename(&new_file.target_file, &ext);
         let full_path = format!("{}/{}", CFG.main.sync_folder, path);
 
         println!("Add entry    [M <- S]: {}", path);
@@ -193,23 +317,25 @@ pub fn sync(apply: bool) -> Result<()> {
             e.uuid = Uuid::new_v4();
 
             // write the new entry
-            e.create()?;
-
-            // now, we must change the entry of the slave, to reflect our changes (else, both branches would diverge)
-            move_entry_to_slave(e.uuid, &path, true)?;
-            slave_changes = true;
+            e.create_in(&mut tx)?;
+            new_entries.push((e.uuid, path));
         }
     }
 
-    // step 3: Entry was edited by the slave, apply changes to the master
+    // step 3: Entry was edited by the slave, apply changes to the master. The other paths of the
+    // entry are updated on the slave afterwards.
+    let mut modified_copies: Vec<(Uuid, String)> = Vec::new();
     for mod_file in slave_patch.modified_files() {
         if !mod_file.target_file.starts_with("b/") {
             continue;
         }
-        if !mod_file.target_file.ends_with(".gpg") {
+        if !mod_file.target_file.ends_with(&ext) {
+            continue;
+        }
+        if is_slave_attachment(&mod_file.target_file) {
             continue;
         }
-        let path = path_from_slave_diff_filename(&mod_file.target_file);
+        let path = path_from_slave_diff_filename(&mod_file.target_file, &ext);
         let full_path = format!("{}/{}", CFG.main.sync_folder, path);
 
         println!("Modify entry [M <- S]: {}", path);
@@ -229,23 +355,42 @@ pub fn sync(apply: bool) -> Result<()> {
             if e.uuid != uuid {
                 return Err(Error::Sync("Slave has modified the uuid!"));
             }
-            if e.path.as_ref() != Some(&path) {
+            if e.path.as_deref() != Some(indexed_path(&uuid)?) {
                 return Err(Error::Sync("Slave has an invalid path!"));
             }
 
             // write the changes
-            e.write()?;
+            e.write_in(&mut tx);
+            modified_copies.extend(
+                index_list
+                    .iter()
+                    .filter(|(id, p)| *id == uuid && p != &path)
+                    .cloned(),
+            );
         }
     }
 
+    tx.commit()?;
+    backup.keep();
+
+    // now, we must change the new entries of the slave, to reflect our changes (else, both
+    // branches would diverge)
+    for (uuid, path) in new_entries {
+        move_entry_to_slave(uuid, &path, true, &mut backup)?;
+        slave_changes = true;
+    }
+    for (uuid, path) in modified_copies {
+        copy_entry_to_slave(uuid, &path, &mut backup)?;
+        slave_changes = true;
+    }
+
     //-----------------
     // SALVE GIT COMMIT
     //-----------------
 
     if slave_changes {
         // change working directory to the sync folder
-        let mut working_path = home_dir().unwrap();
-        working_path.push(def::ROOT_FOLDER);
+        let mut working_path = get_backend().root().to_path_buf();
         working_path.push(CFG.main.sync_folder);
 
         // add changes and fcommit
@@ -272,28 +417,126 @@ pub fn sync(apply: bool) -> Result<()> {
     Ok(())
 }
 
-fn uuid_from_diff_filename(diff_filename: &str) -> Result<Uuid> {
+fn uuid_from_diff_filename(diff_filename: &str, ext: &str) -> Result<Uuid> {
     let uuid_start = "b//".len() + CFG.main.uuid_folder.len();
-    let uuid_end = diff_filename.len() - ".gpg".len();
+    let uuid_end = diff_filename.len() - ext.len();
     let uuid_slice = &diff_filename[uuid_start..uuid_end];
     Ok(Uuid::parse_str(uuid_slice)?)
 }
 
-fn path_from_slave_diff_filename(diff_filename: &str) -> String {
+/// Whether the file of the master diff is an entry, and not stored in a subfolder (like the
+/// attachments or the trash).
+fn is_entry(diff_filename: &str) -> bool {
+    diff_filename[2..]
+        .strip_prefix(&format!("{}/", CFG.main.uuid_folder))
+        .is_some_and(|name| !name.contains('/'))
+}
+
+/// Whether the file of the master diff is an attachment.
+fn is_attachment(diff_filename: &str) -> bool {
+    diff_filename[2..].starts_with(&format!(
+        "{}/{}/",
+        CFG.main.uuid_folder,
+        def::ATTACHMENTS_FOLDER
+    ))
+}
+
+/// Whether the file of the slave diff is an attachment. Attachments are only mirrored from the
+/// master to the slave.
+fn is_slave_attachment(diff_filename: &str) -> bool {
+    diff_filename.contains(&format!("{}/", def::ATTACHMENTS_SUFFIX))
+}
+
+/// Uuid of the entry and name of the attachment of a file of the master diff.
+fn attachment_from_diff_filename(diff_filename: &str, ext: &str) -> Result<(Uuid, String)> {
+    let prefix_len = "b//".len() + CFG.main.uuid_folder.len() + def::ATTACHMENTS_FOLDER.len() + 1;
+    let name = &diff_filename[prefix_len..diff_filename.len() - ext.len()];
+    match name.split_once('/') {
+        Some((uuid, file_name)) => Ok((Uuid::parse_str(uuid)?, file_name.to_string())),
+        None => Err(Error::Sync("Invalid attachment in the master")),
+    }
+}
+
+/// Folder of the slave storing the attachments of the entry at `path`.
+fn slave_attachments_folder(path: &str) -> PathBuf {
+    get_backend().root().join(format!(
+        "{}/{}{}",
+        CFG.main.sync_folder,
+        path,
+        def::ATTACHMENTS_SUFFIX
+    ))
+}
+
+fn path_from_slave_diff_filename(diff_filename: &str, ext: &str) -> String {
     let path_start = "b/".len();
-    let path_end = diff_filename.len() - ".gpg".len();
+    let path_end = diff_filename.len() - ext.len();
     String::from(&diff_filename[path_start..path_end])
 }
 
-fn move_entry_to_slave(uuid: Uuid, path: &str, overwrite: bool) -> Result<()> {
-    let mut working_path = home_dir().unwrap();
-    working_path.push(def::ROOT_FOLDER);
-    let mut src_path = working_path.clone();
-    src_path.push(CFG.main.uuid_folder);
-    src_path.push(format!("{}.gpg", uuid));
-    let mut dst_path = working_path;
-    dst_path.push(CFG.main.sync_folder);
-    dst_path.push(format!("{}.gpg", path));
+/// Original content of the slave files changed by the synchronization. Unless `keep` is called,
+/// all of them are restored when it is dropped, e.g., because the changes of the slave could not
+/// be applied to the master.
+#[derive(Default)]
+struct SlaveBackup {
+    /// Changed files, and their content before the first change (`None` if they did not exist).
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    kept: bool,
+}
+
+impl SlaveBackup {
+    /// Remember the content of `path` before it is changed. Folders are saved with all their files.
+    fn save(&mut self, path: &Path) -> Result<()> {
+        if self.kept || self.files.iter().any(|(f, _)| f == path) {
+            return Ok(());
+        }
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.save(&entry?.path())?;
+            }
+            return Ok(());
+        }
+        let content = match fs::read(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(Error::Io(e)),
+        };
+        self.files.push((path.to_path_buf(), content));
+        Ok(())
+    }
+
+    /// Keep all changes made so far, and stop saving files.
+    fn keep(&mut self) {
+        self.kept = true;
+        self.files.clear();
+    }
+}
+
+impl Drop for SlaveBackup {
+    fn drop(&mut self) {
+        let mut slave = get_backend().root().to_path_buf();
+        slave.push(CFG.main.sync_folder);
+        for (file, content) in self.files.drain(..).rev() {
+            let _ = match content {
+                Some(content) => fs::create_dir_all(file.parent().unwrap())
+                    .and_then(|_| fs::write(&file, content)),
+                None => fs::remove_file(&file),
+            };
+            // remove the folders which were only created for the file
+            let mut dir = file;
+            while dir.pop() && dir != slave && fs::remove_dir(&dir).is_ok() {}
+        }
+    }
+}
+
+fn move_entry_to_slave(
+    uuid: Uuid,
+    path: &str,
+    overwrite: bool,
+    backup: &mut SlaveBackup,
+) -> Result<()> {
+    let backend = get_backend();
+    let src_path = backend.file(&format!("{}/{}", CFG.main.uuid_folder, uuid));
+    let dst_path = backend.file(&format!("{}/{}", CFG.main.sync_folder, path));
 
     let parent = dst_path.parent().unwrap();
     if !parent.is_dir() {
@@ -314,18 +557,99 @@ fn move_entry_to_slave(uuid: Uuid, path: &str, overwrite: bool) -> Result<()> {
     }
 
     // copy the file over
+    backup.save(&dst_path)?;
     fs::copy(src_path, dst_path)?;
 
     Ok(())
 }
 
-fn remove_slave_entry(path: &str) -> Result<()> {
-    let mut dst_path = home_dir().unwrap();
-    dst_path.push(def::ROOT_FOLDER);
-    dst_path.push(CFG.main.sync_folder);
-    dst_path.push(format!("{}.gpg", path));
+/// Copy the entry to `path` of the slave, replacing the file if it exists. This is used for the
+/// aliases of the entry, which are plain copies on the slave.
+fn copy_entry_to_slave(uuid: Uuid, path: &str, backup: &mut SlaveBackup) -> Result<()> {
+    let backend = get_backend();
+    let dst_path = backend.file(&format!("{}/{}", CFG.main.sync_folder, path));
+    backup.save(&dst_path)?;
+    fs::create_dir_all(dst_path.parent().unwrap())?;
+    fs::copy(
+        backend.file(&format!("{}/{}", CFG.main.uuid_folder, uuid)),
+        dst_path,
+    )?;
+    Ok(())
+}
+
+/// Aliases together with the uuid of their entry.
+type Aliases = Vec<(Uuid, String)>;
+
+/// Aliases of all entries before and after the changes of the master, read from the diff of the
+/// index.
+fn alias_changes(patch: &PatchSet, index_file: &str) -> (Aliases, Aliases) {
+    let index_file = format!("b/{}/{}", CFG.main.uuid_folder, index_file);
+    let mut old = Vec::new();
+    let mut new = Vec::new();
+    for file in patch
+        .added_files()
+        .into_iter()
+        .chain(patch.modified_files())
+        .filter(|f| f.target_file == index_file)
+    {
+        for line in file.into_iter().flatten() {
+            let record = match serde_json::from_str::<IndexRecord>(&line.value) {
+                Ok(record) => record,
+                Err(_) => continue,
+            };
+            let uuid = record.uuid;
+            let aliases = record.aliases.into_iter().map(move |a| (uuid, a));
+            match line.line_type.as_str() {
+                "-" => old.extend(aliases),
+                "+" => new.extend(aliases),
+                _ => {}
+            }
+        }
+    }
+    (old, new)
+}
+
+fn copy_attachment_to_slave(
+    uuid: Uuid,
+    path: &str,
+    name: &str,
+    backup: &mut SlaveBackup,
+) -> Result<()> {
+    let backend = get_backend();
+    let src_path = backend.file(&format!("{}/{}", index::attachments_folder(uuid), name));
+    let dst_folder = slave_attachments_folder(path);
+    let dst_path = dst_folder.join(format!("{}.{}", name, backend.extension()));
+    backup.save(&dst_path)?;
+    fs::create_dir_all(&dst_folder)?;
+    fs::copy(src_path, dst_path)?;
+    Ok(())
+}
+
+fn remove_slave_attachment(path: &str, name: &str, backup: &mut SlaveBackup) -> Result<()> {
+    let folder = slave_attachments_folder(path);
+    let file = folder.join(format!("{}.{}", name, get_backend().extension()));
+    backup.save(&file)?;
+    match fs::remove_file(file) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!(
+                "Warning: Attachment {}/{} does not exist for the slave!",
+                path, name
+            )
+        }
+        Err(e) => return Err(Error::Io(e)),
+    }
+    if fs::read_dir(&folder)?.next().is_none() {
+        fs::remove_dir(&folder)?;
+    }
+    Ok(())
+}
+
+fn remove_slave_entry(path: &str, backup: &mut SlaveBackup) -> Result<()> {
+    let mut dst_path = get_backend().file(&format!("{}/{}", CFG.main.sync_folder, path));
 
     // remove the file
+    backup.save(&dst_path)?;
     match fs::remove_file(&dst_path) {
         Ok(()) => {}
         Err(e) => match e.kind() {
@@ -336,33 +660,33 @@ fn remove_slave_entry(path: &str) -> Result<()> {
         },
     }
 
+    // remove the attachments of the entry
+    let attachments = slave_attachments_folder(path);
+    if attachments.is_dir() {
+        backup.save(&attachments)?;
+        fs::remove_dir_all(attachments)?;
+    }
+
     // recursively walk back directories if the current path is empty
     loop {
         dst_path.pop();
         if dst_path.file_name().unwrap() == CFG.main.sync_folder {
             break;
         }
-        println!("{:?}", dst_path);
-        match fs::remove_dir(&dst_path) {
-            Ok(()) => {}
-            Err(e) => match e.kind() {
-                ErrorKind::Other => break,
-                _ => return Err(e.into()),
-            },
+        // stop as soon as the directory still contains other entries
+        if fs::read_dir(&dst_path)?.next().is_some() {
+            break;
         }
+        fs::remove_dir(&dst_path)?;
     }
 
     Ok(())
 }
 
-fn rename_slave_entry(old_path: &str, new_path: &str) -> Result<()> {
-    let mut working_path = home_dir().unwrap();
-    working_path.push(def::ROOT_FOLDER);
-    working_path.push(CFG.main.sync_folder);
-    let mut src_path = working_path.clone();
-    src_path.push(format!("{}.gpg", old_path));
-    let mut dst_path = working_path;
-    dst_path.push(format!("{}.gpg", new_path));
+fn rename_slave_entry(old_path: &str, new_path: &str, backup: &mut SlaveBackup) -> Result<()> {
+    let backend = get_backend();
+    let src_path = backend.file(&format!("{}/{}", CFG.main.sync_folder, old_path));
+    let dst_path = backend.file(&format!("{}/{}", CFG.main.sync_folder, new_path));
 
     // create target directory if it does not already exist
     let parent = dst_path.parent().unwrap();
@@ -375,8 +699,20 @@ fn rename_slave_entry(old_path: &str, new_path: &str) -> Result<()> {
         return Err(Error::Sync("Destination file already exists!"));
     }
 
-    // move the entry
+    // move the entry and its attachments
+    backup.save(&src_path)?;
+    backup.save(&dst_path)?;
     fs::rename(src_path, dst_path)?;
+    let attachments = slave_attachments_folder(old_path);
+    if attachments.is_dir() {
+        let new_attachments = slave_attachments_folder(new_path);
+        for entry in fs::read_dir(&attachments)? {
+            let entry = entry?;
+            backup.save(&entry.path())?;
+            backup.save(&new_attachments.join(entry.file_name()))?;
+        }
+        fs::rename(attachments, new_attachments)?;
+    }
 
     Ok(())
 }
@@ -385,8 +721,7 @@ fn parse_diffs() -> Result<(PatchSet, PatchSet)> {
     let (master_commit, slave_commit) = get_last_sync_commits()?;
 
     // delete old file if it exists
-    let mut working_path = home_dir().unwrap();
-    working_path.push(def::ROOT_FOLDER);
+    let mut working_path = get_backend().root().to_path_buf();
 
     // get master commit
     let master_patch = Command::new("git")
@@ -419,8 +754,7 @@ fn parse_diffs() -> Result<(PatchSet, PatchSet)> {
 }
 
 fn get_last_sync_commits() -> Result<(String, String)> {
-    let mut sync_commit_file = home_dir().unwrap();
-    sync_commit_file.push(def::ROOT_FOLDER);
+    let mut sync_commit_file = get_backend().root().to_path_buf();
     sync_commit_file.push(CFG.main.sync_folder);
     sync_commit_file.push(CFG.main.sync_commit_file);
 
@@ -0,0 +1,29 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::errors::Result;
+use crate::pass::backend::get_backend;
+
+/// Print the decrypted content of an encrypted file. This is used by git as `textconv` command to
+/// show the diff of entries.
+pub fn textconv(file: &str) -> Result<()> {
+    let content = get_backend().read_file(Path::new(file))?;
+    io::stdout().write_all(content.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::time::{Duration, SystemTime};
+
+use crate::errors::{Error, Result};
+use crate::pass::expiry::parse_duration;
+use crate::pass::trash::{self, TrashRecord};
+
+/// Print all entries in the trash, starting with the most recently deleted one.
+pub fn list() -> Result<()> {
+    for record in trash::records()?.iter().rev() {
+        println!(
+            "{}  {}  ({})",
+            record.uuid,
+            record.path,
+            deleted_since(record)
+        );
+    }
+    Ok(())
+}
+
+/// Restore the trashed entry with the given path or uuid, or the most recently deleted one.
+pub fn restore(path: Option<&str>, id: Option<&str>) -> Result<()> {
+    let record = trash::records()?
+        .into_iter()
+        .rev()
+        .find(|r| match (path, id) {
+            (Some(path), _) => r.path == path,
+            (None, Some(id)) => r.uuid.to_string() == id.to_lowercase(),
+            (None, None) => true,
+        })
+        .ok_or(Error::InvalidInput("Entry is not in the trash"))?;
+    trash::restore(record.uuid)?;
+    println!("Restored {}", record.path);
+    Ok(())
+}
+
+/// Permanently delete the entries which are in the trash for longer than `older_than` (e.g.,
+/// `30d`), or all of them.
+pub fn empty(older_than: Option<&str>) -> Result<()> {
+    let age = match older_than {
+        Some(duration) => parse_duration(duration)?,
+        None => 0,
+    };
+    for record in trash::empty(age)? {
+        println!("Deleted {}", record.path);
+    }
+    Ok(())
+}
+
+/// Time since the entry was deleted, e.g., `3 days ago`.
+fn deleted_since(record: &TrashRecord) -> String {
+    let deleted = SystemTime::UNIX_EPOCH + Duration::from_secs(record.deleted);
+    let secs = SystemTime::now()
+        .duration_since(deleted)
+        .unwrap_or_default()
+        .as_secs();
+    match secs {
+        s if s < 60 * 60 => format!("{} minutes ago", s / 60),
+        s if s < 24 * 60 * 60 => format!("{} hours ago", s / (60 * 60)),
+        s => format!("{} days ago", s / (24 * 60 * 60)),
+    }
+}
@@ -31,14 +31,21 @@ use crate::config::CFG;
 use crate::def;
 use crate::errors::{Error, Result};
 use crate::pass::entry::Entry;
+use crate::pass::hibp::Breaches;
 use crate::pass::index::{get_index, to_graph, to_hashmap_reverse, touch_entry};
+use crate::pass::store;
+use crate::pass::strength::Strength;
 
 pub fn choose_entry(path: Option<&str>, id: Option<&str>, use_rofi: bool) -> Result<Entry> {
     match (path, id) {
         (Some(path), None) => {
+            let path = match store::is_combined() {
+                true => store::enter(path)?,
+                false => path.to_string(),
+            };
             let index_list = get_index()?;
-            let uuid_lookup = to_hashmap_reverse(&index_list);
-            let entry_id = match uuid_lookup.get(path) {
+            let uuid_lookup = to_hashmap_reverse(&index_list)?;
+            let entry_id = match uuid_lookup.get(path.as_str()) {
                 Some(id) => id,
                 None => return Err(Error::UnknownPath(path.to_string())),
             };
@@ -47,6 +54,9 @@ pub fn choose_entry(path: Option<&str>, id: Option<&str>, use_rofi: bool) -> Res
 
         (None, Some(id)) => {
             let id = Uuid::parse_str(id)?;
+            if store::is_combined() {
+                store::enter_uuid(id)?;
+            }
             Entry::get(id)
         }
 
@@ -62,26 +72,41 @@ pub fn choose_entry(path: Option<&str>, id: Option<&str>, use_rofi: bool) -> Res
     }
 }
 
+/// Index of all entries the user can choose from. If multiple stores are configured, this
+/// contains the entries of all stores.
+pub fn get_searchable_index() -> Result<Vec<(Uuid, String)>> {
+    match store::is_combined() {
+        true => store::get_index(),
+        false => get_index(),
+    }
+}
+
+/// Get the entry at the path chosen from the searchable index.
+fn get_chosen_entry(id: Uuid, path: &str) -> Result<Entry> {
+    if store::is_combined() {
+        store::enter(path)?;
+    }
+    touch_entry(id);
+    Entry::get(id)
+}
+
 fn choose_entry_fzf() -> Result<Entry> {
-    let index_list = get_index()?;
+    let index_list = get_searchable_index()?;
     let index_list_clone = index_list.clone();
-    let uuid_lookup = to_hashmap_reverse(&index_list_clone);
+    let uuid_lookup = to_hashmap_reverse(&index_list_clone)?;
     let path_list: Vec<String> = index_list.into_iter().map(|x| x.1).collect();
     let choice = interactor::pick_from_list(Some(&mut Command::new("fzf")), &path_list, "")?;
     match uuid_lookup.get(choice.as_str()) {
-        Some(id) => {
-            touch_entry(*id);
-            Entry::get(*id)
-        }
+        Some(id) => get_chosen_entry(*id, &choice),
         None => Err(Error::UnknownPath(choice)),
     }
 }
 
 fn choose_entry_rofi() -> Result<Entry> {
     // prepare the index list
-    let index_list = get_index()?;
+    let index_list = get_searchable_index()?;
     let index_list_clone = index_list.clone();
-    let uuid_lookup = to_hashmap_reverse(&index_list_clone);
+    let uuid_lookup = to_hashmap_reverse(&index_list_clone)?;
     let path_list: Vec<String> = index_list.into_iter().map(|x| x.1).collect();
 
     // show with rofi
@@ -94,12 +119,10 @@ fn choose_entry_rofi() -> Result<Entry> {
         .return_format(Format::StrippedText)
         .run()?;
 
-    let entry_id = match uuid_lookup.get(selection.as_str()) {
-        Some(id) => id,
-        None => return Err(Error::UnknownPath(selection)),
-    };
-    touch_entry(*entry_id);
-    Entry::get(*entry_id)
+    match uuid_lookup.get(selection.as_str()) {
+        Some(id) => get_chosen_entry(*id, &selection),
+        None => Err(Error::UnknownPath(selection)),
+    }
 }
 
 pub fn gen_path_interactive() -> Result<String> {
@@ -108,7 +131,7 @@ pub fn gen_path_interactive() -> Result<String> {
 
 pub fn gen_path_recursive(cur_path: String) -> Result<String> {
     let mut index_list = get_index().expect("Cannot get index file");
-    index_list.sort_by(|a, b| b.1.to_lowercase().cmp(&a.1.to_lowercase()));
+    index_list.sort_by_key(|x| std::cmp::Reverse(x.1.to_lowercase()));
     let (g, root) = to_graph(&index_list);
 
     let mut last_node = root;
@@ -230,6 +253,67 @@ pub fn question_rofi<S: AsRef<str>>(q: S) -> Result<Option<String>> {
     }
 }
 
+/// Ask for a new password, which is entered twice on the terminal. If `check` is set, passwords
+/// below the `min_strength` from the config, or found in the configured breach dump, are refused
+/// if `refuse_weak` is set, and must be confirmed otherwise.
+pub fn ask_password(name: &str, check: bool, use_rofi: bool) -> Result<String> {
+    let mut prompt = name.to_string();
+    loop {
+        let password = match use_rofi {
+            true => match question_rofi(&prompt)? {
+                Some(pw) => pw,
+                None => return Err(Error::InvalidInput("Password cannot be empty!")),
+            },
+            false => ask_password_stdio(name)?,
+        };
+        let problem = match check {
+            true => password_problem(name, &password)?,
+            false => None,
+        };
+        let problem = match problem {
+            Some(problem) => problem,
+            None => return Ok(password),
+        };
+        if CFG.main.refuse_weak {
+            match use_rofi {
+                true => prompt = format!("{} ({})", name, problem),
+                false => println!("{}", problem),
+            }
+        } else if confirm(format!("{}. Use it anyway?", problem), use_rofi) {
+            return Ok(password);
+        }
+    }
+}
+
+/// Why `password` should not be used, e.g., because it is too weak.
+fn password_problem(name: &str, password: &str) -> Result<Option<String>> {
+    let strength = Strength::of(password);
+    if strength.score < CFG.main.min_strength {
+        return Ok(Some(format!("The {} is too weak: {}", name, strength)));
+    }
+    if let Some(mut breaches) = Breaches::configured()? {
+        let count = breaches.count(password)?;
+        if count > 0 {
+            return Ok(Some(format!(
+                "The {} appeared in {} data breaches",
+                name, count
+            )));
+        }
+    }
+    Ok(None)
+}
+
+fn ask_password_stdio(name: &str) -> Result<String> {
+    loop {
+        let password = rpassword::prompt_password_stdout(&format!("Enter a {}: ", name))?;
+        let repeated = rpassword::prompt_password_stdout(&format!("Repeat the {}: ", name))?;
+        if password == repeated {
+            return Ok(password);
+        }
+        println!("The two values don't match. try again!");
+    }
+}
+
 pub fn two_options<S: AsRef<str>>(primary: S, secondary: S) -> bool {
     print!("1: {}, 2: {} [1|2]: ", primary.as_ref(), secondary.as_ref());
     io::stdout().flush().expect("Could not flush stdout");
@@ -322,3 +406,37 @@ pub fn notify_action<S: AsRef<str>>(action: S) {
         .show()
         .unwrap();
 }
+
+/// Notify that the entry at `path` was moved to the trash, and offer to undo it. This blocks
+/// until the notification is closed.
+pub fn notify_trashed(id: Uuid, path: &str) -> Result<()> {
+    let mut undo = false;
+    Notification::new()
+        .summary("Moved to trash")
+        .body(path)
+        .action("undo", "Undo")
+        .urgency(NotificationUrgency::Low)
+        .timeout(Timeout::Milliseconds(10000))
+        .show()?
+        .wait_for_action(|action| undo = action == "undo");
+    if undo {
+        crate::pass::trash::restore(id)?;
+        notify_action(format!("Restored {}", path));
+    }
+    Ok(())
+}
+
+/// Notify that the password of the entry must be changed soon, if it is due.
+pub fn notify_due(entry: &Entry) {
+    if let Some(warning) = entry.expiry_warning() {
+        let _ = Notification::new()
+            .summary(&format!(
+                "{} is due",
+                entry.path.as_deref().unwrap_or_default()
+            ))
+            .body(&warning)
+            .urgency(NotificationUrgency::Normal)
+            .timeout(Timeout::Milliseconds(10000))
+            .show();
+    }
+}
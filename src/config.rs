@@ -14,8 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use crate::errors::Result;
-use dirs::config_dir;
+use dirs::{config_dir, home_dir};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
@@ -44,18 +47,66 @@ pub struct ConfigBuilder<'a> {
     pub main: Option<ConfigMainBuilder<'a>>,
     pub theme: Option<ConfigThemeBuilder<'a>>,
     pub pass: Option<ConfigPassBuilder<'a>>,
+    #[serde(borrow)]
+    pub fields: Option<ConfigFieldsBuilder<'a>>,
+    #[serde(borrow)]
+    pub stores: Option<BTreeMap<&'a str, ConfigStoreBuilder<'a>>>,
+    #[serde(borrow)]
+    pub templates: Option<BTreeMap<&'a str, ConfigTemplateBuilder<'a>>>,
+    #[serde(borrow)]
+    pub policies: Option<BTreeMap<&'a str, ConfigPolicyBuilder<'a>>>,
+}
+
+/// Backend used to store and encrypt the entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Call `pass` for every operation
+    Pass,
+    /// Call `gpg` directly, without going through `pass`
+    Gpg,
+    /// Encrypt the entries with age, using the recipients in `.age-recipients`
+    Age,
+}
+
+/// Format of a field of an entry type, which is validated when inserting a new entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldFormat {
+    /// Any single line
+    Text,
+    /// Only digits, e.g., a CVV
+    Digits,
+    /// Card number, checked with the Luhn algorithm
+    Card,
+    /// Expiry date of a card, written as `MM/YY`
+    Expiry,
+    /// Multiline note, entered in the editor
+    Note,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConfigMainBuilder<'a> {
+    pub backend: Option<BackendKind>,
+    pub default_store: Option<&'a str>,
+    pub age_identity_file: Option<&'a str>,
+    pub agent_socket: Option<&'a str>,
+    pub agent_timeout: Option<u64>,
+    pub agent_cache_entries: Option<bool>,
+    pub gui_editor: Option<&'a str>,
     pub uuid_folder: Option<&'a str>,
     pub index_entry: Option<&'a str>,
+    /// Deprecated and ignored: the index is stored as `index_entry` in the uuid folder.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub index_file: Option<&'a str>,
     pub sync_folder: Option<&'a str>,
     pub sync_commit_file: Option<&'a str>,
     pub last_command_file: Option<&'a str>,
     pub history_file: Option<&'a str>,
     pub history_days: Option<u64>,
+    pub min_strength: Option<u8>,
+    pub refuse_weak: Option<bool>,
+    pub hibp_file: Option<&'a str>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -76,8 +127,57 @@ pub struct ConfigPassBuilder<'a> {
     pub url_key: Option<&'a str>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigFieldsBuilder<'a> {
+    #[serde(borrow)]
+    pub secret: Option<Vec<&'a str>>,
+    pub secret_prefix: Option<&'a str>,
+    #[serde(borrow)]
+    pub url: Option<Vec<&'a str>>,
+    #[serde(borrow)]
+    pub email: Option<Vec<&'a str>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigStoreBuilder<'a> {
+    pub path: &'a str,
+    pub backend: Option<BackendKind>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigTemplateBuilder<'a> {
+    pub password: Option<&'a str>,
+    pub generate: Option<bool>,
+    pub username: Option<bool>,
+    pub url: Option<bool>,
+    #[serde(borrow)]
+    pub fields: Option<Vec<ConfigTemplateFieldBuilder<'a>>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigTemplateFieldBuilder<'a> {
+    pub name: &'a str,
+    pub format: Option<FieldFormat>,
+    pub optional: Option<bool>,
+    pub secret: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigPolicyBuilder<'a> {
+    pub length: Option<usize>,
+    pub lowercase: Option<bool>,
+    pub uppercase: Option<bool>,
+    pub digits: Option<bool>,
+    pub symbols: Option<bool>,
+    pub exclude_ambiguous: Option<bool>,
+    pub words: Option<usize>,
+    pub separator: Option<&'a str>,
+    #[serde(borrow)]
+    pub folders: Option<Vec<&'a str>>,
+}
+
 impl<'a> ConfigBuilder<'a> {
-    fn build(mut self) -> Config<'a> {
+    pub(crate) fn build(mut self) -> Config<'a> {
         Config {
             main: self
                 .main
@@ -94,6 +194,36 @@ impl<'a> ConfigBuilder<'a> {
                 .take()
                 .unwrap_or_else(ConfigPassBuilder::new)
                 .build(),
+            fields: self
+                .fields
+                .take()
+                .unwrap_or_else(ConfigFieldsBuilder::new)
+                .build(),
+            stores: self
+                .stores
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, store)| ConfigStore {
+                    name,
+                    path: store.path,
+                    backend: store.backend,
+                })
+                .collect(),
+            templates: self
+                .templates
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, template)| template.build(name))
+                .collect(),
+            policies: self
+                .policies
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, policy)| policy.build(name))
+                .collect(),
         }
     }
 }
@@ -101,6 +231,13 @@ impl<'a> ConfigBuilder<'a> {
 impl<'a> ConfigMainBuilder<'a> {
     fn new() -> Self {
         Self {
+            backend: None,
+            default_store: None,
+            age_identity_file: None,
+            agent_socket: None,
+            agent_timeout: None,
+            agent_cache_entries: None,
+            gui_editor: None,
             uuid_folder: None,
             index_entry: None,
             index_file: None,
@@ -109,14 +246,32 @@ impl<'a> ConfigMainBuilder<'a> {
             last_command_file: None,
             history_file: None,
             history_days: None,
+            min_strength: None,
+            refuse_weak: None,
+            hibp_file: None,
         }
     }
 
     fn build(mut self) -> ConfigMain<'a> {
+        if let Some(index_file) = self.index_file.take() {
+            eprintln!(
+                "[Warning] the option index_file = \"{}\" is deprecated and ignored, use index_entry instead",
+                index_file
+            );
+        }
         ConfigMain {
+            backend: self.backend.take().unwrap_or(BackendKind::Pass),
+            default_store: self.default_store.take(),
+            age_identity_file: self
+                .age_identity_file
+                .take()
+                .unwrap_or(".config/rpass/identity.txt"),
+            agent_socket: self.agent_socket.take(),
+            agent_timeout: self.agent_timeout.take().unwrap_or(900),
+            agent_cache_entries: self.agent_cache_entries.take().unwrap_or(false),
+            gui_editor: self.gui_editor.take(),
             uuid_folder: self.uuid_folder.take().unwrap_or("uuids"),
             index_entry: self.index_entry.take().unwrap_or("index"),
-            index_file: self.index_file.take().unwrap_or("index.gpg"),
             sync_folder: self.sync_folder.take().unwrap_or(".sync"),
             sync_commit_file: self.sync_commit_file.take().unwrap_or(".sync_commit"),
             last_command_file: self.last_command_file.take().unwrap_or(".cache/rpass_last"),
@@ -125,6 +280,9 @@ impl<'a> ConfigMainBuilder<'a> {
                 .take()
                 .unwrap_or(".cache/rpass_history"),
             history_days: self.history_days.take().unwrap_or(50),
+            min_strength: self.min_strength.take().unwrap_or(2),
+            refuse_weak: self.refuse_weak.take().unwrap_or(false),
+            hibp_file: self.hibp_file.take(),
         }
     }
 }
@@ -173,23 +331,114 @@ impl<'a> ConfigPassBuilder<'a> {
     }
 }
 
+impl<'a> ConfigFieldsBuilder<'a> {
+    fn new() -> Self {
+        Self {
+            secret: None,
+            secret_prefix: None,
+            url: None,
+            email: None,
+        }
+    }
+
+    fn build(mut self) -> ConfigFields<'a> {
+        ConfigFields {
+            secret: self
+                .secret
+                .take()
+                .unwrap_or_else(|| vec!["pin", "cvv", "secret", "token", "api key"]),
+            secret_prefix: self.secret_prefix.take().unwrap_or("!"),
+            url: self
+                .url
+                .take()
+                .unwrap_or_else(|| vec!["website", "homepage"]),
+            email: self
+                .email
+                .take()
+                .unwrap_or_else(|| vec!["email", "e-mail", "mail"]),
+        }
+    }
+}
+
+impl<'a> ConfigTemplateBuilder<'a> {
+    fn build(self, name: &'a str) -> ConfigTemplate<'a> {
+        ConfigTemplate {
+            name,
+            password: self.password.unwrap_or("password"),
+            generate: self.generate.unwrap_or(true),
+            username: self.username.unwrap_or(true),
+            url: self.url.unwrap_or(true),
+            fields: self
+                .fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|field| ConfigTemplateField {
+                    name: field.name,
+                    format: field.format.unwrap_or(FieldFormat::Text),
+                    optional: field.optional.unwrap_or(false),
+                    secret: field.secret.unwrap_or(false),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> ConfigPolicyBuilder<'a> {
+    fn build(self, name: &'a str) -> ConfigPolicy<'a> {
+        ConfigPolicy {
+            name,
+            length: self.length.unwrap_or(20),
+            lowercase: self.lowercase.unwrap_or(true),
+            uppercase: self.uppercase.unwrap_or(true),
+            digits: self.digits.unwrap_or(true),
+            symbols: self.symbols.unwrap_or(true),
+            exclude_ambiguous: self.exclude_ambiguous.unwrap_or(false),
+            words: self.words,
+            separator: self.separator.unwrap_or("-"),
+            folders: self.folders.unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Config<'a> {
     pub main: ConfigMain<'a>,
     pub theme: ConfigTheme<'a>,
     pub pass: ConfigPass<'a>,
+    pub fields: ConfigFields<'a>,
+    pub stores: Vec<ConfigStore<'a>>,
+    pub templates: Vec<ConfigTemplate<'a>>,
+    pub policies: Vec<ConfigPolicy<'a>>,
 }
 
 #[derive(Debug)]
 pub struct ConfigMain<'a> {
+    pub backend: BackendKind,
+    pub default_store: Option<&'a str>,
+    pub age_identity_file: &'a str,
+    /// Socket of the agent. By default, it is placed in the runtime folder of the user.
+    pub agent_socket: Option<&'a str>,
+    /// Seconds after which an idle agent forgets everything and stops.
+    pub agent_timeout: u64,
+    /// Also keep recently used entries in the agent, not only the index.
+    pub agent_cache_entries: bool,
+    /// Editor started from rofi, e.g., `alacritty -e nvim`. By default, `$EDITOR` is started in
+    /// `$TERMINAL`.
+    pub gui_editor: Option<&'a str>,
     pub uuid_folder: &'a str,
     pub index_entry: &'a str,
-    pub index_file: &'a str,
     pub sync_folder: &'a str,
     pub sync_commit_file: &'a str,
     pub last_command_file: &'a str,
     pub history_file: &'a str,
     pub history_days: u64,
+    /// Score (0 to 4) below which a password typed in by the user is weak.
+    pub min_strength: u8,
+    /// Refuse weak passwords, instead of asking whether to use them anyway.
+    pub refuse_weak: bool,
+    /// Local copy of the Have I Been Pwned SHA-1 dump, sorted by hash. Relative paths are
+    /// relative to the home folder.
+    pub hibp_file: Option<&'a str>,
 }
 
 #[derive(Debug)]
@@ -210,6 +459,84 @@ pub struct ConfigPass<'a> {
     pub url_key: &'a str,
 }
 
+/// Names of custom fields (case insensitive) with a special kind, configured in the `[fields]`
+/// section.
+#[derive(Debug)]
+pub struct ConfigFields<'a> {
+    pub secret: Vec<&'a str>,
+    /// Fields whose name starts with this prefix are secret as well.
+    pub secret_prefix: &'a str,
+    pub url: Vec<&'a str>,
+    pub email: Vec<&'a str>,
+}
+
+/// Named password store, configured in the `[stores]` table
+#[derive(Debug)]
+pub struct ConfigStore<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub backend: Option<BackendKind>,
+}
+
+/// User-defined entry type, configured in the `[templates]` table
+#[derive(Debug)]
+pub struct ConfigTemplate<'a> {
+    pub name: &'a str,
+    /// Name of the password shown when asking for it, e.g., `PIN`.
+    pub password: &'a str,
+    /// Offer to generate a random password.
+    pub generate: bool,
+    pub username: bool,
+    pub url: bool,
+    pub fields: Vec<ConfigTemplateField<'a>>,
+}
+
+#[derive(Debug)]
+pub struct ConfigTemplateField<'a> {
+    pub name: &'a str,
+    pub format: FieldFormat,
+    pub optional: bool,
+    /// Treat the field as secret, independent of its name.
+    pub secret: bool,
+}
+
+/// Password policy, configured in the `[policies]` table
+#[derive(Debug)]
+pub struct ConfigPolicy<'a> {
+    pub name: &'a str,
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Never use characters which are easily confused, like `l`, `1` and `I`.
+    pub exclude_ambiguous: bool,
+    /// Generate a passphrase of this many words instead of a password.
+    pub words: Option<usize>,
+    /// Separator between the words of a passphrase.
+    pub separator: &'a str,
+    /// Folders whose entries use this policy by default.
+    pub folders: Vec<&'a str>,
+}
+
+impl<'a> ConfigStore<'a> {
+    /// Location of the store. Relative paths are relative to the home folder.
+    pub fn root(&self) -> PathBuf {
+        expand_path(self.path)
+    }
+}
+
+/// Expand a path from the config or the environment. A leading `~` or a relative path refers
+/// to the home folder of the user.
+pub fn expand_path(path: &str) -> PathBuf {
+    let mut result = home_dir().unwrap();
+    match path.strip_prefix('~') {
+        Some(rest) => result.push(rest.trim_start_matches('/')),
+        None => result.push(path),
+    }
+    result
+}
+
 /// Store the config to file
 pub fn store_config() -> Result<()> {
     let default_config = toml::from_str::<ConfigBuilder<'static>>("")
@@ -217,14 +544,24 @@ pub fn store_config() -> Result<()> {
         .build();
     let write_config: ConfigBuilder = ConfigBuilder {
         main: Some(ConfigMainBuilder {
+            backend: Some(default_config.main.backend),
+            default_store: default_config.main.default_store,
+            age_identity_file: Some(default_config.main.age_identity_file),
+            agent_socket: default_config.main.agent_socket,
+            agent_timeout: Some(default_config.main.agent_timeout),
+            agent_cache_entries: Some(default_config.main.agent_cache_entries),
+            gui_editor: default_config.main.gui_editor,
             uuid_folder: Some(default_config.main.uuid_folder),
             index_entry: Some(default_config.main.index_entry),
-            index_file: Some(default_config.main.index_file),
+            index_file: None,
             sync_folder: Some(default_config.main.sync_folder),
             sync_commit_file: Some(default_config.main.sync_commit_file),
             last_command_file: Some(default_config.main.last_command_file),
             history_file: Some(default_config.main.history_file),
             history_days: Some(default_config.main.history_days),
+            min_strength: Some(default_config.main.min_strength),
+            refuse_weak: Some(default_config.main.refuse_weak),
+            hibp_file: default_config.main.hibp_file,
         }),
         theme: Some(ConfigThemeBuilder {
             theme_name: default_config.theme.theme_name,
@@ -240,6 +577,15 @@ pub fn store_config() -> Result<()> {
             path_key: Some(default_config.pass.path_key),
             url_key: Some(default_config.pass.url_key),
         }),
+        fields: Some(ConfigFieldsBuilder {
+            secret: Some(default_config.fields.secret),
+            secret_prefix: Some(default_config.fields.secret_prefix),
+            url: Some(default_config.fields.url),
+            email: Some(default_config.fields.email),
+        }),
+        stores: None,
+        templates: None,
+        policies: None,
     };
 
     let config_str = toml::to_string_pretty(&write_config).unwrap();
@@ -39,18 +39,46 @@ lazy_static! {
 }
 
 pub const ROOT_FOLDER: &str = ".password-store";
+pub const STORE_DIR_ENV: &str = "PASSWORD_STORE_DIR";
 pub const ENTRY_EXTENSION: &str = "gpg";
 pub const GIT_FOLDER: &str = ".git";
+pub const ATTACHMENTS_FOLDER: &str = "attachments";
+pub const ATTACHMENTS_SUFFIX: &str = ".attachments";
+pub const TRASH_FOLDER: &str = "trash";
+/// Number of days before its due date from which on a password is shown as due.
+pub const EXPIRY_WARNING_DAYS: i64 = 14;
+/// Number of days after which `rpass audit` reports a password that was never changed.
+pub const AUDIT_MAX_AGE_DAYS: i64 = 365;
+pub const GPG_TEXTCONV: &str =
+    "gpg2 -d --quiet --yes --compress-algo=none --no-encrypt-to --batch --use-agent";
 
 pub const DISPLAY_PATH: &str = "path:   ";
 pub const DISPLAY_UUID: &str = "uuid:   ";
 pub const DISPLAY_USER: &str = "user:   ";
 pub const DISPLAY_PASS: &str = "pass:   ";
 pub const DISPLAY_URL: &str = "url:   ";
+pub const DISPLAY_OTP: &str = "otp:   ";
+pub const DISPLAY_DUE: &str = "due:   ";
+pub const DISPLAY_STRENGTH: &str = "strength:   ";
 pub const DISPLAY_RAW: &str = "raw data ";
 
 pub const DISPLAY_RAW_SEP: &str = ":   ";
 
+/// Name of the field storing the notes of an entry.
+pub const NOTES_FIELD: &str = "notes";
+/// Name of the field storing the type of an entry.
+pub const TYPE_FIELD: &str = "type";
+/// Name of the fields linking the attachments of an entry.
+pub const ATTACHMENT_FIELD: &str = "attachment";
+/// Name of the field storing the date when the password of an entry expires.
+pub const EXPIRES_FIELD: &str = "expires";
+/// Name of the field storing how often the password of an entry must be changed.
+pub const ROTATE_FIELD: &str = "rotate-every";
+/// Name of the field storing the password policy of an entry.
+pub const POLICY_FIELD: &str = "policy";
+/// Name of the field storing the password rules of the site, like `len=16 charset=alnum`.
+pub const PWRULE_FIELD: &str = "pwrule";
+
 pub const DISPLAY_EMPTY: &str = "empty";
 
 pub const DISPLAY_BTN_SHOW_PWD: &str = "Show Password";
@@ -58,6 +86,7 @@ pub const DISPLAY_BTN_HIDE_PWD: &str = "Hide Password";
 pub const DISPLAY_BTN_EDIT_ENTRY: &str = "Edit entry";
 pub const DISPLAY_BTN_MAIN_MENU: &str = "Main menu";
 pub const DISPLAY_BTN_NEW_RAW: &str = "New raw line";
+pub const DISPLAY_BTN_EDIT_NOTES: &str = "Edit notes";
 pub const DISPLAY_BTN_DELETE: &str = "Delete";
 pub const DISPLAY_BTN_CANCEL: &str = "Cancel";
 pub const DISPLAY_BTN_NEW_PATH: &str = "New path";
@@ -65,6 +94,8 @@ pub const DISPLAY_BTN_TYPE_ONE_AT_A_TIME: &str = "Username, then password";
 pub const DISPLAY_BTN_TYPE_USERNAME: &str = "Only Username";
 pub const DISPLAY_BTN_TYPE_PASSWORD: &str = "Only Password";
 pub const DISPLAY_BTN_TYPE_BOTH: &str = "Both";
+pub const DISPLAY_BTN_TYPE_OTP: &str = "Type OTP";
+pub const DISPLAY_BTN_COPY_OTP: &str = "Copy OTP";
 pub const DISPLAY_BTN_SHOW: &str = "show";
 pub const DISPLAY_BTN_EXIT: &str = "exit";
 
@@ -73,6 +104,9 @@ pub const DISPLAY_BTN_MM_NEW: &str = "New Entry";
 pub const DISPLAY_BTN_MM_EDIT: &str = "Edit Entry";
 pub const DISPLAY_BTN_MM_EXIT: &str = "Exit";
 
+/// Choice in the "New Entry" flow to use the password policy of the folder.
+pub const DISPLAY_POLICY_FOLDER: &str = "policy of the folder";
+
 pub fn format_small(s: &str) -> String {
     SMALL_PANGO.build_content(s)
 }
@@ -15,6 +15,7 @@
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
 use thiserror::Error;
+use uuid::Uuid;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -32,20 +33,62 @@ pub enum Error {
     InvalidInput(&'static str),
     #[error("Index file does not contain the path: {0}")]
     UnknownPath(String),
+    #[error("Path {0} is claimed by two entries: {1} and {2}")]
+    PathConflict(String, Uuid, Uuid),
     #[error("Index file was not found!")]
     NoIndexFile,
+    #[error("Unknown store: {0}")]
+    UnknownStore(String),
+    #[error("Index version {0} is not supported, please update rpass!")]
+    IndexVersion(u32),
+    #[error("Trash index is corrupted: {0}")]
+    TrashIndex(String),
     #[error("Managed folder (uuids) was not found!")]
     ManagedFolderNotFound,
+    #[error("Could not read entry: {0}")]
+    EntryRead(String),
+    #[error("Could not encrypt entry: {0}")]
+    Encryption(String),
+    #[error("No recipients found for entry: {0}")]
+    NoRecipients(String),
+    #[error("OpenPGP Error: {0}")]
+    OpenPgp(String),
+    #[error("Unsupported OpenPGP message or key: {0}")]
+    OpenPgpUnsupported(String),
+    #[error("gpg-agent Error: {0}")]
+    GpgAgent(String),
+    #[error("Could not load age identities from {0}")]
+    AgeIdentity(String),
     #[error("Could not modify entry raw line: {0}")]
     EntryRawEdit(String),
     #[error("Entry does not have a path: {0}")]
     EntryWithoutPath(String),
+    #[error("Command failed: {0}")]
+    Command(String),
+    #[error("Agent Error: {0}")]
+    Agent(String),
     #[error("Sync Error: {0}!")]
     Sync(&'static str),
     #[error("Empty entry found: {0}")]
     EmptyEntry(String),
     #[error("Cannot create clipboard context")]
     Clipboard,
+    #[error("Unknown entry type: {0}")]
+    UnknownType(String),
+    #[error("Unknown password policy: {0}")]
+    UnknownPolicy(String),
+    #[error("Invalid password policy {0}: {1}")]
+    InvalidPolicy(String, &'static str),
+    #[error("Invalid {0}: {1}")]
+    InvalidField(String, &'static str),
+    #[error("No history of {0}")]
+    NoHistory(String),
+    #[error("Attachment Error: {0}")]
+    Attachment(String),
+    #[error("OTP Error: {0}")]
+    Otp(String),
+    #[error("Invalid regular expression: {0}")]
+    Regex(#[from] regex::Error),
     #[error("UUID Error: {0}")]
     Uuid(#[from] uuid::Error),
     #[error("Notification Error: {0}")]
@@ -58,6 +101,10 @@ pub enum Error {
     XDoCreation(#[from] libxdo::CreationError),
     #[error("XDo Error {0}")]
     XDo(#[from] libxdo::OpError),
+    #[error("Breach dump Error: {0}")]
+    Breaches(String),
+    #[error("Audit found {0} problems")]
+    AuditFailed(usize),
     #[error("{0}")]
     Other(String),
 }
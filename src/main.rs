@@ -19,7 +19,7 @@ use std::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, SubCommand};
 
 mod commands;
 mod config;
@@ -27,12 +27,13 @@ mod def;
 mod errors;
 mod pass;
 mod rofi_app;
+#[cfg(test)]
+mod tests;
 
 use config::CFG;
 use errors::{Error, Result};
 use rofi::{Rofi, RofiChild, Width};
 
-const DEFAULT_PW_SIZE: usize = 20;
 static ROFI_APP: AtomicBool = AtomicBool::new(false);
 
 fn main() {
@@ -40,6 +41,12 @@ fn main() {
         .version("0.3.1")
         .author("Tibor Schneider <tiborschneider@bluewin.ch>")
         .about("Manage pass without leaking information")
+        .arg(Arg::with_name("store")
+             .long("store")
+             .value_name("NAME")
+             .help("Only use the store NAME, configured in the [stores] table")
+             .takes_value(true)
+             .global(true))
         .subcommand(
             SubCommand::with_name("menu")
                 .about("Interactive app with rofi interface")
@@ -83,6 +90,11 @@ fn main() {
                      .long("username")
                      .help("only print out the username")
                      .takes_value(false))
+                .arg(Arg::with_name("reveal")
+                     .short("r")
+                     .long("reveal")
+                     .help("show secret fields")
+                     .takes_value(false))
         )
         .subcommand(
             SubCommand::with_name("edit")
@@ -100,6 +112,11 @@ fn main() {
                      .help("uuid of the key to edit")
                      .takes_value(true)
                      .conflicts_with("path"))
+                .arg(Arg::with_name("notes")
+                     .short("n")
+                     .long("notes")
+                     .help("only edit the multiline notes of the entry")
+                     .takes_value(false))
         )
         .subcommand(
             SubCommand::with_name("mv")
@@ -149,9 +166,16 @@ fn main() {
                 .arg(Arg::with_name("generate")
                      .short("g")
                      .long("generate")
-                     .help("automatically generate a password with 20 characters")
+                     .help("automatically generate a password, following the password policy")
                      .takes_value(false)
                      .conflicts_with("password"))
+                .arg(Arg::with_name("policy")
+                     .short("P")
+                     .long("policy")
+                     .value_name("POLICY")
+                     .help("generate a password with this policy, e.g., default, alphanumeric, pin or passphrase")
+                     .takes_value(true)
+                     .conflicts_with("password"))
         )
         .subcommand(
             SubCommand::with_name("insert")
@@ -177,18 +201,39 @@ fn main() {
                 .arg(Arg::with_name("generate")
                      .short("g")
                      .long("generate")
-                     .help("automatically generate a password with 20 characters")
+                     .help("automatically generate a password, following the password policy")
                      .takes_value(false)
                      .conflicts_with("password"))
+                .arg(Arg::with_name("policy")
+                     .short("P")
+                     .long("policy")
+                     .value_name("POLICY")
+                     .help("generate a password with this policy, e.g., default, alphanumeric, pin or passphrase")
+                     .takes_value(true)
+                     .conflicts_with("password"))
                 .arg(Arg::with_name("url")
                      .long("url")
                      .value_name("URL")
                      .help("url for the key")
                      .takes_value(true))
+                .arg(Arg::with_name("type")
+                     .short("t")
+                     .long("type")
+                     .value_name("TYPE")
+                     .help("type of the entry, e.g., login, card, ssh, wifi or api")
+                     .takes_value(true))
+                .arg(Arg::with_name("field")
+                     .short("f")
+                     .long("field")
+                     .value_name("NAME=VALUE")
+                     .help("value of a field of the entry")
+                     .takes_value(true)
+                     .multiple(true)
+                     .number_of_values(1))
         )
         .subcommand(
             SubCommand::with_name("rm")
-                .about("Delete an existing key")
+                .about("Move an existing key to the trash")
                 .arg(Arg::with_name("path")
                      .short("d")
                      .long("path")
@@ -207,11 +252,285 @@ fn main() {
                      .long("force")
                      .help("skip confirmation")
                      .takes_value(false))
+                .arg(Arg::with_name("permanent")
+                     .long("permanent")
+                     .help("delete the key permanently instead of moving it to the trash")
+                     .takes_value(false))
+        )
+        .subcommand(
+            SubCommand::with_name("trash")
+                .about("Manage deleted entries. Without subcommand, list them.")
+                .subcommand(
+                    SubCommand::with_name("ls")
+                        .about("List all entries in the trash")
+                )
+                .subcommand(
+                    SubCommand::with_name("restore")
+                        .about("Restore an entry from the trash (by default, the most recently deleted one)")
+                        .arg(Arg::with_name("path")
+                             .short("d")
+                             .long("path")
+                             .value_name("PATH")
+                             .help("path of the deleted key")
+                             .takes_value(true))
+                        .arg(Arg::with_name("uuid")
+                             .short("u")
+                             .long("uuid")
+                             .value_name("UUID")
+                             .help("uuid of the deleted key")
+                             .takes_value(true)
+                             .conflicts_with("path"))
+                )
+                .subcommand(
+                    SubCommand::with_name("empty")
+                        .about("Permanently delete the entries in the trash")
+                        .arg(Arg::with_name("older-than")
+                             .long("older-than")
+                             .value_name("DURATION")
+                             .help("only delete entries deleted before, e.g., 30d (units: s, m, h, d, w)")
+                             .takes_value(true))
+                )
         )
         .subcommand(
             SubCommand::with_name("ls")
                 .about("Lists all keys in a tree-like structure")
         )
+        .subcommand(
+            SubCommand::with_name("alias")
+                .about("Manage further paths of an entry. Without subcommand, list the aliases of an entry.")
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("List the entry under a further path")
+                        .arg(Arg::with_name("path")
+                             .short("d")
+                             .long("path")
+                             .value_name("PATH")
+                             .help("path of the key")
+                             .takes_value(true))
+                        .arg(Arg::with_name("uuid")
+                             .short("u")
+                             .long("uuid")
+                             .value_name("UUID")
+                             .help("uuid of the key")
+                             .takes_value(true)
+                             .conflicts_with("path"))
+                        .arg(Arg::with_name("alias")
+                             .value_name("ALIAS")
+                             .help("further path of the key")
+                             .required(true))
+                )
+                .subcommand(
+                    SubCommand::with_name("rm")
+                        .about("Remove an alias, keeping the entry")
+                        .arg(Arg::with_name("alias")
+                             .value_name("ALIAS")
+                             .help("alias to remove")
+                             .required(true))
+                )
+                .subcommand(
+                    SubCommand::with_name("ls")
+                        .about("List the aliases of an entry")
+                        .arg(Arg::with_name("path")
+                             .short("d")
+                             .long("path")
+                             .value_name("PATH")
+                             .help("path of the key")
+                             .takes_value(true))
+                        .arg(Arg::with_name("uuid")
+                             .short("u")
+                             .long("uuid")
+                             .value_name("UUID")
+                             .help("uuid of the key")
+                             .takes_value(true)
+                             .conflicts_with("path"))
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("expiring")
+                .about("List entries whose password must be changed soon")
+                .arg(Arg::with_name("within")
+                     .short("w")
+                     .long("within")
+                     .value_name("DURATION")
+                     .help("list entries due within this time, e.g., 14d (units: s, m, h, d, w)")
+                     .takes_value(true))
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Report reused, weak, incomplete and old passwords, and problems of the index")
+                .after_help("Exits with code 2 if any problem was found, and with code 1 on errors.")
+                .arg(Arg::with_name("json")
+                     .short("j")
+                     .long("json")
+                     .help("print the report as JSON")
+                     .takes_value(false))
+                .arg(Arg::with_name("max-age")
+                     .long("max-age")
+                     .value_name("DURATION")
+                     .help("report passwords without rotation which were not changed for this time (default: 365d)")
+                     .takes_value(true))
+                .arg(Arg::with_name("hibp")
+                     .long("hibp")
+                     .value_name("FILE")
+                     .help("look up all passwords in this local Have I Been Pwned SHA-1 dump, sorted by hash")
+                     .takes_value(true))
+        )
+        .subcommand(
+            SubCommand::with_name("attach")
+                .about("Attach a file to an entry")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+                .arg(Arg::with_name("file")
+                     .value_name("FILE")
+                     .help("file to attach")
+                     .required(true))
+                .arg(Arg::with_name("name")
+                     .short("n")
+                     .long("name")
+                     .value_name("NAME")
+                     .help("name of the attachment (defaults to the file name)")
+                     .takes_value(true))
+        )
+        .subcommand(
+            SubCommand::with_name("attachments")
+                .about("List the files attached to an entry")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+        )
+        .subcommand(
+            SubCommand::with_name("extract")
+                .about("Decrypt a file attached to an entry")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+                .arg(Arg::with_name("name")
+                     .value_name("NAME")
+                     .help("name of the attachment")
+                     .required(true))
+                .arg(Arg::with_name("output")
+                     .short("o")
+                     .long("output")
+                     .value_name("FILE")
+                     .help("file to write (defaults to NAME in the current folder)")
+                     .takes_value(true))
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("List previous versions of an entry and the values they changed")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+                .arg(Arg::with_name("reveal")
+                     .short("r")
+                     .long("reveal")
+                     .help("show the secrets instead of masking them")
+                     .takes_value(false))
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Restore a previous version of an entry")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+                .arg(Arg::with_name("version")
+                     .short("v")
+                     .long("version")
+                     .value_name("N")
+                     .help("version to restore, as listed by rpass history")
+                     .takes_value(true)
+                     .required(true))
+        )
+        .subcommand(
+            SubCommand::with_name("otp")
+                .about("Print the current one-time password of an entry")
+                .arg(Arg::with_name("path")
+                     .short("d")
+                     .long("path")
+                     .value_name("PATH")
+                     .help("path to the key")
+                     .takes_value(true))
+                .arg(Arg::with_name("uuid")
+                     .short("u")
+                     .long("uuid")
+                     .value_name("UUID")
+                     .help("uuid of the key")
+                     .takes_value(true)
+                     .conflicts_with("path"))
+                .arg(Arg::with_name("copy")
+                     .short("c")
+                     .long("copy")
+                     .help("copy the code to the clipboard instead of printing it")
+                     .takes_value(false))
+        )
+        .subcommand(
+            SubCommand::with_name("find")
+                .about("Search entries by their path, username, url and other fields")
+                .arg(Arg::with_name("terms")
+                     .value_name("TERM")
+                     .help("regular expression, optionally only matching a field (e.g., user:alice or url:github.com)")
+                     .required(true)
+                     .multiple(true))
+        )
+        .subcommand(
+            SubCommand::with_name("grep")
+                .about("Search entries with a line matching the regular expression (the password is not searched)")
+                .arg(Arg::with_name("pattern")
+                     .value_name("PATTERN")
+                     .required(true)
+                     .takes_value(true))
+        )
         .subcommand(
             SubCommand::with_name("fix-index")
                 .about("Checks all indices and fixes them")
@@ -237,6 +556,28 @@ fn main() {
                 //         .about("Starts the daemon for synchronization in the local network")
                 // )
         )
+        .subcommand(
+            SubCommand::with_name("agent")
+                .about("Keep the decrypted index in memory, such that it is not decrypted by every command")
+                .arg(Arg::with_name("timeout")
+                     .short("t")
+                     .long("timeout")
+                     .value_name("SECONDS")
+                     .help("forget everything and stop after being idle for SECONDS")
+                     .takes_value(true))
+        )
+        .subcommand(
+            SubCommand::with_name("lock")
+                .about("Make the agent forget all decrypted data and stop it")
+        )
+        .subcommand(
+            SubCommand::with_name("textconv")
+                .about("Print the decrypted content of a file, used by git to show diffs")
+                .setting(AppSettings::Hidden)
+                .arg(Arg::with_name("file")
+                     .required(true)
+                     .takes_value(true))
+        )
         .subcommand(
             SubCommand::with_name("default-config")
                 .about("Write the default config to disk")
@@ -256,6 +597,17 @@ actions.")
         )
         .get_matches();
 
+    // global arguments are only propagated to the subcommands
+    let store = matches
+        .value_of("store")
+        .or_else(|| matches.subcommand().1.and_then(|args| args.value_of("store")));
+    if let Some(name) = store {
+        if let Err(e) = pass::store::select(name) {
+            eprintln!("Error: {}", e);
+            exit(1);
+        }
+    }
+
     let result = match matches.subcommand() {
         ("menu", _) => {
             ROFI_APP.store(true, Ordering::Relaxed);
@@ -272,8 +624,14 @@ actions.")
             false,
             args.is_present("password"),
             args.is_present("username"),
+            args.is_present("reveal"),
+        ),
+        ("edit", Some(args)) => commands::edit(
+            args.value_of("path"),
+            args.value_of("uuid"),
+            false,
+            args.is_present("notes"),
         ),
-        ("edit", Some(args)) => commands::edit(args.value_of("path"), args.value_of("uuid"), false),
         ("mv", Some(args)) => commands::mv(
             args.value_of("path"),
             args.value_of("uuid"),
@@ -285,29 +643,77 @@ actions.")
             args.value_of("username"),
             args.value_of("password"),
             args.value_of("url"),
-            match args.is_present("generate") {
-                true => Some(DEFAULT_PW_SIZE),
-                false => None,
-            },
+            args.is_present("generate"),
+            args.value_of("policy"),
             false,
+            args.value_of("type"),
+            &args.values_of("field").map(|f| f.collect::<Vec<_>>()).unwrap_or_default(),
         ),
         ("passwd", Some(args)) => commands::passwd(
             args.value_of("path"),
             args.value_of("uuid"),
             args.value_of("password"),
-            match args.is_present("generate") {
-                true => Some(DEFAULT_PW_SIZE),
-                false => None,
-            },
+            args.is_present("generate"),
+            args.value_of("policy"),
             false,
         ),
         ("rm", Some(args)) => commands::delete(
             args.value_of("path"),
             args.value_of("uuid"),
             args.is_present("force"),
+            args.is_present("permanent"),
             false,
         ),
+        ("trash", Some(args)) => match args.subcommand() {
+            ("restore", Some(a)) => commands::trash::restore(a.value_of("path"), a.value_of("uuid")),
+            ("empty", Some(a)) => commands::trash::empty(a.value_of("older-than")),
+            _ => commands::trash::list(),
+        },
         ("ls", _) => commands::list(),
+        ("alias", Some(args)) => match args.subcommand() {
+            ("add", Some(a)) => commands::alias::add(a.value_of("path"), a.value_of("uuid"), a.value_of("alias").unwrap()),
+            ("rm", Some(a)) => commands::alias::remove(a.value_of("alias").unwrap()),
+            ("ls", Some(a)) => commands::alias::list(a.value_of("path"), a.value_of("uuid")),
+            _ => commands::alias::list(None, None),
+        },
+        ("expiring", Some(args)) => commands::expiring(args.value_of("within")),
+        ("audit", Some(args)) => commands::audit(
+            args.is_present("json"),
+            args.value_of("max-age"),
+            args.value_of("hibp"),
+        ),
+        ("attach", Some(args)) => commands::attach(
+            args.value_of("path"),
+            args.value_of("uuid"),
+            args.value_of("file").unwrap(),
+            args.value_of("name"),
+        ),
+        ("attachments", Some(args)) => commands::attachments(
+            args.value_of("path"),
+            args.value_of("uuid"),
+        ),
+        ("extract", Some(args)) => commands::extract(
+            args.value_of("path"),
+            args.value_of("uuid"),
+            args.value_of("name").unwrap(),
+            args.value_of("output"),
+        ),
+        ("history", Some(args)) => commands::history(
+            args.value_of("path"),
+            args.value_of("uuid"),
+            args.is_present("reveal"),
+        ),
+        ("restore", Some(args)) => match args.value_of("version").unwrap().parse() {
+            Ok(version) => commands::restore(args.value_of("path"), args.value_of("uuid"), version),
+            Err(_) => Err(Error::InvalidInput("The version must be a number")),
+        },
+        ("otp", Some(args)) => commands::otp(
+            args.value_of("path"),
+            args.value_of("uuid"),
+            args.is_present("copy"),
+        ),
+        ("find", Some(args)) => commands::find(&args.values_of("terms").unwrap().collect::<Vec<_>>()),
+        ("grep", Some(args)) => commands::grep(args.value_of("pattern").unwrap()),
         ("fix-index", _) => commands::fix_index(),
         ("sync", Some(args)) => match args.subcommand() {
             // ("repo", Some(a)) => commands::sync::sync(!a.is_present("debug")),
@@ -315,6 +721,12 @@ actions.")
             // ("daemon", _) => commands::sync::daemon(),
             _ => commands::sync::full(),
         },
+        ("agent", Some(args)) => match args.value_of("timeout").map(str::parse).transpose() {
+            Ok(timeout) => commands::agent(timeout),
+            Err(_) => Err(Error::InvalidInput("timeout must be a number of seconds")),
+        },
+        ("lock", _) => commands::lock(),
+        ("textconv", Some(args)) => commands::textconv(args.value_of("file").unwrap()),
         ("default-config", _) => config::store_config(),
         ("bulk-rename", _) => commands::bulk_rename(),
         _ => {
@@ -327,6 +739,10 @@ actions.")
         Ok(()) => {}
         Err(e) => match e {
             Error::Interrupted => {}
+            Error::AuditFailed(_) => {
+                eprintln!("{}", e);
+                exit(2);
+            }
             _ => {
                 eprintln!("Error: {:#?}", e);
                 exit(1);
@@ -337,7 +753,6 @@ actions.")
 
 pub struct Loading(Option<RofiChild<usize>>);
 
-#[allow(dead_code)]
 impl Loading {
     pub fn new(msg: impl Into<String>) -> Result<Self> {
         Ok(Self(if ROFI_APP.load(Ordering::Relaxed) {
@@ -0,0 +1,260 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Agent keeping decrypted files in memory, such that they don't need to be decrypted by every
+//! invocation of rpass. The agent never decrypts anything itself. Clients send it the content
+//! they have decrypted, together with the modification time of the file. Later, the content is
+//! only returned if the file was not modified since.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::DirBuilder;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{expand_path, CFG};
+use crate::errors::Result;
+use crate::pass::backend::get_backend;
+
+thread_local! {
+    static SOCKET: RefCell<Option<PathBuf>> = RefCell::new(default_socket());
+}
+
+/// Timeout for the communication with the agent.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Get the content of a file, if it was not modified since it was stored.
+    Get { key: String, modified: SystemTime },
+    /// Store the decrypted content of a file.
+    Put {
+        key: String,
+        modified: SystemTime,
+        content: String,
+    },
+    /// Forget everything and stop the agent.
+    Lock,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    Content(Option<String>),
+    Done,
+}
+
+/// Decrypted content of the files, together with their modification time.
+type Cache = Arc<Mutex<HashMap<String, (SystemTime, String)>>>;
+
+/// Location of the socket, either from the config, or in the runtime folder of the user. `None`
+/// if neither is set, as other folders might be shared with other users.
+pub fn default_socket() -> Option<PathBuf> {
+    match CFG.main.agent_socket {
+        Some(path) => Some(expand_path(path)),
+        None => dirs::runtime_dir().map(|dir| dir.join("rpass-agent.sock")),
+    }
+}
+
+/// Use the agent listening on `socket`, or disable the agent.
+#[cfg(test)]
+pub fn set_socket(socket: Option<PathBuf>) {
+    SOCKET.with(|s| s.replace(socket));
+}
+
+/// Send a request to the agent. Returns `None` if no agent is running.
+pub fn request(request: &Request) -> Option<Response> {
+    let socket = SOCKET.with(|s| s.borrow().clone())?;
+    let mut stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+    let mut line = serde_json::to_string(request).ok()?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).ok()?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).ok()?;
+    serde_json::from_str(&response).ok()
+}
+
+/// Key of a file of the current backend.
+fn key(name: &str) -> String {
+    get_backend().file(name).to_string_lossy().into_owned()
+}
+
+/// Read a file of the current backend, asking the agent first. The decrypted content is passed
+/// to the agent afterwards.
+pub fn read(name: &str) -> Result<String> {
+    let backend = get_backend();
+    let modified = match backend.modified(name) {
+        Ok(modified) => modified,
+        Err(_) => return backend.read(name),
+    };
+    let key = key(name);
+    let cached = request(&Request::Get {
+        key: key.clone(),
+        modified,
+    });
+    if let Some(Response::Content(Some(content))) = cached {
+        return Ok(content);
+    }
+    let content = backend.read(name)?;
+    request(&Request::Put {
+        key,
+        modified,
+        content: content.clone(),
+    });
+    Ok(content)
+}
+
+/// Write a file of the current backend, and pass the new content to the agent.
+pub fn write(name: &str, content: &str) -> Result<()> {
+    let backend = get_backend();
+    backend.write(name, content)?;
+    if let Ok(modified) = backend.modified(name) {
+        request(&Request::Put {
+            key: key(name),
+            modified,
+            content: content.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Create the socket of the agent. Missing folders are only accessible by the user, and the
+/// socket is never accessible by anyone else, not even right after it was created.
+pub fn bind(socket: &Path) -> Result<UnixListener> {
+    if let Some(parent) = socket.parent() {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)?;
+    }
+    // the umask is only changed while binding, and the agent does not create any other files
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(socket);
+    unsafe { libc::umask(umask) };
+    Ok(listener?)
+}
+
+/// Whether the other end of `stream` runs as the same user as the agent.
+fn same_user(stream: &UnixStream) -> bool {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    result == 0 && cred.uid == unsafe { libc::getuid() }
+}
+
+/// Serve requests on `listener` until the agent is locked, or until no request was received for
+/// `timeout`. Every client is answered in its own thread, such that a slow client never blocks
+/// the others. Clients of other users are refused. The socket is removed afterwards.
+pub fn serve(listener: UnixListener, socket: &Path, timeout: Duration) -> Result<()> {
+    let last_request = Arc::new(Mutex::new(Instant::now()));
+    watch_idle(socket.to_path_buf(), last_request.clone(), timeout);
+
+    let cache: Cache = Arc::new(Mutex::new(HashMap::new()));
+    let locked = Arc::new(AtomicBool::new(false));
+    for stream in listener.incoming() {
+        if locked.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) if same_user(&stream) => stream,
+            _ => continue,
+        };
+        *last_request.lock().unwrap() = Instant::now();
+        let (cache, locked, socket) = (cache.clone(), locked.clone(), socket.to_path_buf());
+        thread::spawn(move || {
+            if let Some(Request::Lock) = handle(stream, &cache) {
+                locked.store(true, Ordering::SeqCst);
+                // wake up the listener, such that it notices the lock
+                let _ = UnixStream::connect(&socket);
+            }
+        });
+    }
+    let _ = std::fs::remove_file(socket);
+    Ok(())
+}
+
+/// Lock the agent as soon as it was idle for `timeout`.
+fn watch_idle(socket: PathBuf, last_request: Arc<Mutex<Instant>>, timeout: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(timeout.min(TIMEOUT));
+        if !socket.exists() {
+            return;
+        }
+        if last_request.lock().unwrap().elapsed() >= timeout {
+            let lock = format!("{}\n", serde_json::to_string(&Request::Lock).unwrap());
+            let _ = UnixStream::connect(&socket)
+                .and_then(|mut stream| stream.write_all(lock.as_bytes()));
+            return;
+        }
+    });
+}
+
+/// Answer a single request, and return it.
+fn handle(stream: UnixStream, cache: &Cache) -> Option<Request> {
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    let mut line = String::new();
+    let mut reader = BufReader::new(&stream);
+    reader.read_line(&mut line).ok()?;
+    let request: Request = serde_json::from_str(&line).ok()?;
+    let mut cache = cache.lock().unwrap();
+    let response = match &request {
+        Request::Get { key, modified } => Response::Content(
+            cache
+                .get(key)
+                .filter(|(m, _)| m == modified)
+                .map(|(_, content)| content.clone()),
+        ),
+        Request::Put {
+            key,
+            modified,
+            content,
+        } => {
+            cache.insert(key.clone(), (*modified, content.clone()));
+            Response::Done
+        }
+        Request::Lock => {
+            cache.clear();
+            Response::Done
+        }
+    };
+    drop(cache);
+    let mut response = serde_json::to_string(&response).ok()?;
+    response.push('\n');
+    let _ = (&stream).write_all(response.as_bytes());
+    Some(request)
+}
@@ -0,0 +1,150 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use age::x25519;
+use age::{Decryptor, Encryptor, IdentityFile};
+
+use super::{
+    delete_entry, edit_entry, find_recipients, read_entry, read_entry_file, read_parallel,
+    write_atomic, write_entry, Backend, Cipher,
+};
+use crate::errors::{Error, Result};
+
+const RECIPIENTS_FILE: &str = ".age-recipients";
+const EXTENSION: &str = "age";
+const TEXTCONV: &str = "rpass textconv";
+
+/// Backend that encrypts the entries with age. The recipients are read from the closest
+/// `.age-recipients` file, and the entries are decrypted with the identities stored in the
+/// identity file. All changes are committed if the store is a git repository.
+pub struct Age {
+    root: PathBuf,
+    identity_file: PathBuf,
+    textconv: String,
+}
+
+impl Age {
+    pub fn new(root: impl Into<PathBuf>, identity_file: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            identity_file: identity_file.into(),
+            textconv: TEXTCONV.to_string(),
+        }
+    }
+
+    /// Use a different command for git to show the diff of encrypted files.
+    #[cfg(test)]
+    pub fn with_textconv(mut self, textconv: impl Into<String>) -> Self {
+        self.textconv = textconv.into();
+        self
+    }
+
+    /// Recipients stored in the `.age-recipients` file closest to the entry.
+    pub fn recipients(&self, name: &str) -> Result<Vec<x25519::Recipient>> {
+        find_recipients(&self.root, &self.file(name), RECIPIENTS_FILE)
+            .ok_or_else(|| Error::NoRecipients(name.to_string()))?
+            .into_iter()
+            .map(|r| {
+                x25519::Recipient::from_str(&r)
+                    .map_err(|_| Error::Encryption(format!("{} (invalid recipient {})", name, r)))
+            })
+            .collect()
+    }
+
+    fn identities(&self) -> Result<Vec<Box<dyn age::Identity>>> {
+        let file = self.identity_file.to_string_lossy().to_string();
+        IdentityFile::from_file(file.clone())
+            .map_err(|_| Error::AgeIdentity(file.clone()))?
+            .into_identities()
+            .map_err(|_| Error::AgeIdentity(file))
+    }
+}
+
+impl Cipher for Age {
+    fn decrypt(&self, file: &Path) -> Result<Option<String>> {
+        let identities = self.identities()?;
+        let ciphertext = match fs::read(file) {
+            Ok(c) => c,
+            Err(_) => return Ok(None),
+        };
+        let mut reader = match Decryptor::new_buffered(&ciphertext[..])
+            .and_then(|d| d.decrypt(identities.iter().map(|i| i.as_ref())))
+        {
+            Ok(r) => r,
+            Err(_) => return Ok(None),
+        };
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        Ok(Some(content))
+    }
+
+    fn encrypt(&self, name: &str, content: &str) -> Result<()> {
+        let recipients = self.recipients(name)?;
+        let file = self.file(name);
+
+        let encryptor = Encryptor::with_recipients(recipients.iter().map(|r| r as _))
+            .map_err(|_| Error::Encryption(name.to_string()))?;
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+        writer.write_all(content.as_bytes())?;
+        writer.finish()?;
+
+        write_atomic(&file, &ciphertext)
+    }
+}
+
+impl Backend for Age {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn extension(&self) -> &str {
+        EXTENSION
+    }
+
+    fn textconv(&self) -> Option<String> {
+        Some(self.textconv.clone())
+    }
+
+    fn read_all(&self, names: &[String]) -> Vec<Result<String>> {
+        read_parallel(self, names)
+    }
+
+    fn read(&self, name: &str) -> Result<String> {
+        read_entry(self, name)
+    }
+
+    fn read_file(&self, file: &Path) -> Result<String> {
+        read_entry_file(self, file)
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        write_entry(self, name, content)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        delete_entry(self, name)
+    }
+
+    fn edit(&self, name: &str) -> Result<()> {
+        edit_entry(self, name)
+    }
+}
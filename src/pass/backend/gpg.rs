@@ -0,0 +1,410 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::gpg_agent::GpgAgent;
+use super::openpgp::{self, EncryptedKey, Message, PublicKey, SessionKey};
+use super::{
+    commit_staged, delete_entry, edit_entry, find_recipients, read_entry, read_entry_file,
+    read_parallel, run_checked, staging_file, write_atomic, write_entry, Backend, Cipher,
+};
+use crate::errors::{Error, Result};
+
+const GPG_ID_FILE: &str = ".gpg-id";
+
+/// Backend that decrypts and encrypts the entries by itself, without going through `pass`. The
+/// recipients are read from the closest `.gpg-id` file, exactly like `pass` does, and all changes
+/// are committed if the store is a git repository. The session keys are decrypted by the
+/// gpg-agent, such that the secret keys never leave it. `gpg` itself only runs a few times to look
+/// up the keys, independent of the number of entries, and for messages or keys which use
+/// algorithms that are not implemented in `openpgp`.
+pub struct Gpg {
+    root: PathBuf,
+    homedir: Option<PathBuf>,
+    /// Secret keys, loaded on the first decryption, or `None` if they cannot be used.
+    keyring: OnceLock<Option<Keyring>>,
+    /// Encryption keys of the recipients of every `.gpg-id` file that was used.
+    recipient_keys: Mutex<HashMap<Vec<String>, Vec<PublicKey>>>,
+    /// Number of `gpg` processes that were started.
+    processes: AtomicUsize,
+}
+
+/// Secret key held by the gpg-agent.
+struct SecretKey {
+    public: PublicKey,
+    keygrip: String,
+    user_id: String,
+}
+
+/// Secret keys of the user, and the gpg-agent that holds them.
+struct Keyring {
+    keys: HashMap<[u8; 8], SecretKey>,
+    agent: Mutex<GpgAgent>,
+}
+
+impl Gpg {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            homedir: None,
+            keyring: OnceLock::new(),
+            recipient_keys: Mutex::new(HashMap::new()),
+            processes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Use a different GnuPG home directory than the default one.
+    #[cfg(test)]
+    pub fn with_homedir(mut self, homedir: impl Into<PathBuf>) -> Self {
+        self.homedir = Some(homedir.into());
+        self
+    }
+
+    /// Number of `gpg` processes that were started so far.
+    #[cfg(test)]
+    pub fn processes(&self) -> usize {
+        self.processes.load(Ordering::Relaxed)
+    }
+
+    fn command(&self, program: &str) -> Command {
+        self.processes.fetch_add(1, Ordering::Relaxed);
+        let mut cmd = Command::new(program);
+        if let Some(homedir) = self.homedir.as_ref() {
+            cmd.env("GNUPGHOME", homedir);
+        }
+        cmd
+    }
+
+    fn gpg(&self) -> Command {
+        let mut cmd = self.command("gpg");
+        cmd.arg("--batch").arg("--quiet").arg("--yes");
+        cmd
+    }
+
+    /// Recipients stored in the `.gpg-id` file closest to the entry.
+    pub fn recipients(&self, name: &str) -> Result<Vec<String>> {
+        find_recipients(&self.root, &self.file(name), GPG_ID_FILE)
+            .ok_or_else(|| Error::NoRecipients(name.to_string()))
+    }
+
+    /// Encryption keys of `recipients`, looked up with `gpg` the first time they are used.
+    fn recipient_keys(&self, recipients: &[String]) -> Result<Vec<PublicKey>> {
+        if let Some(keys) = self.recipient_keys.lock().unwrap().get(recipients) {
+            return Ok(keys.clone());
+        }
+        let mut fingerprints: Vec<String> = Vec::new();
+        for recipient in recipients {
+            let fingerprint = self.encryption_key(recipient)?;
+            if !fingerprints.contains(&fingerprint) {
+                fingerprints.push(fingerprint);
+            }
+        }
+
+        // export exactly the chosen subkeys
+        let output = run_checked(
+            self.gpg()
+                .args([
+                    "--no-armor",
+                    "--export-options",
+                    "export-minimal",
+                    "--export",
+                ])
+                .args(fingerprints.iter().map(|f| format!("{}!", f))),
+        )?;
+        let exported = openpgp::parse_keys(&output.stdout)?;
+        let keys = fingerprints
+            .iter()
+            .map(|fingerprint| {
+                exported
+                    .iter()
+                    .find(|key| hex(&key.fingerprint) == *fingerprint)
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::OpenPgpUnsupported(format!("key {} cannot be read", fingerprint))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.recipient_keys
+            .lock()
+            .unwrap()
+            .insert(recipients.to_vec(), keys.clone());
+        Ok(keys)
+    }
+
+    /// Fingerprint of the key that `gpg --recipient` uses for `recipient`: the newest valid
+    /// encryption key of the first matching key that has one. Keys which are not fully valid are
+    /// left to `gpg`, which decides whether they can be used.
+    fn encryption_key(&self, recipient: &str) -> Result<String> {
+        let unusable = || Error::OpenPgpUnsupported(format!("no usable key for {}", recipient));
+        let output = self
+            .gpg()
+            .args(["--with-colons", "--list-keys", "--", recipient])
+            .output()?;
+        if !output.status.success() {
+            return Err(unusable());
+        }
+
+        let valid = |validity: &str| validity == "u" || validity == "f";
+        let mut newest: Option<(u64, String)> = None;
+        let mut primary_valid = false;
+        let mut candidate = None;
+        for line in String::from_utf8(output.stdout)?.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+            let can_encrypt = valid(field(1)) && field(11).contains('e');
+            match field(0) {
+                "pub" if newest.is_some() => break,
+                "pub" => {
+                    primary_valid = valid(field(1)) && !field(11).contains('D');
+                    candidate = (primary_valid && can_encrypt).then(|| field(5));
+                }
+                "sub" => candidate = (primary_valid && can_encrypt).then(|| field(5)),
+                "fpr" => {
+                    if let Some(created) = candidate.take() {
+                        let created = created.parse().unwrap_or_default();
+                        if newest.as_ref().is_none_or(|(newest, _)| created >= *newest) {
+                            newest = Some((created, field(9).to_string()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        newest
+            .map(|(_, fingerprint)| fingerprint)
+            .ok_or_else(unusable)
+    }
+
+    fn keyring(&self) -> Option<&Keyring> {
+        self.keyring
+            .get_or_init(|| Keyring::load(self).ok())
+            .as_ref()
+    }
+
+    /// Decrypt a message with the gpg-agent. Fails with `Error::OpenPgpUnsupported` if `gpg`
+    /// must decrypt it instead.
+    fn decrypt_native(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let message = Message::parse(data)?;
+        let keyring = self.keyring().ok_or_else(|| {
+            Error::OpenPgpUnsupported("the secret keys cannot be loaded".to_string())
+        })?;
+        let mut result = Err(Error::OpenPgpUnsupported(
+            "no secret key of any recipient".to_string(),
+        ));
+        for key in message.keys.iter() {
+            if let Some(secret) = keyring.keys.get(&key.key_id) {
+                result = keyring
+                    .session_key(key, secret)
+                    .and_then(|session_key| message.decrypt(&session_key));
+                if result.is_ok() {
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    fn decrypt_with_gpg(&self, file: &Path) -> Result<Option<String>> {
+        let output = self.gpg().arg("--decrypt").arg(file).output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(output.stdout)?))
+    }
+
+    fn encrypt_with_gpg(&self, name: &str, recipients: &[String], content: &str) -> Result<()> {
+        let file = self.file(name);
+        fs::create_dir_all(file.parent().unwrap())?;
+
+        let mut cmd = self.gpg();
+        cmd.arg("--encrypt")
+            .arg("--no-encrypt-to")
+            .arg("--compress-algo=none")
+            .arg("--output")
+            .arg(staging_file(&file));
+        for recipient in recipients.iter() {
+            cmd.arg("--recipient").arg(recipient);
+        }
+        let mut p = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).spawn()?;
+
+        let written = (|| {
+            if let Some(mut writer) = p.stdin.take() {
+                writer.write_all(content.as_bytes())?;
+            }
+            match p.wait()?.success() {
+                true => Ok(()),
+                false => Err(Error::Encryption(name.to_string())),
+            }
+        })();
+        commit_staged(&file, written)
+    }
+}
+
+impl Keyring {
+    /// Look up the secret keys of the user with `gpg`, and connect to the gpg-agent.
+    fn load(gpg: &Gpg) -> Result<Self> {
+        let output =
+            run_checked(
+                gpg.gpg()
+                    .args(["--with-colons", "--with-keygrip", "--list-secret-keys"]),
+            )?;
+        // fingerprint, keygrip and user id of every key whose secret is held by the agent
+        let mut listed: Vec<(String, String, String)> = Vec::new();
+        let mut available = false;
+        let mut user_id = String::new();
+        for line in String::from_utf8(output.stdout)?.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            let field = |i: usize| fields.get(i).copied().unwrap_or_default().to_string();
+            match fields[0] {
+                "sec" | "ssb" => {
+                    if fields[0] == "sec" {
+                        user_id.clear();
+                    }
+                    // keys whose secret is stored elsewhere, e.g., on a smartcard, are left to gpg
+                    available = field(14) != "#";
+                    if available {
+                        listed.push(Default::default());
+                    }
+                }
+                "fpr" if available => listed.last_mut().unwrap().0 = field(9),
+                "grp" if available => listed.last_mut().unwrap().1 = field(9),
+                "uid" if user_id.is_empty() => {
+                    user_id = field(9).replace("\\x3a", ":");
+                    for key in listed.iter_mut().filter(|key| key.2.is_empty()) {
+                        key.2 = user_id.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if listed.is_empty() {
+            return Err(Error::OpenPgpUnsupported("no secret keys".to_string()));
+        }
+
+        let output = run_checked(
+            gpg.gpg()
+                .args([
+                    "--no-armor",
+                    "--export-options",
+                    "export-minimal",
+                    "--export",
+                ])
+                .args(listed.iter().map(|(fingerprint, _, _)| fingerprint)),
+        )?;
+        let public = openpgp::parse_keys(&output.stdout)?;
+        let mut keys = HashMap::new();
+        for (fingerprint, keygrip, user_id) in listed {
+            if let Some(key) = public.iter().find(|k| hex(&k.fingerprint) == fingerprint) {
+                let secret = SecretKey {
+                    public: key.clone(),
+                    keygrip,
+                    user_id,
+                };
+                keys.insert(key.key_id(), secret);
+            }
+        }
+
+        let output = run_checked(gpg.command("gpgconf").args(["--list-dirs", "agent-socket"]))?;
+        let socket = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+        Ok(Self {
+            keys,
+            agent: Mutex::new(GpgAgent::connect(&socket)?),
+        })
+    }
+
+    /// Let the agent decrypt the session key `key` with `secret`.
+    fn session_key(&self, key: &EncryptedKey, secret: &SecretKey) -> Result<SessionKey> {
+        let description = format!(
+            "Please enter the passphrase to unlock the OpenPGP secret key of\n\"{}\"\n\
+             to decrypt the password store.",
+            secret.user_id
+        );
+        let value = self
+            .agent
+            .lock()
+            .unwrap()
+            .decrypt(&secret.keygrip, &description, key)?;
+        key.session_key(&secret.public, &value)
+    }
+}
+
+/// Fingerprint in upper case hex, as printed by gpg.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+impl Cipher for Gpg {
+    fn decrypt(&self, file: &Path) -> Result<Option<String>> {
+        let data = match fs::read(file) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        match self.decrypt_native(&data) {
+            Ok(content) => Ok(Some(String::from_utf8(content)?)),
+            Err(Error::OpenPgpUnsupported(_)) => self.decrypt_with_gpg(file),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn encrypt(&self, name: &str, content: &str) -> Result<()> {
+        let recipients = self.recipients(name)?;
+        let encrypted = self
+            .recipient_keys(&recipients)
+            .and_then(|keys| openpgp::encrypt(&keys, content.as_bytes()));
+        match encrypted {
+            Ok(ciphertext) => write_atomic(&self.file(name), &ciphertext),
+            Err(Error::OpenPgpUnsupported(_)) => self.encrypt_with_gpg(name, &recipients, content),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Backend for Gpg {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn read_all(&self, names: &[String]) -> Vec<Result<String>> {
+        read_parallel(self, names)
+    }
+
+    fn read(&self, name: &str) -> Result<String> {
+        read_entry(self, name)
+    }
+
+    fn read_file(&self, file: &Path) -> Result<String> {
+        read_entry_file(self, file)
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        write_entry(self, name, content)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        delete_entry(self, name)
+    }
+
+    fn edit(&self, name: &str) -> Result<()> {
+        edit_entry(self, name)
+    }
+}
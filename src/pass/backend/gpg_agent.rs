@@ -0,0 +1,215 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Client of the gpg-agent, which holds the secret keys of GnuPG and asks for their passphrase
+//! with pinentry. The agent speaks the line based Assuan protocol on a unix socket.
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+use super::openpgp::{EncryptedKey, KeyCiphertext};
+use crate::errors::{Error, Result};
+
+/// Maximal number of raw bytes sent in a single data line, such that the escaped line stays below
+/// the limit of 1000 bytes.
+const DATA_CHUNK: usize = 300;
+
+/// Settings of the session which pinentry needs to show the passphrase prompt, as variables in
+/// the environment and the name of their agent option.
+const SESSION_OPTIONS: [(&str, &str); 4] = [
+    ("GPG_TTY", "ttyname"),
+    ("TERM", "ttytype"),
+    ("DISPLAY", "display"),
+    ("XAUTHORITY", "xauthority"),
+];
+
+pub struct GpgAgent {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl GpgAgent {
+    pub fn connect(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket).map_err(|e| {
+            Error::GpgAgent(format!("cannot connect to {}: {}", socket.display(), e))
+        })?;
+        let mut agent = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        agent.response(None)?;
+        for (var, option) in SESSION_OPTIONS.iter() {
+            if let Ok(value) = env::var(var) {
+                // an unknown option must not prevent decryption
+                let _ = agent.command(&format!("OPTION {}={}", option, value));
+            }
+        }
+        Ok(agent)
+    }
+
+    /// Decrypt the session key `key` with the secret key `keygrip`. `description` is shown when
+    /// the agent asks for the passphrase. Returns the decrypted value.
+    pub fn decrypt(
+        &mut self,
+        keygrip: &str,
+        description: &str,
+        key: &EncryptedKey,
+    ) -> Result<Vec<u8>> {
+        self.command(&format!("SETKEY {}", keygrip))?;
+        self.command(&format!("SETKEYDESC {}", escape_description(description)))?;
+        self.send(b"PKDECRYPT")?;
+        let result = self.response(Some(&ciphertext(key)?))?;
+
+        // the result is `(5:value<len>:<value>)`
+        let value = result
+            .strip_prefix(b"(5:value")
+            .and_then(|rest| {
+                let colon = rest.iter().position(|b| *b == b':')?;
+                let len: usize = std::str::from_utf8(&rest[..colon]).ok()?.parse().ok()?;
+                rest.get(colon + 1..colon + 1 + len)
+            })
+            .ok_or_else(|| Error::GpgAgent("invalid result of PKDECRYPT".to_string()))?;
+        Ok(value.to_vec())
+    }
+
+    fn command(&mut self, line: &str) -> Result<Vec<u8>> {
+        self.send(line.as_bytes())?;
+        self.response(None)
+    }
+
+    fn send(&mut self, line: &[u8]) -> Result<()> {
+        self.writer.write_all(line)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Read the response to a command, and return the data it contains. Inquiries of the agent
+    /// are answered with `inquiry`.
+    fn response(&mut self, inquiry: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Err(Error::GpgAgent("connection closed".to_string()));
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            match line.as_slice() {
+                [b'O', b'K'] | [b'O', b'K', b' ', ..] => return Ok(data),
+                [b'E', b'R', b'R', b' ', message @ ..] => {
+                    return Err(Error::GpgAgent(
+                        String::from_utf8_lossy(message).to_string(),
+                    ))
+                }
+                [b'D', b' ', rest @ ..] => data.extend(unescape(rest)),
+                [b'I', b'N', b'Q', b'U', b'I', b'R', b'E', b' ', ..] => {
+                    for chunk in inquiry.unwrap_or_default().chunks(DATA_CHUNK) {
+                        let mut line = b"D ".to_vec();
+                        line.extend(escape(chunk));
+                        self.send(&line)?;
+                    }
+                    self.send(b"END")?;
+                }
+                // status lines and comments
+                _ => {}
+            }
+        }
+    }
+}
+
+/// S-expression of the encrypted session key, in the format that gpg sends to the agent.
+fn ciphertext(key: &EncryptedKey) -> Result<Vec<u8>> {
+    let mut sexp = b"(7:enc-val".to_vec();
+    match &key.ciphertext {
+        KeyCiphertext::Rsa(a) => {
+            sexp.extend_from_slice(b"(3:rsa(1:a");
+            push_atom(&mut sexp, a);
+            sexp.extend_from_slice(b"))");
+        }
+        KeyCiphertext::Ecdh { ephemeral, wrapped } => {
+            sexp.extend_from_slice(b"(4:ecdh(1:s");
+            push_atom(&mut sexp, wrapped);
+            sexp.extend_from_slice(b")(1:e");
+            push_atom(&mut sexp, ephemeral);
+            sexp.extend_from_slice(b"))");
+        }
+        KeyCiphertext::Other(algo) => {
+            return Err(Error::OpenPgpUnsupported(format!(
+                "public key algorithm {}",
+                algo
+            )))
+        }
+    }
+    sexp.push(b')');
+    Ok(sexp)
+}
+
+fn push_atom(sexp: &mut Vec<u8>, value: &[u8]) {
+    sexp.extend_from_slice(format!("{}:", value.len()).as_bytes());
+    sexp.extend_from_slice(value);
+}
+
+/// Escape raw data for a data line.
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for b in data {
+        match b {
+            b'%' | b'\r' | b'\n' => escaped.extend(format!("%{:02X}", b).as_bytes()),
+            _ => escaped.push(*b),
+        }
+    }
+    escaped
+}
+
+/// Escape a text for a command, where spaces are written as `+`.
+fn escape_description(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b' ' => "+".to_string(),
+            b'+' | b'%' | b'"' | b':' => format!("%{:02X}", b),
+            b if !(0x20..0x80).contains(&b) => format!("%{:02X}", b),
+            b => (b as char).to_string(),
+        })
+        .collect()
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let decoded = match data[i] {
+            b'%' => data
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match decoded {
+            Some(b) => {
+                raw.push(b);
+                i += 3;
+            }
+            None => {
+                raw.push(data[i]);
+                i += 1;
+            }
+        }
+    }
+    raw
+}
@@ -0,0 +1,478 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::cell::RefCell;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::rc::Rc;
+use std::thread;
+use std::time::SystemTime;
+
+use dirs::home_dir;
+use uuid::Uuid;
+
+use crate::config::{BackendKind, ConfigStore, CFG};
+use crate::def;
+use crate::errors::{Error, Result};
+use crate::pass::index::{Index, INDEX};
+
+mod age;
+mod gpg;
+mod gpg_agent;
+mod openpgp;
+mod pass_cli;
+#[cfg(test)]
+mod plaintext;
+
+pub use self::age::Age;
+pub use gpg::Gpg;
+pub use pass_cli::PassCli;
+#[cfg(test)]
+pub use plaintext::Plaintext;
+
+const SECURE_TMP_DIR: &str = "/dev/shm";
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn Backend>> = RefCell::new(from_config());
+}
+
+/// Storage of the password store. Entries are always addressed by their name relative to the
+/// root of the store, without the file extension (e.g., `uuids/index`), just like `pass` does.
+pub trait Backend {
+    /// Root folder of the store on disk.
+    fn root(&self) -> &Path;
+
+    /// File extension of the stored entries.
+    fn extension(&self) -> &str {
+        def::ENTRY_EXTENSION
+    }
+
+    /// Command used by git to convert the stored files into text when showing a diff, or `None`
+    /// if the files are already stored as text.
+    fn textconv(&self) -> Option<String> {
+        Some(def::GPG_TEXTCONV.to_string())
+    }
+
+    /// Read and decrypt the content of an entry.
+    fn read(&self, name: &str) -> Result<String>;
+
+    /// Read and decrypt the entries `names`, returning their content in the same order.
+    fn read_all(&self, names: &[String]) -> Vec<Result<String>> {
+        names.iter().map(|name| self.read(name)).collect()
+    }
+
+    /// Read and decrypt any file that was encrypted like an entry, e.g., when git converts a
+    /// file for showing a diff.
+    fn read_file(&self, file: &Path) -> Result<String> {
+        Err(Error::EntryRead(file.to_string_lossy().to_string()))
+    }
+
+    /// Encrypt and write the content of an entry, replacing any previous content.
+    fn write(&self, name: &str, content: &str) -> Result<()>;
+
+    /// Remove an entry from the store.
+    fn delete(&self, name: &str) -> Result<()>;
+
+    /// Open the entry in the editor of the user, and store the modified content.
+    fn edit(&self, name: &str) -> Result<()>;
+
+    /// Let the user edit `content` (e.g., the notes of an entry) and return the result. If `gui`
+    /// is set, the editor is started in a new window.
+    fn edit_text(&self, content: &str, gui: bool) -> Result<String> {
+        match gui {
+            true => edit_content_with(content, &gui_editor()),
+            false => edit_content(content),
+        }
+    }
+
+    /// Path of the file on disk where the entry is stored.
+    fn file(&self, name: &str) -> PathBuf {
+        self.root().join(format!("{}.{}", name, self.extension()))
+    }
+
+    /// Names of all entries in `folder` and all its subfolders. Pass an empty string to list
+    /// the entire store. Git folders are always skipped.
+    fn list(&self, folder: &str) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        list_recursive(
+            &self.root().join(folder),
+            folder,
+            self.extension(),
+            &mut names,
+        )?;
+        names.sort();
+        Ok(names)
+    }
+
+    /// Last modification time of an entry.
+    fn modified(&self, name: &str) -> Result<SystemTime> {
+        Ok(fs::metadata(self.file(name))?.modified()?)
+    }
+}
+
+/// Backend which encrypts every entry into its own file by itself. The methods of `Backend` which
+/// read and write entries are shared by all of them, see `read_entry`, `write_entry`,
+/// `delete_entry` and `edit_entry`.
+trait Cipher: Backend {
+    /// Decrypt `file`, or return `None` if it cannot be decrypted.
+    fn decrypt(&self, file: &Path) -> Result<Option<String>>;
+
+    /// Encrypt `content` for the recipients of the entry `name`, and store it.
+    fn encrypt(&self, name: &str, content: &str) -> Result<()>;
+}
+
+fn read_entry(backend: &impl Cipher, name: &str) -> Result<String> {
+    match backend.decrypt(&backend.file(name))? {
+        Some(content) => Ok(content),
+        None => Err(Error::EntryRead(name.to_string())),
+    }
+}
+
+fn read_entry_file(backend: &impl Cipher, file: &Path) -> Result<String> {
+    match backend.decrypt(file)? {
+        Some(content) => Ok(content),
+        None => Err(Error::EntryRead(file.to_string_lossy().to_string())),
+    }
+}
+
+fn write_entry(backend: &impl Cipher, name: &str, content: &str) -> Result<()> {
+    backend.encrypt(name, content)?;
+    commit(
+        backend.root(),
+        &backend.file(name),
+        format!("Add given password for {} to store.", name),
+    )
+}
+
+fn delete_entry(backend: &impl Cipher, name: &str) -> Result<()> {
+    let file = backend.file(name);
+    remove_file(backend.root(), &file)?;
+    commit(
+        backend.root(),
+        &file,
+        format!("Remove {} from store.", name),
+    )
+}
+
+fn edit_entry(backend: &impl Cipher, name: &str) -> Result<()> {
+    let content = backend.read(name)?;
+    let new_content = edit_content(&content)?;
+
+    if new_content == content {
+        println!("Password for {} unchanged.", name);
+        return Ok(());
+    }
+
+    backend.encrypt(name, &new_content)?;
+    commit(
+        backend.root(),
+        &backend.file(name),
+        format!("Edit password for {} using {}.", name, editor()),
+    )
+}
+
+/// Read the entries `names` with `backend`, split among one thread per CPU.
+fn read_parallel(backend: &(impl Backend + Sync), names: &[String]) -> Vec<Result<String>> {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let chunk_size = names.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = names
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().map(|n| backend.read(n)).collect::<Vec<_>>())
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+fn list_recursive(dir: &Path, prefix: &str, ext: &str, names: &mut Vec<String>) -> Result<()> {
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let file_name = match path.file_name().and_then(|f| f.to_str()) {
+            Some(f) => f,
+            None => continue,
+        };
+        let name = match prefix {
+            "" => file_name.to_string(),
+            _ => format!("{}/{}", prefix, file_name),
+        };
+        if path.is_dir() {
+            if file_name != def::GIT_FOLDER {
+                list_recursive(&path, &name, ext, names)?;
+            }
+        } else if path.extension().map(|e| e == ext).unwrap_or(false) {
+            names.push(name[..name.len() - ext.len() - 1].to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Recipients stored in the file `id_file` closest to `file`, searching all parent folders up to
+/// the root of the store. Comments and empty lines are ignored.
+fn find_recipients(root: &Path, file: &Path, id_file: &str) -> Option<Vec<String>> {
+    let mut dir = file.to_path_buf();
+    while dir.pop() && dir.starts_with(root) {
+        let path = dir.join(id_file);
+        if let Ok(content) = fs::read_to_string(path) {
+            return Some(
+                content
+                    .lines()
+                    .map(|l| l.split('#').next().unwrap().trim())
+                    .filter(|l| !l.is_empty())
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+    }
+    None
+}
+
+/// Remove the file of an entry, and all parent folders that become empty, just like pass does.
+fn remove_file(root: &Path, file: &Path) -> Result<()> {
+    fs::remove_file(file)?;
+    let mut dir = file.to_path_buf();
+    while dir.pop() && dir != root && fs::read_dir(&dir)?.next().is_none() {
+        fs::remove_dir(&dir)?;
+    }
+    Ok(())
+}
+
+/// Temporary file next to `file`, which replaces `file` once it is written completely. It does
+/// not have the extension of the entries, so it is never listed.
+fn staging_file(file: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(file.file_name().unwrap_or_default());
+    name.push(".tmp");
+    file.with_file_name(name)
+}
+
+/// Replace `file` with its staged version, or remove the staged version if writing it failed.
+fn commit_staged<T>(file: &Path, written: Result<T>) -> Result<T> {
+    let staged = staging_file(file);
+    match written.and_then(|result| {
+        fs::rename(&staged, file)?;
+        Ok(result)
+    }) {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            let _ = fs::remove_file(&staged);
+            Err(e)
+        }
+    }
+}
+
+/// Write `content` to `file`, such that a crash never leaves a truncated file behind.
+fn write_atomic(file: &Path, content: &[u8]) -> Result<()> {
+    fs::create_dir_all(file.parent().unwrap())?;
+    let written = (|| {
+        let mut staged = fs::File::create(staging_file(file))?;
+        staged.write_all(content)?;
+        Ok(staged.sync_all()?)
+    })();
+    commit_staged(file, written)
+}
+
+/// Run `cmd`, and fail with its error output if it does not exit successfully.
+pub fn run_checked(cmd: &mut Command) -> Result<Output> {
+    let output = cmd.output()?;
+    match output.status.success() {
+        true => Ok(output),
+        false => Err(Error::Command(format!(
+            "{}: {}",
+            cmd.get_program().to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+/// Stage the changes to `files` in the git repository at `root`, including their removal.
+pub fn git_stage(root: &Path, files: &[PathBuf]) -> Result<()> {
+    let (present, removed): (Vec<&PathBuf>, Vec<&PathBuf>) = files.iter().partition(|f| f.exists());
+    if !present.is_empty() {
+        run_checked(
+            Command::new("git")
+                .args(["add", "--all", "--"])
+                .args(present)
+                .current_dir(root),
+        )?;
+    }
+    if !removed.is_empty() {
+        run_checked(
+            Command::new("git")
+                .args(["rm", "--cached", "--quiet", "--ignore-unmatch", "--"])
+                .args(removed)
+                .current_dir(root),
+        )?;
+    }
+    Ok(())
+}
+
+/// Commit the changes to the file if the store is a git repository.
+fn commit(root: &Path, file: &Path, message: String) -> Result<()> {
+    if !root.join(def::GIT_FOLDER).is_dir() {
+        return Ok(());
+    }
+    // e.g., the working copies of the synchronization
+    let ignored = Command::new("git")
+        .args(["check-ignore", "--quiet", "--"])
+        .arg(file)
+        .current_dir(root)
+        .status()?;
+    if ignored.success() {
+        return Ok(());
+    }
+    git_stage(root, &[file.to_path_buf()])?;
+    // writing the same content again leaves nothing to commit
+    let staged = Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .current_dir(root)
+        .status()?;
+    if staged.success() {
+        return Ok(());
+    }
+    run_checked(
+        Command::new("git")
+            .arg("commit")
+            .arg("--quiet")
+            .arg("-m")
+            .arg(message)
+            .current_dir(root),
+    )?;
+    Ok(())
+}
+
+fn editor() -> String {
+    env::var("EDITOR").unwrap_or_else(|_| "vi".to_string())
+}
+
+/// Editor used from rofi: `gui_editor` from the config, or the editor of the user in a terminal.
+fn gui_editor() -> String {
+    match CFG.main.gui_editor {
+        Some(editor) => editor.to_string(),
+        None => format!(
+            "{} -e {}",
+            env::var("TERMINAL").unwrap_or_else(|_| "xterm".to_string()),
+            editor()
+        ),
+    }
+}
+
+/// Let the user edit `content` in the editor and return the result. The content is stored in a
+/// private temporary file, preferably in memory, which is removed afterwards.
+fn edit_content(content: &str) -> Result<String> {
+    edit_content_with(content, &editor())
+}
+
+/// Like `edit_content`, but using the command `editor`.
+fn edit_content_with(content: &str, editor: &str) -> Result<String> {
+    let tmp_dir = match Path::new(SECURE_TMP_DIR).is_dir() {
+        true => PathBuf::from(SECURE_TMP_DIR),
+        false => env::temp_dir(),
+    };
+    let tmp_file = tmp_dir.join(format!("rpass.{}.txt", Uuid::new_v4()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp_file)?
+        .write_all(content.as_bytes())?;
+
+    let result = edit_file(&tmp_file, editor).and_then(|_| Ok(fs::read_to_string(&tmp_file)?));
+    fs::remove_file(&tmp_file)?;
+    result
+}
+
+fn edit_file(file: &Path, editor: &str) -> Result<()> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program).args(parts).arg(file).status()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(Error::Interrupted),
+    }
+}
+
+/// Location of the default password store: `PASSWORD_STORE_DIR` if set, the `default_store`
+/// from the configuration, or `~/.password-store`.
+pub fn default_root() -> PathBuf {
+    if let Some(dir) = env::var_os(def::STORE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    match default_store() {
+        Some(store) => store.root(),
+        None => {
+            let mut root = home_dir().unwrap();
+            root.push(def::ROOT_FOLDER);
+            root
+        }
+    }
+}
+
+fn default_store() -> Option<&'static ConfigStore<'static>> {
+    let name = CFG.main.default_store?;
+    let store = CFG.stores.iter().find(|s| s.name == name);
+    if store.is_none() {
+        eprintln!("[Warning] default store {} is not configured", name);
+    }
+    store
+}
+
+/// Create a backend of the given kind for the store at `root`.
+pub fn new_backend(kind: BackendKind, root: PathBuf) -> Rc<dyn Backend> {
+    match kind {
+        BackendKind::Pass => Rc::new(PassCli::new(root)),
+        BackendKind::Gpg => Rc::new(Gpg::new(root)),
+        BackendKind::Age => {
+            let mut identity_file = home_dir().unwrap();
+            identity_file.push(CFG.main.age_identity_file);
+            Rc::new(Age::new(root, identity_file))
+        }
+    }
+}
+
+/// Create the backend of the default store.
+fn from_config() -> Rc<dyn Backend> {
+    let kind = match (env::var_os(def::STORE_DIR_ENV), default_store()) {
+        (None, Some(store)) => store.backend.unwrap_or(CFG.main.backend),
+        _ => CFG.main.backend,
+    };
+    new_backend(kind, default_root())
+}
+
+/// Get the backend which is currently in use.
+pub fn get_backend() -> Rc<dyn Backend> {
+    BACKEND.with(|backend| backend.borrow().clone())
+}
+
+/// Replace the backend which is used from now on. This also invalidates the cached index.
+#[cfg(test)]
+pub fn set_backend(new: impl Backend + 'static) {
+    replace_backend(Rc::new(new))
+}
+
+/// Replace the backend which is used from now on by a shared one, e.g., of another store.
+pub fn replace_backend(new: Rc<dyn Backend>) {
+    BACKEND.with(|backend| backend.replace(new));
+    INDEX.with(|index| index.replace(Index::default()));
+}
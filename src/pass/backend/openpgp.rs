@@ -0,0 +1,690 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Just enough of OpenPGP (RFC 4880 and RFC 6637) to read and write the files of a password
+//! store without starting `gpg`: the session key is encrypted to RSA or Curve25519 keys, and the
+//! content is encrypted with AES and protected by a modification detection code. Secret keys are
+//! never used here, the session key is decrypted by the gpg-agent instead. Anything else fails
+//! with `Error::OpenPgpUnsupported`, such that the caller can fall back to `gpg`.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::Read;
+
+use aes::{Aes128, Aes192, Aes256};
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use base64::Engine;
+use cfb_mode::cipher::{AsyncStreamCipher, KeyIvInit};
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rsa::{BigUint, Pkcs1v15Encrypt, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha384, Sha512};
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519Public};
+
+use crate::errors::{Error, Result};
+
+const TAG_PKESK: u8 = 1;
+const TAG_SIGNATURE: u8 = 2;
+const TAG_SKESK: u8 = 3;
+const TAG_ONE_PASS_SIGNATURE: u8 = 4;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_COMPRESSED: u8 = 8;
+const TAG_SED: u8 = 9;
+const TAG_MARKER: u8 = 10;
+const TAG_LITERAL: u8 = 11;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+const TAG_SEIPD: u8 = 18;
+const TAG_AEAD: u8 = 20;
+
+const RSA: u8 = 1;
+const RSA_ENCRYPT_ONLY: u8 = 2;
+const ECDH: u8 = 18;
+
+const AES128: u8 = 7;
+const AES192: u8 = 8;
+const AES256: u8 = 9;
+
+const SHA256: u8 = 8;
+const SHA384: u8 = 9;
+const SHA512: u8 = 10;
+
+const UNCOMPRESSED: u8 = 0;
+const ZIP: u8 = 1;
+const ZLIB: u8 = 2;
+
+/// Object identifier of Curve25519 for ECDH.
+const CURVE25519_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x97, 0x55, 0x01, 0x05, 0x01];
+/// Block size of AES, which is the only supported cipher.
+const BLOCK_SIZE: usize = 16;
+/// Header and length of the modification detection code packet.
+const MDC_HEADER: [u8; 2] = [0xd3, 0x14];
+
+fn invalid(what: impl Into<String>) -> Error {
+    Error::OpenPgp(what.into())
+}
+
+fn unsupported(what: impl Into<String>) -> Error {
+    Error::OpenPgpUnsupported(what.into())
+}
+
+/// Public key or subkey of version 4.
+#[derive(Debug, Clone)]
+pub struct PublicKey {
+    pub fingerprint: [u8; 20],
+    material: KeyMaterial,
+}
+
+#[derive(Debug, Clone)]
+enum KeyMaterial {
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// Curve25519 point, with the hash and cipher used to derive the key encryption key.
+    Cv25519 {
+        point: [u8; 32],
+        hash: u8,
+        cipher: u8,
+    },
+    /// Public key algorithm or curve which is not supported.
+    Other(u8),
+}
+
+impl PublicKey {
+    /// The lower 64 bits of the fingerprint.
+    pub fn key_id(&self) -> [u8; 8] {
+        let mut id = [0; 8];
+        id.copy_from_slice(&self.fingerprint[12..]);
+        id
+    }
+}
+
+/// All public keys and subkeys of version 4 in `data`, e.g., the output of `gpg --export`.
+pub fn parse_keys(data: &[u8]) -> Result<Vec<PublicKey>> {
+    let data = dearmor(data)?;
+    let mut keys = Vec::new();
+    for packet in packets(&data)? {
+        if packet.tag == TAG_PUBLIC_KEY || packet.tag == TAG_PUBLIC_SUBKEY {
+            keys.extend(parse_key(&packet.body)?);
+        }
+    }
+    Ok(keys)
+}
+
+fn parse_key(body: &[u8]) -> Result<Option<PublicKey>> {
+    if body.first() != Some(&4) {
+        return Ok(None);
+    }
+    let mut hasher = Sha1::new();
+    hasher.update([0x99]);
+    hasher.update((body.len() as u16).to_be_bytes());
+    hasher.update(body);
+    let mut fingerprint = [0; 20];
+    fingerprint.copy_from_slice(&hasher.finalize());
+
+    let (header, rest) = split(body, 6)?;
+    let material = match header[5] {
+        RSA | RSA_ENCRYPT_ONLY => {
+            let (n, rest) = read_mpi(rest)?;
+            let (e, _) = read_mpi(rest)?;
+            KeyMaterial::Rsa {
+                n: n.to_vec(),
+                e: e.to_vec(),
+            }
+        }
+        ECDH => {
+            let (oid, rest) = read_sized(rest)?;
+            let (point, rest) = read_mpi(rest)?;
+            let (kdf, _) = read_sized(rest)?;
+            match (oid, point, kdf) {
+                (CURVE25519_OID, [0x40, point @ ..], [1, hash, cipher]) if point.len() == 32 => {
+                    KeyMaterial::Cv25519 {
+                        point: <[u8; 32]>::try_from(point).unwrap(),
+                        hash: *hash,
+                        cipher: *cipher,
+                    }
+                }
+                _ => KeyMaterial::Other(ECDH),
+            }
+        }
+        algo => KeyMaterial::Other(algo),
+    };
+    Ok(Some(PublicKey {
+        fingerprint,
+        material,
+    }))
+}
+
+/// Symmetric key of a message, together with the id of its cipher.
+pub struct SessionKey {
+    cipher: u8,
+    key: Vec<u8>,
+}
+
+impl SessionKey {
+    fn generate() -> Self {
+        let mut key = vec![0; 32];
+        OsRng.fill_bytes(&mut key);
+        Self {
+            cipher: AES256,
+            key,
+        }
+    }
+
+    /// Cipher id, key and checksum, as encrypted for every recipient.
+    fn encode(&self) -> Vec<u8> {
+        let checksum = self
+            .key
+            .iter()
+            .map(|b| *b as u16)
+            .fold(0, u16::wrapping_add);
+        let mut data = vec![self.cipher];
+        data.extend_from_slice(&self.key);
+        data.extend_from_slice(&checksum.to_be_bytes());
+        data
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let (cipher, rest) = data
+            .split_first()
+            .ok_or_else(|| invalid("empty session key"))?;
+        if rest.len() != key_len(*cipher)? + 2 {
+            return Err(invalid("session key of the wrong length"));
+        }
+        let (key, checksum) = rest.split_at(rest.len() - 2);
+        let session_key = Self {
+            cipher: *cipher,
+            key: key.to_vec(),
+        };
+        match session_key.encode().ends_with(checksum) {
+            true => Ok(session_key),
+            false => Err(invalid("wrong checksum of the session key")),
+        }
+    }
+}
+
+/// Session key of a message, encrypted to the key `key_id`.
+pub struct EncryptedKey {
+    pub key_id: [u8; 8],
+    pub ciphertext: KeyCiphertext,
+}
+
+pub enum KeyCiphertext {
+    Rsa(Vec<u8>),
+    /// Ephemeral point, and the wrapped session key prefixed with its length.
+    Ecdh {
+        ephemeral: Vec<u8>,
+        wrapped: Vec<u8>,
+    },
+    Other(u8),
+}
+
+impl EncryptedKey {
+    fn parse(body: &[u8]) -> Result<Self> {
+        let (header, rest) = split(body, 10)?;
+        if header[0] != 3 {
+            return Err(unsupported(format!(
+                "session key packet version {}",
+                header[0]
+            )));
+        }
+        let ciphertext = match header[9] {
+            RSA | RSA_ENCRYPT_ONLY => KeyCiphertext::Rsa(read_mpi(rest)?.0.to_vec()),
+            ECDH => {
+                let (ephemeral, rest) = read_mpi(rest)?;
+                let (wrapped, _) = read_sized(rest)?;
+                KeyCiphertext::Ecdh {
+                    ephemeral: ephemeral.to_vec(),
+                    wrapped: rest[..=wrapped.len()].to_vec(),
+                }
+            }
+            algo => KeyCiphertext::Other(algo),
+        };
+        Ok(Self {
+            key_id: <[u8; 8]>::try_from(&header[1..9]).unwrap(),
+            ciphertext,
+        })
+    }
+
+    /// Session key, given the `value` which the gpg-agent decrypted with the secret of `key`.
+    pub fn session_key(&self, key: &PublicKey, value: &[u8]) -> Result<SessionKey> {
+        match (&self.ciphertext, &key.material) {
+            (KeyCiphertext::Rsa(_), KeyMaterial::Rsa { .. }) => {
+                SessionKey::decode(unpad_pkcs1(value)?)
+            }
+            (KeyCiphertext::Ecdh { wrapped, .. }, KeyMaterial::Cv25519 { hash, cipher, .. }) => {
+                let shared = match value {
+                    [0x40, shared @ ..] if shared.len() == 32 => shared,
+                    _ => return Err(invalid("shared secret of the wrong length")),
+                };
+                let kek = kdf(*hash, *cipher, shared, key)?;
+                let padded = unwrap_key(*cipher, &kek, &wrapped[1..])?;
+                SessionKey::decode(unpad_pkcs5(&padded)?)
+            }
+            _ => Err(unsupported("public key algorithm of the recipient")),
+        }
+    }
+}
+
+/// Encrypted message, as written by `gpg --encrypt`.
+pub struct Message {
+    pub keys: Vec<EncryptedKey>,
+    data: Vec<u8>,
+}
+
+impl Message {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let data = dearmor(data)?;
+        let mut keys = Vec::new();
+        for packet in packets(&data)? {
+            match packet.tag {
+                TAG_PKESK => keys.push(EncryptedKey::parse(&packet.body)?),
+                TAG_MARKER => {}
+                TAG_SEIPD => {
+                    return Ok(Self {
+                        keys,
+                        data: packet.body,
+                    })
+                }
+                TAG_SKESK | TAG_SED | TAG_AEAD => {
+                    return Err(unsupported(format!("packet {}", packet.tag)))
+                }
+                tag => return Err(invalid(format!("unexpected packet {}", tag))),
+            }
+        }
+        Err(invalid("no encrypted data"))
+    }
+
+    /// Decrypt the content of the message with its session key.
+    pub fn decrypt(&self, session_key: &SessionKey) -> Result<Vec<u8>> {
+        let (version, data) = self
+            .data
+            .split_first()
+            .ok_or_else(|| invalid("empty encrypted data"))?;
+        if *version != 1 {
+            return Err(unsupported(format!("encrypted data version {}", version)));
+        }
+        let mut data = data.to_vec();
+        cfb(session_key, &mut data, false)?;
+        if data.len() < BLOCK_SIZE + 2 + 22 {
+            return Err(invalid("encrypted data is too short"));
+        }
+        if data[BLOCK_SIZE - 2..BLOCK_SIZE] != data[BLOCK_SIZE..BLOCK_SIZE + 2] {
+            return Err(invalid("wrong session key"));
+        }
+        let (content, mdc) = data.split_at(data.len() - 20);
+        if !content.ends_with(&MDC_HEADER) || Sha1::digest(content)[..] != *mdc {
+            return Err(invalid("the message was modified"));
+        }
+        literal_data(&content[BLOCK_SIZE + 2..content.len() - 2])
+    }
+}
+
+/// Encrypt `content` with AES-256 for all `recipients`, like `gpg --encrypt` does.
+pub fn encrypt(recipients: &[PublicKey], content: &[u8]) -> Result<Vec<u8>> {
+    let session_key = SessionKey::generate();
+    let mut message = Vec::new();
+    for recipient in recipients {
+        let body = encrypt_session_key(recipient, &session_key)?;
+        write_packet(&mut message, TAG_PKESK, &body);
+    }
+
+    // random prefix, whose last two bytes are repeated to detect a wrong session key
+    let mut data = vec![0; BLOCK_SIZE + 2];
+    OsRng.fill_bytes(&mut data[..BLOCK_SIZE]);
+    data.copy_within(BLOCK_SIZE - 2..BLOCK_SIZE, BLOCK_SIZE);
+    // binary literal data without file name and date
+    let mut literal = vec![b'b', 0, 0, 0, 0, 0];
+    literal.extend_from_slice(content);
+    write_packet(&mut data, TAG_LITERAL, &literal);
+    data.extend_from_slice(&MDC_HEADER);
+    let mdc = Sha1::digest(&data);
+    data.extend_from_slice(&mdc);
+    cfb(&session_key, &mut data, true)?;
+
+    let mut body = vec![1];
+    body.extend_from_slice(&data);
+    write_packet(&mut message, TAG_SEIPD, &body);
+    Ok(message)
+}
+
+fn encrypt_session_key(recipient: &PublicKey, session_key: &SessionKey) -> Result<Vec<u8>> {
+    let mut body = vec![3];
+    body.extend_from_slice(&recipient.key_id());
+    match &recipient.material {
+        KeyMaterial::Rsa { n, e } => {
+            let key = RsaPublicKey::new_with_max_size(
+                BigUint::from_bytes_be(n),
+                BigUint::from_bytes_be(e),
+                16384,
+            )
+            .map_err(|e| invalid(format!("invalid RSA key: {}", e)))?;
+            let encrypted = key
+                .encrypt(&mut OsRng, Pkcs1v15Encrypt, &session_key.encode())
+                .map_err(|e| invalid(format!("RSA encryption failed: {}", e)))?;
+            body.push(RSA);
+            write_mpi(&mut body, &encrypted);
+        }
+        KeyMaterial::Cv25519 {
+            point,
+            hash,
+            cipher,
+        } => {
+            let secret = EphemeralSecret::random_from_rng(OsRng);
+            let ephemeral = X25519Public::from(&secret);
+            let shared = secret.diffie_hellman(&X25519Public::from(*point));
+            let kek = kdf(*hash, *cipher, shared.as_bytes(), recipient)?;
+            let wrapped = wrap_key(*cipher, &kek, &pad_pkcs5(session_key.encode()))?;
+            body.push(ECDH);
+            let mut point = vec![0x40];
+            point.extend_from_slice(ephemeral.as_bytes());
+            write_mpi(&mut body, &point);
+            body.push(wrapped.len() as u8);
+            body.extend_from_slice(&wrapped);
+        }
+        KeyMaterial::Other(algo) => {
+            return Err(unsupported(format!("public key algorithm {}", algo)))
+        }
+    }
+    Ok(body)
+}
+
+/// Key encryption key of ECDH, derived from the shared secret and the key of the recipient.
+fn kdf(hash: u8, cipher: u8, shared: &[u8], key: &PublicKey) -> Result<Vec<u8>> {
+    let mut input = vec![0, 0, 0, 1];
+    input.extend_from_slice(shared);
+    input.push(CURVE25519_OID.len() as u8);
+    input.extend_from_slice(CURVE25519_OID);
+    input.extend_from_slice(&[ECDH, 3, 1, hash, cipher]);
+    input.extend_from_slice(b"Anonymous Sender    ");
+    input.extend_from_slice(&key.fingerprint);
+    let digest = match hash {
+        SHA256 => Sha256::digest(&input).to_vec(),
+        SHA384 => Sha384::digest(&input).to_vec(),
+        SHA512 => Sha512::digest(&input).to_vec(),
+        _ => return Err(unsupported(format!("hash algorithm {}", hash))),
+    };
+    let len = key_len(cipher)?;
+    Ok(digest[..len].to_vec())
+}
+
+fn key_len(cipher: u8) -> Result<usize> {
+    match cipher {
+        AES128 => Ok(16),
+        AES192 => Ok(24),
+        AES256 => Ok(32),
+        _ => Err(unsupported(format!("cipher {}", cipher))),
+    }
+}
+
+fn wrap_key(cipher: u8, kek: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![0; data.len() + 8];
+    match cipher {
+        AES128 => KekAes128::try_from(kek).and_then(|k| k.wrap(data, &mut out)),
+        AES192 => KekAes192::try_from(kek).and_then(|k| k.wrap(data, &mut out)),
+        AES256 => KekAes256::try_from(kek).and_then(|k| k.wrap(data, &mut out)),
+        _ => return Err(unsupported(format!("cipher {}", cipher))),
+    }
+    .map_err(|_| invalid("cannot wrap the session key"))?;
+    Ok(out)
+}
+
+fn unwrap_key(cipher: u8, kek: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let len = data
+        .len()
+        .checked_sub(8)
+        .ok_or_else(|| invalid("wrapped session key is too short"))?;
+    let mut out = vec![0; len];
+    match cipher {
+        AES128 => KekAes128::try_from(kek).and_then(|k| k.unwrap(data, &mut out)),
+        AES192 => KekAes192::try_from(kek).and_then(|k| k.unwrap(data, &mut out)),
+        AES256 => KekAes256::try_from(kek).and_then(|k| k.unwrap(data, &mut out)),
+        _ => return Err(unsupported(format!("cipher {}", cipher))),
+    }
+    .map_err(|_| invalid("cannot unwrap the session key"))?;
+    Ok(out)
+}
+
+/// Encrypt or decrypt `data` in CFB mode, without the resynchronization of old OpenPGP packets.
+fn cfb(session_key: &SessionKey, data: &mut [u8], encrypt: bool) -> Result<()> {
+    macro_rules! apply {
+        ($cipher:ty) => {
+            match encrypt {
+                true => cfb_mode::Encryptor::<$cipher>::new_from_slices(&session_key.key, &[0; 16])
+                    .map(|c| c.encrypt(data)),
+                false => {
+                    cfb_mode::Decryptor::<$cipher>::new_from_slices(&session_key.key, &[0; 16])
+                        .map(|c| c.decrypt(data))
+                }
+            }
+        };
+    }
+    match session_key.cipher {
+        AES128 => apply!(Aes128),
+        AES192 => apply!(Aes192),
+        AES256 => apply!(Aes256),
+        cipher => return Err(unsupported(format!("cipher {}", cipher))),
+    }
+    .map_err(|_| invalid("session key of the wrong length"))
+}
+
+/// Pad to a multiple of 8 bytes, as required by the key wrapping of ECDH.
+fn pad_pkcs5(mut data: Vec<u8>) -> Vec<u8> {
+    let padding = 8 - data.len() % 8;
+    data.resize(data.len() + padding, padding as u8);
+    data
+}
+
+fn unpad_pkcs5(data: &[u8]) -> Result<&[u8]> {
+    let padding = *data.last().unwrap_or(&0) as usize;
+    match padding > 0 && padding <= data.len() {
+        true if data[data.len() - padding..]
+            .iter()
+            .all(|b| *b as usize == padding) =>
+        {
+            Ok(&data[..data.len() - padding])
+        }
+        _ => Err(invalid("wrong padding of the session key")),
+    }
+}
+
+/// Remove the PKCS #1 v1.5 padding of a decrypted RSA value, whose leading zero may be missing.
+fn unpad_pkcs1(value: &[u8]) -> Result<&[u8]> {
+    let value = value.strip_prefix(&[0]).unwrap_or(value);
+    match value.split_first() {
+        Some((2, rest)) => match rest.iter().position(|b| *b == 0) {
+            Some(i) if i >= 8 => Ok(&rest[i + 1..]),
+            _ => Err(invalid("wrong padding of the session key")),
+        },
+        _ => Err(invalid("wrong padding of the session key")),
+    }
+}
+
+/// Content of the literal data packet in the decrypted `data`.
+fn literal_data(data: &[u8]) -> Result<Vec<u8>> {
+    for packet in packets(data)? {
+        match packet.tag {
+            TAG_LITERAL => {
+                let (header, rest) = split(&packet.body, 2)?;
+                let (_, rest) = split(rest, header[1] as usize)?;
+                let (_, content) = split(rest, 4)?;
+                return Ok(content.to_vec());
+            }
+            TAG_COMPRESSED => return literal_data(&decompress(&packet.body)?),
+            TAG_ONE_PASS_SIGNATURE | TAG_SIGNATURE | TAG_MARKER => {}
+            tag => return Err(invalid(format!("unexpected packet {}", tag))),
+        }
+    }
+    Err(invalid("no literal data"))
+}
+
+fn decompress(body: &[u8]) -> Result<Vec<u8>> {
+    let (algo, data) = body
+        .split_first()
+        .ok_or_else(|| invalid("empty compressed data"))?;
+    let mut out = Vec::new();
+    match *algo {
+        UNCOMPRESSED => out.extend_from_slice(data),
+        ZIP => {
+            DeflateDecoder::new(data).read_to_end(&mut out)?;
+        }
+        ZLIB => {
+            ZlibDecoder::new(data).read_to_end(&mut out)?;
+        }
+        algo => return Err(unsupported(format!("compression algorithm {}", algo))),
+    }
+    Ok(out)
+}
+
+/// Binary content of `data`, which may be ASCII armored.
+fn dearmor(data: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !data.starts_with(b"-----BEGIN PGP ") {
+        return Ok(Cow::Borrowed(data));
+    }
+    let text = std::str::from_utf8(data).map_err(|_| invalid("armor is not ASCII"))?;
+    let base64: String = text
+        .lines()
+        .skip(1)
+        .skip_while(|line| !line.trim().is_empty())
+        .skip(1)
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('=') && !line.starts_with("-----"))
+        .collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(base64)
+        .map(Cow::Owned)
+        .map_err(|_| invalid("invalid armor"))
+}
+
+struct Packet {
+    tag: u8,
+    body: Vec<u8>,
+}
+
+fn packets(mut data: &[u8]) -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    while !data.is_empty() {
+        let (packet, rest) = read_packet(data)?;
+        packets.push(packet);
+        data = rest;
+    }
+    Ok(packets)
+}
+
+fn read_packet(data: &[u8]) -> Result<(Packet, &[u8])> {
+    let (ctb, mut data) = match data.split_first() {
+        Some((ctb, data)) if ctb & 0x80 != 0 => (*ctb, data),
+        _ => return Err(invalid("not an OpenPGP packet")),
+    };
+
+    // old format, where the length may extend to the end of the data
+    if ctb & 0x40 == 0 {
+        let (len, rest) = match ctb & 0x03 {
+            0 => (read_number(data, 1)?, &data[1..]),
+            1 => (read_number(data, 2)?, &data[2..]),
+            2 => (read_number(data, 4)?, &data[4..]),
+            _ => (data.len(), data),
+        };
+        let (body, rest) = split(rest, len)?;
+        let packet = Packet {
+            tag: (ctb >> 2) & 0x0f,
+            body: body.to_vec(),
+        };
+        return Ok((packet, rest));
+    }
+
+    // new format, where the body may be split into parts
+    let mut body = Vec::new();
+    loop {
+        let (len, partial, rest) = match data {
+            [a, rest @ ..] if *a < 192 => (*a as usize, false, rest),
+            [a, b, rest @ ..] if *a < 224 => {
+                (((*a as usize - 192) << 8) + *b as usize + 192, false, rest)
+            }
+            [255, rest @ ..] => (read_number(rest, 4)?, false, &rest[4..]),
+            [a, rest @ ..] => (1 << (a & 0x1f), true, rest),
+            [] => return Err(invalid("truncated OpenPGP packet")),
+        };
+        let (part, rest) = split(rest, len)?;
+        body.extend_from_slice(part);
+        data = rest;
+        if !partial {
+            break;
+        }
+    }
+    Ok((
+        Packet {
+            tag: ctb & 0x3f,
+            body,
+        },
+        data,
+    ))
+}
+
+fn write_packet(out: &mut Vec<u8>, tag: u8, body: &[u8]) {
+    out.push(0xc0 | tag);
+    match body.len() {
+        len if len < 192 => out.push(len as u8),
+        len if len < 8384 => {
+            out.push(((len - 192) >> 8) as u8 + 192);
+            out.push((len - 192) as u8);
+        }
+        len => {
+            out.push(255);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(body);
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8])> {
+    match data.len() >= len {
+        true => Ok(data.split_at(len)),
+        false => Err(invalid("truncated OpenPGP packet")),
+    }
+}
+
+/// Big endian number of `len` bytes.
+fn read_number(data: &[u8], len: usize) -> Result<usize> {
+    let (bytes, _) = split(data, len)?;
+    Ok(bytes.iter().fold(0, |n, b| (n << 8) | *b as usize))
+}
+
+/// Field prefixed with its length in a single byte.
+fn read_sized(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (len, rest) = data
+        .split_first()
+        .ok_or_else(|| invalid("truncated OpenPGP packet"))?;
+    split(rest, *len as usize)
+}
+
+fn read_mpi(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let bits = read_number(data, 2)?;
+    split(&data[2..], bits.div_ceil(8))
+}
+
+fn write_mpi(out: &mut Vec<u8>, value: &[u8]) {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    let bits = match value.first() {
+        Some(first) => value.len() * 8 - first.leading_zeros() as usize,
+        None => 0,
+    };
+    out.extend_from_slice(&(bits as u16).to_be_bytes());
+    out.extend_from_slice(value);
+}
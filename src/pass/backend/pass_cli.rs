@@ -0,0 +1,124 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::{read_parallel, run_checked, staging_file, Backend};
+use crate::def;
+use crate::errors::{Error, Result};
+
+/// Backend that calls the `pass` command line tool for every operation.
+pub struct PassCli {
+    root: PathBuf,
+}
+
+impl PassCli {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Call pass on the store at the root of the backend.
+    fn pass(&self) -> Command {
+        let mut cmd = Command::new("pass");
+        cmd.env(def::STORE_DIR_ENV, &self.root);
+        cmd
+    }
+}
+
+impl Backend for PassCli {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn read_all(&self, names: &[String]) -> Vec<Result<String>> {
+        read_parallel(self, names)
+    }
+
+    fn read(&self, name: &str) -> Result<String> {
+        let output = self.pass().arg(name).output()?;
+        if !output.status.success() {
+            return Err(Error::EntryRead(name.to_string()));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn read_file(&self, file: &Path) -> Result<String> {
+        // pass cannot decrypt files outside of the store, so call gpg like git does
+        let mut args = def::GPG_TEXTCONV.split_whitespace();
+        let output = Command::new(args.next().unwrap())
+            .args(args)
+            .arg(file)
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::EntryRead(file.to_string_lossy().to_string()));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        // pass writes the file in place, so keep the previous version until it succeeded.
+        let file = self.file(name);
+        let backup = staging_file(&file);
+        if file.exists() {
+            fs::copy(&file, &backup)?;
+        }
+
+        let written = (|| {
+            let mut p = self
+                .pass()
+                .arg("insert")
+                .arg("--multiline")
+                .arg(name)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()?;
+
+            if let Some(mut writer) = p.stdin.take() {
+                writer.write_all(content.as_bytes())?;
+            }
+
+            match p.wait()?.success() {
+                true => Ok(()),
+                false => Err(Error::Encryption(name.to_string())),
+            }
+        })();
+
+        if backup.exists() {
+            match written {
+                Ok(()) => fs::remove_file(&backup)?,
+                Err(_) => fs::rename(&backup, &file)?,
+            }
+        }
+        written
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        run_checked(self.pass().arg("rm").arg("--force").arg(name))?;
+        Ok(())
+    }
+
+    fn edit(&self, name: &str) -> Result<()> {
+        // the editor runs in the terminal, so there is no error output to report
+        let status = self.pass().arg("edit").arg(name).status()?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(Error::Command(format!("pass edit {}: {}", name, status))),
+        }
+    }
+}
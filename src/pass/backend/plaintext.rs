@@ -0,0 +1,94 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{read_parallel, remove_file, write_atomic, Backend};
+use crate::errors::{Error, Result};
+
+type Editor = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Backend storing all entries unencrypted as text files. This must only be used for testing!
+pub struct Plaintext {
+    root: PathBuf,
+    editor: Option<Editor>,
+}
+
+impl Plaintext {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            editor: None,
+        }
+    }
+
+    /// Use the function `editor` instead of the editor of the user to edit entries.
+    pub fn with_editor(mut self, editor: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.editor = Some(Box::new(editor));
+        self
+    }
+}
+
+impl Backend for Plaintext {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn textconv(&self) -> Option<String> {
+        None
+    }
+
+    fn read_all(&self, names: &[String]) -> Vec<Result<String>> {
+        read_parallel(self, names)
+    }
+
+    fn read(&self, name: &str) -> Result<String> {
+        fs::read_to_string(self.file(name)).map_err(|_| Error::EntryRead(name.to_string()))
+    }
+
+    fn read_file(&self, file: &Path) -> Result<String> {
+        fs::read_to_string(file).map_err(|_| Error::EntryRead(file.to_string_lossy().to_string()))
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        write_atomic(&self.file(name), content.as_bytes())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        remove_file(&self.root, &self.file(name))
+    }
+
+    fn edit(&self, name: &str) -> Result<()> {
+        let editor = match self.editor.as_ref() {
+            Some(editor) => editor,
+            None => return Err(Error::Other("Plaintext backend has no editor".to_string())),
+        };
+        let content = editor(&self.read(name)?);
+        self.write(name, &content)
+    }
+
+    fn edit_text(&self, content: &str, _gui: bool) -> Result<String> {
+        match self.editor.as_ref() {
+            Some(editor) => Ok(editor(content)),
+            None => Err(Error::Other("Plaintext backend has no editor".to_string())),
+        }
+    }
+}
@@ -14,15 +14,25 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
+use std::collections::BTreeMap;
 use std::fmt;
-use std::io::Write;
-use std::process::{Command, Stdio};
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::NaiveDate;
 use uuid::Uuid;
 
 use crate::config::CFG;
 use crate::errors::{Error, Result};
-use crate::pass::index;
+use crate::pass::agent;
+use crate::pass::backend::get_backend;
+use crate::pass::expiry;
+use crate::pass::field::{Field, FieldKind};
+use crate::pass::generator::Policy;
+use crate::pass::index::{self, IndexRecord, Transaction};
+use crate::pass::otp::Totp;
+use crate::pass::strength::Strength;
+use crate::pass::template::{Template, TemplateField};
 use crate::{def, Loading};
 
 #[derive(Clone)]
@@ -31,9 +41,34 @@ pub struct Entry {
     pub password: String,
     pub path: Option<String>,
     pub url: Option<String>,
+    /// `otpauth://` URI to generate one-time passwords.
+    pub otp: Option<String>,
     pub uuid: Uuid,
-    pub raw: String,
+    /// Custom fields, in the order of the file.
+    pub fields: Vec<Field>,
+    /// Order of the lines in the file, used to write the entry without reordering it.
+    layout: Vec<Slot>,
+    trailing_newline: bool,
     pub hidden: bool,
+    /// Mask secret fields even while the password is shown.
+    pub mask_secrets: bool,
+    /// Time since which the password is used, in seconds since the unix epoch. Read from the
+    /// index by `Entry::get`.
+    password_since: Option<u64>,
+}
+
+/// Line of an entry file. The known values are stored together with their key, as it is written
+/// in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Slot {
+    Username(String),
+    Url(String),
+    Path(String),
+    Otp(String),
+    /// Key and value of the uuid line.
+    Uuid(String, String),
+    /// The next custom field.
+    Field,
 }
 
 impl fmt::Display for Entry {
@@ -53,6 +88,12 @@ impl fmt::Display for Entry {
         if let Some(ref url) = self.url {
             writeln!(f, "    url:      {}", url)?;
         }
+        if self.otp.is_some() {
+            writeln!(f, "    otp:      {}", self.display_otp())?;
+        }
+        for field in self.fields.iter().filter(|f| !f.name.is_empty()) {
+            writeln!(f, "    {}: {}", field.name, self.rofi_value(field))?;
+        }
         Ok(())
     }
 }
@@ -68,26 +109,204 @@ impl fmt::Debug for Entry {
             false => self.password.clone(),
         };
         writeln!(f, "    password: {}", hidden_pw)?;
+        writeln!(f, "    strength: {}", Strength::of(&self.password))?;
+        if let Some(warning) = self.expiry_warning() {
+            writeln!(f, "    due:      {}", warning)?;
+        }
         if let Some(ref path) = self.path {
             writeln!(f, "    path:     {}", path)?;
         }
         if let Some(ref url) = self.url {
             writeln!(f, "    url:      {}", url)?;
         }
-        let mut raw_printed = false;
-        for line in self.raw.lines() {
-            if !raw_printed {
-                writeln!(f, "    raw:")?;
-                raw_printed = true;
+        if self.otp.is_some() {
+            writeln!(f, "    otp:      {}", self.display_otp())?;
+        }
+        let mut fields = self
+            .fields
+            .iter()
+            .filter(|f| !f.is_blank() && f.kind != FieldKind::Note)
+            .peekable();
+        if fields.peek().is_some() {
+            writeln!(f, "    raw:")?;
+        }
+        for field in fields {
+            writeln!(f, "        {}", self.shown(field).to_text())?;
+        }
+        for note in self.fields.iter().filter(|f| f.kind == FieldKind::Note) {
+            writeln!(f, "    {}:", note.name)?;
+            for line in self.shown(note).value.lines() {
+                writeln!(f, "        {}", line)?;
             }
-            writeln!(f, "        {}", line)?;
         }
         Ok(())
     }
 }
 
-#[allow(dead_code)]
 impl Entry {
+    /// Current one-time password, generated from the `otpauth://` URI of the entry.
+    pub fn otp_code(&self) -> Result<String> {
+        match self.otp.as_ref() {
+            Some(uri) => Ok(Totp::parse(uri)?.code()),
+            None => Err(Error::Otp(format!("Entry {} has no otp", self.uuid))),
+        }
+    }
+
+    /// One-time password as it is shown to the user, respecting `hidden`.
+    fn display_otp(&self) -> String {
+        let totp = self.otp.as_deref().map(Totp::parse);
+        match (self.hidden, totp) {
+            (true, _) => "*".repeat(6),
+            (false, Some(Ok(totp))) => format!("{} ({}s left)", totp.code(), totp.remaining()),
+            (false, _) => "invalid".to_string(),
+        }
+    }
+
+    /// Value of the custom field `name` (case insensitive).
+    pub fn field_value(&self, name: &str) -> Option<&str> {
+        let key = name.to_lowercase();
+        self.fields
+            .iter()
+            .find(|f| f.key() == key)
+            .map(|f| f.value.trim())
+    }
+
+    /// Set the value of the custom field `name`, or add the field if the entry has none.
+    pub fn set_field(&mut self, name: &str, value: &str) {
+        let key = name.to_lowercase();
+        match self.fields.iter_mut().find(|f| f.key() == key) {
+            Some(field) => field.value = value.to_string(),
+            None => self.push_field(name, value),
+        }
+    }
+
+    /// Name of the entry type, as stored in the `type:` field.
+    pub fn entry_type(&self) -> Option<&str> {
+        self.field_value(def::TYPE_FIELD)
+    }
+
+    /// Password policy of the entry: the policy `name` if given, the one in its `policy:` field,
+    /// or the one of its folder, changed by the rules in its `pwrule:` field.
+    pub fn policy(&self, name: Option<&str>) -> Result<Policy> {
+        let policy = Policy::select(
+            name.or_else(|| self.field_value(def::POLICY_FIELD)),
+            self.path.as_deref().unwrap_or_default(),
+        )?;
+        match self.field_value(def::PWRULE_FIELD) {
+            Some(rule) => policy.with_rule(rule),
+            None => Ok(policy),
+        }
+    }
+
+    /// Date at which the password must be changed, if the entry expires.
+    pub fn due_date(&self) -> Option<NaiveDate> {
+        expiry::due_date(
+            self.field_value(def::EXPIRES_FIELD),
+            self.field_value(def::ROTATE_FIELD),
+            self.password_since,
+        )
+    }
+
+    /// Warning shown if the password is due within the next days, e.g., `expires in 3 days`.
+    pub fn expiry_warning(&self) -> Option<String> {
+        self.due_date()
+            .filter(|due| expiry::is_due(*due, def::EXPIRY_WARNING_DAYS))
+            .map(|due| format!("Password {}", expiry::describe(due)))
+    }
+
+    /// Type of the entry, or `None` for entries of unknown types.
+    pub fn template(&self) -> Option<Template> {
+        Template::get(
+            self.entry_type()
+                .unwrap_or(crate::pass::template::DEFAULT_TYPE),
+        )
+        .ok()
+    }
+
+    /// Definition of the custom field in the type of the entry.
+    fn template_field(&self, field: &Field) -> Option<TemplateField> {
+        self.template().and_then(|t| t.field(&field.name).cloned())
+    }
+
+    /// Whether the custom field is secret, either by its name or by the type of the entry.
+    fn is_secret(&self, field: &Field) -> bool {
+        field.kind == FieldKind::Secret || self.template_field(field).is_some_and(|f| f.secret)
+    }
+
+    /// Custom field as it is shown to the user. Secrets are masked, unless `hidden` is unset.
+    fn shown(&self, field: &Field) -> Field {
+        if !(self.hidden || self.mask_secrets) || !self.is_secret(field) {
+            return field.clone();
+        }
+        let mut shown = field.clone();
+        shown.value = match self.template_field(field) {
+            Some(f) => f.format.mask(&field.value),
+            None => field.masked().value,
+        };
+        shown
+    }
+
+    /// Value of the custom field, as it is shown in rofi. Notes only show their first line.
+    fn rofi_value(&self, field: &Field) -> String {
+        let value = self.shown(field).value;
+        match value.split_once('\n') {
+            Some((first, rest)) if !rest.is_empty() => format!("{} ...", first),
+            Some((first, _)) => first.to_string(),
+            None => value,
+        }
+    }
+
+    /// Password, username, url, path, otp and all named custom fields of the entry, as
+    /// `(name, value, shown value)`. Secrets are masked in the shown value while `hidden` is set.
+    pub fn named_values(&self) -> Vec<(String, String, String)> {
+        let mask = |value: &str| match self.hidden {
+            true => "*".repeat(value.chars().count()),
+            false => value.to_string(),
+        };
+        let mut values = vec![(
+            "password".to_string(),
+            self.password.clone(),
+            mask(&self.password),
+        )];
+        let known = [
+            ("user", &self.username),
+            ("url", &self.url),
+            ("path", &self.path),
+        ];
+        for (name, value) in known.iter() {
+            if let Some(value) = value {
+                values.push((name.to_string(), value.clone(), value.clone()));
+            }
+        }
+        if let Some(otp) = self.otp.as_ref() {
+            values.push(("otp".to_string(), otp.clone(), mask(otp)));
+        }
+        for field in self.fields.iter().filter(|f| !f.name.is_empty()) {
+            // fields with the same name are numbered, e.g., `attachment (2)`
+            let count = values.iter().filter(|(n, _, _)| {
+                n == &field.name || n.starts_with(&format!("{} (", field.name))
+            });
+            let name = match count.count() {
+                0 => field.name.clone(),
+                n => format!("{} ({})", field.name, n + 1),
+            };
+            values.push((name, field.value.clone(), self.rofi_value(field)));
+        }
+        values
+    }
+
+    /// Index of the custom field, which is either written as `line` in the file, or shown as
+    /// `line` in rofi (without markup).
+    pub fn field_at_line(&self, line: &str) -> Option<usize> {
+        self.fields.iter().position(|f| {
+            let shown = match f.name.is_empty() {
+                true => self.rofi_value(f),
+                false => format!("{}{}{}", f.name, def::DISPLAY_RAW_SEP, self.rofi_value(f)),
+            };
+            f.to_text() == line || shown == line
+        })
+    }
+
     pub fn new(
         username: Option<String>,
         password: String,
@@ -99,9 +318,14 @@ impl Entry {
             password,
             path: Some(path),
             url,
+            otp: None,
             uuid: Uuid::new_v4(),
-            raw: String::new(),
+            fields: Vec::new(),
+            layout: Vec::new(),
+            trailing_newline: true,
             hidden: true,
+            mask_secrets: false,
+            password_since: None,
         }
     }
 
@@ -111,6 +335,10 @@ impl Entry {
             println!("[Warning] Fixing UUID stored in entry {}", id);
             e.uuid = id;
         }
+        e.password_since = index::get_records()
+            .ok()
+            .and_then(|records| records.into_iter().find(|r| r.uuid == id))
+            .and_then(|r| r.password_since());
         Ok(e)
     }
 
@@ -119,46 +347,69 @@ impl Entry {
         S: AsRef<str>,
     {
         let _loading = Loading::new("Decrypting password...")?;
+        let content = match CFG.main.agent_cache_entries {
+            true => agent::read(path.as_ref())?,
+            false => get_backend().read(path.as_ref())?,
+        };
+        Entry::parse(path.as_ref(), &content)
+    }
+
+    /// Parse the content of the file `name`. Writing the entry back without changing it results
+    /// in the same content.
+    pub fn parse(name: &str, content: &str) -> Result<Entry> {
         let mut e = Entry {
             username: None,
             password: String::new(),
             path: None,
             url: None,
+            otp: None,
             uuid: Uuid::nil(),
-            raw: String::new(),
+            fields: Vec::new(),
+            layout: Vec::new(),
+            trailing_newline: content.ends_with('\n'),
             hidden: true,
+            mask_secrets: false,
+            password_since: None,
         };
 
-        let raw = String::from_utf8(Command::new("pass").arg(path.as_ref()).output()?.stdout)?;
-
-        // parse the raw content, and remove the lines which are parsed (or add the lines which are not parsed to raw)
-        let mut lines = raw.lines();
+        let mut lines = content.split_terminator('\n').peekable();
         e.password = match lines.next() {
             Some(s) => s.to_string(),
-            None => return Err(Error::EmptyEntry(path.as_ref().to_string())),
+            None => return Err(Error::EmptyEntry(name.to_string())),
         };
 
-        // search for username and path
-        for line in lines {
-            let line_lower = line.to_lowercase();
-            if line_lower.starts_with(CFG.pass.user_key) {
-                e.username = Some(line[CFG.pass.user_key.len()..].to_string());
-            } else if line_lower.starts_with(CFG.pass.user_key_alt) {
-                e.username = Some(line[CFG.pass.user_key_alt.len()..].to_string());
-            } else if line_lower.starts_with(CFG.pass.path_key) {
-                e.path = Some(line[CFG.pass.path_key.len()..].to_string());
-            } else if line_lower.starts_with(CFG.pass.url_key) {
-                e.url = Some(line[CFG.pass.url_key.len()..].to_string());
-            } else if line_lower.starts_with(CFG.pass.uuid_key) {
-                e.uuid = match Uuid::parse_str(&line[CFG.pass.uuid_key.len()..]) {
-                    Ok(id) => id,
-                    Err(_) => Uuid::nil(),
-                }
+        // search for the known keys. Only the first occurrence of every key is used, all other
+        // lines are custom fields.
+        while let Some(line) = lines.next() {
+            let user_key = strip_key(line, CFG.pass.user_key)
+                .or_else(|| strip_key(line, CFG.pass.user_key_alt));
+            if let (Some((prefix, user)), None) = (user_key, &e.username) {
+                e.username = Some(user.to_string());
+                e.layout.push(Slot::Username(prefix.to_string()));
+            } else if let (Some((prefix, path)), None) =
+                (strip_key(line, CFG.pass.path_key), &e.path)
+            {
+                e.path = Some(path.to_string());
+                e.layout.push(Slot::Path(prefix.to_string()));
+            } else if let (Some((prefix, url)), None) = (strip_key(line, CFG.pass.url_key), &e.url)
+            {
+                e.url = Some(url.to_string());
+                e.layout.push(Slot::Url(prefix.to_string()));
+            } else if let (Some((prefix, id)), false) = (
+                strip_key(line, CFG.pass.uuid_key),
+                e.layout.iter().any(|s| matches!(s, Slot::Uuid(..))),
+            ) {
+                e.uuid = Uuid::parse_str(id).unwrap_or_else(|_| Uuid::nil());
+                e.layout
+                    .push(Slot::Uuid(prefix.to_string(), id.to_string()));
             } else {
-                // line is not recognized! add line to raw
-                if !line.is_empty() {
-                    e.raw.push_str(line);
-                    e.raw.push('\n');
+                let field = Field::parse(line, &mut lines);
+                if field.kind == FieldKind::Otp && e.otp.is_none() {
+                    e.layout.push(Slot::Otp(field.prefix()));
+                    e.otp = Some(field.value);
+                } else {
+                    e.layout.push(Slot::Field);
+                    e.fields.push(field);
                 }
             }
         }
@@ -167,81 +418,168 @@ impl Entry {
     }
 
     pub fn create(&self) -> Result<()> {
-        if self.path.is_none() {
-            return Err(Error::EntryWithoutPath(format!("{}", self.uuid)));
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Err(Error::EntryWithoutPath(format!("{}", self.uuid))),
+        };
+
+        // check before writing the entry, such that no entry is left behind without a path
+        let records = index::get_records().unwrap_or_default();
+        if let Some(other) = records.iter().find(|r| r.paths().any(|p| p == path)) {
+            return Err(Error::PathConflict(path.clone(), other.uuid, self.uuid));
         }
 
         self.write()?;
-        index::insert(self.uuid, self.path.as_ref().unwrap())?;
-        Ok(())
+        let mut record = IndexRecord::new(self.uuid, path);
+        self.update_record(&mut record);
+        index::insert_record(record)
     }
 
-    pub fn write(&self) -> Result<()> {
-        // rebuild raw
-        let mut raw_content: String = String::new();
+    /// Custom fields of the entry as text, one line per field.
+    #[cfg(test)]
+    pub fn raw(&self) -> String {
+        self.fields
+            .iter()
+            .map(|f| format!("{}\n", f.to_text()))
+            .collect()
+    }
 
-        // push password
-        raw_content.push_str(self.password.as_str());
-        raw_content.push('\n');
-        // push Username
-        if let Some(ref username) = self.username {
-            raw_content.push_str(CFG.pass.user_key);
-            raw_content.push_str(username);
-            raw_content.push('\n');
+    /// Replace all custom fields by the fields in `raw`.
+    #[cfg(test)]
+    pub fn set_raw(&mut self, raw: &str) {
+        self.fields = Field::parse_all(raw);
+    }
+
+    /// Named custom fields with lowercase keys, which can be stored in the index. Secrets and
+    /// otp URIs are never included.
+    pub fn search_fields(&self) -> BTreeMap<String, String> {
+        self.fields
+            .iter()
+            .filter(|f| !self.is_secret(f) && f.kind != FieldKind::Otp)
+            .map(|f| (f.key(), f.value.trim().to_string()))
+            .filter(|(key, _)| !key.is_empty())
+            .collect()
+    }
+
+    /// Store the metadata of the entry in its record of the index.
+    pub fn update_record(&self, record: &mut IndexRecord) {
+        record.username = self.username.clone();
+        record.url = self.url.clone();
+        record.fields = Some(self.search_fields());
+        record.entry_type = self.entry_type().map(String::from);
+    }
+
+    /// Name of the file storing the entry, relative to the root of the store.
+    fn name(&self) -> String {
+        format!("{}/{}", CFG.main.uuid_folder, self.uuid)
+    }
+
+    /// Content of the file storing the entry. Lines are written in the order in which they were
+    /// read. New values are placed at their usual position: the username, url, path and otp after
+    /// the password, new custom fields after the last one, and the uuid at the end (unless the entry has none).
+    pub fn raw_content(&self) -> String {
+        let has = |f: fn(&Slot) -> bool| self.layout.iter().any(f);
+        let mut layout: Vec<Slot> = Vec::new();
+        if !has(|s| matches!(s, Slot::Username(_))) {
+            layout.push(Slot::Username(CFG.pass.user_key.to_string()));
         }
-        // push url
-        if let Some(ref url) = self.url {
-            raw_content.push_str(CFG.pass.url_key);
-            raw_content.push_str(url);
-            raw_content.push('\n');
+        if !has(|s| matches!(s, Slot::Url(_))) {
+            layout.push(Slot::Url(CFG.pass.url_key.to_string()));
         }
-        // push path
-        if let Some(ref path) = self.path {
-            raw_content.push_str(CFG.pass.path_key);
-            raw_content.push_str(path);
-            raw_content.push('\n');
+        if !has(|s| matches!(s, Slot::Path(_))) {
+            layout.push(Slot::Path(CFG.pass.path_key.to_string()));
+        }
+        if !has(|s| matches!(s, Slot::Otp(_))) {
+            layout.push(Slot::Otp(String::new()));
+        }
+        layout.extend(self.layout.iter().cloned());
+        let new_fields_at = match layout.iter().rposition(|s| *s == Slot::Field) {
+            Some(pos) => pos + 1,
+            None => layout
+                .iter()
+                .position(|s| matches!(s, Slot::Uuid(..)))
+                .unwrap_or(layout.len()),
+        };
+        let num_slots = layout.iter().filter(|s| **s == Slot::Field).count();
+        let new_fields = self.fields.len().saturating_sub(num_slots);
+        layout.splice(
+            new_fields_at..new_fields_at,
+            std::iter::repeat_n(Slot::Field, new_fields),
+        );
+        if !has(|s| matches!(s, Slot::Uuid(..))) && !self.uuid.is_nil() {
+            layout.push(Slot::Uuid(CFG.pass.uuid_key.to_string(), String::new()));
         }
-        // push all the content of self.raw
-        raw_content.push_str(self.raw.as_str());
-        //push the uuid last
-        raw_content.push_str(CFG.pass.uuid_key);
-        raw_content.push_str(format!("{}", self.uuid).as_ref());
-        raw_content.push('\n');
 
-        // write raw_content to pass
-        let mut p = Command::new("pass")
-            .arg("insert")
-            .arg("--multiline")
-            .arg(format!("{}/{}", CFG.main.uuid_folder, self.uuid))
-            .stdin(Stdio::piped())
-            .stdout(Stdio::null())
-            .spawn()?;
+        let mut lines: Vec<String> = vec![self.password.clone()];
+        let mut fields = self.fields.iter();
+        let with_key =
+            |key: &str, value: &Option<String>| value.as_ref().map(|v| format!("{}{}", key, v));
+        for slot in layout {
+            match slot {
+                Slot::Username(key) => lines.extend(with_key(&key, &self.username)),
+                Slot::Url(key) => lines.extend(with_key(&key, &self.url)),
+                Slot::Path(key) => lines.extend(with_key(&key, &self.path)),
+                Slot::Otp(key) => lines.extend(with_key(&key, &self.otp)),
+                Slot::Uuid(key, id) => {
+                    let unchanged = !id.is_empty()
+                        && Uuid::parse_str(&id).unwrap_or_else(|_| Uuid::nil()) == self.uuid;
+                    match unchanged {
+                        true => lines.push(format!("{}{}", key, id)),
+                        false => lines.push(format!("{}{}", key, self.uuid)),
+                    }
+                }
+                Slot::Field => lines.extend(fields.next().map(Field::to_text)),
+            }
+        }
 
-        if let Some(mut writer) = p.stdin.take() {
-            writer.write_all(raw_content[..].as_bytes())?;
+        let mut content = lines.join("\n");
+        if self.trailing_newline {
+            content.push('\n');
         }
+        content
+    }
 
-        p.wait()?;
+    pub fn write(&self) -> Result<()> {
+        self.store(false)
+    }
 
-        Ok(())
+    /// Write the entry, and record in the index whether its password was changed.
+    fn store(&self, password_changed: bool) -> Result<()> {
+        // write raw_content to the store
+        let name = self.name();
+        let raw_content = self.raw_content();
+        match CFG.main.agent_cache_entries {
+            true => agent::write(&name, &raw_content)?,
+            false => get_backend().write(&name, &raw_content)?,
+        }
+
+        self.update_index(password_changed)
+    }
+
+    /// Keep the metadata in the index up to date.
+    fn update_index(&self, password_changed: bool) -> Result<()> {
+        index::update(self.uuid, |record| {
+            self.update_record(record);
+            if password_changed {
+                record.password_changed = self.password_since;
+            }
+        })
     }
 
     pub fn edit(&mut self) -> Result<()> {
-        Command::new("pass")
-            .arg("edit")
-            .arg(format!("{}/{}", CFG.main.uuid_folder, self.uuid))
-            .spawn()?
-            .wait()?;
+        get_backend().edit(&self.name())?;
 
         // update the own settings and check if the path is unchanged. If not, update the path
         let old_path = self.path.clone().unwrap();
+        let old_password = self.password.clone();
+        let old_expires = self.field_value(def::EXPIRES_FIELD).map(String::from);
 
         let new_entry = Entry::get(self.uuid)?;
-        self.username = new_entry.username.clone();
-        self.password = new_entry.password.clone();
-        self.path = new_entry.path.clone();
-        self.url = new_entry.url.clone();
-        self.raw = new_entry.raw;
+        *self = Entry {
+            hidden: self.hidden,
+            mask_secrets: self.mask_secrets,
+            ..new_entry
+        };
 
         let new_path = self.path.clone().unwrap();
         if old_path != new_path {
@@ -249,7 +587,18 @@ impl Entry {
             index::mv(self.uuid, new_path)?
         }
 
-        Ok(())
+        if self.password == old_password {
+            return self.update_index(false);
+        }
+        if self.field_value(def::EXPIRES_FIELD) != old_expires.as_deref() {
+            // the new due date was set in the editor as well
+            self.password_since = Some(index::now());
+            return self.update_index(true);
+        }
+        match self.start_rotation() {
+            true => self.store(true),
+            false => self.update_index(true),
+        }
     }
 
     pub fn change_username(&mut self, username: Option<String>) -> Result<()> {
@@ -270,11 +619,31 @@ impl Entry {
         }
     }
 
+    /// Change the password. If the password must be rotated, it expires again after `rotate-every`.
     pub fn change_password(&mut self, new_pw: String) -> Result<()> {
         self.password = new_pw;
-        self.write()
+        self.start_rotation();
+        self.store(true)
+    }
+
+    /// Record that the password was just changed. If it must be rotated, it expires again after
+    /// `rotate-every`. Returns whether the `expires` field was changed.
+    fn start_rotation(&mut self) -> bool {
+        self.password_since = Some(index::now());
+        let due = self
+            .field_value(def::ROTATE_FIELD)
+            .and_then(|rotate_every| expiry::rotated(expiry::today(), rotate_every).ok());
+        match due {
+            Some(due) => {
+                self.set_field(def::EXPIRES_FIELD, &expiry::format_date(due));
+                true
+            }
+            None => false,
+        }
     }
 
+    /// Replace or delete the custom field `old_line`, or add a new one. `old_line` is either the
+    /// line in the file, or the line shown in rofi.
     pub fn change_raw_line(
         &mut self,
         old_line: Option<String>,
@@ -282,26 +651,16 @@ impl Entry {
     ) -> Result<()> {
         if let Some(old_line) = old_line {
             // replace the old line
-            let mut found: bool = false;
-            let raw_clone = self.raw.clone();
-            self.raw = String::new();
-
-            for line in raw_clone.lines() {
-                if line == old_line {
-                    found = true;
-                    if let Some(new_line) = new_line.clone() {
-                        self.raw.push_str(new_line.as_str());
-                        self.raw.push('\n');
-                    }
-                } else {
-                    self.raw.push_str(line);
-                    self.raw.push('\n');
+            match (self.field_at_line(&old_line), new_line) {
+                (Some(i), Some(new_line)) => {
+                    self.fields.splice(i..=i, Field::parse_all(&new_line));
+                    self.write()
                 }
-            }
-
-            match found {
-                true => self.write(),
-                false => Err(Error::EntryRawEdit(
+                (Some(i), None) => {
+                    self.remove_field(i);
+                    self.write()
+                }
+                (None, _) => Err(Error::EntryRawEdit(
                     "Could not find the line to edit".to_string(),
                 )),
             }
@@ -309,8 +668,7 @@ impl Entry {
             // insert new line
             match new_line {
                 Some(new_line) => {
-                    self.raw.push_str(new_line.as_str());
-                    self.raw.push('\n');
+                    self.fields.extend(Field::parse_all(&new_line));
                     self.write()
                 }
                 None => Ok(()),
@@ -318,11 +676,162 @@ impl Entry {
         }
     }
 
+    /// Add the custom field `name`. Values with multiple lines are added as a note.
+    pub fn push_field(&mut self, name: &str, value: &str) {
+        match value.trim_end().contains('\n') {
+            true => self
+                .fields
+                .push(Field::note(name, format!("{}\n", value.trim_end()))),
+            false => self
+                .fields
+                .extend(Field::parse_all(&format!("{}: {}", name, value))),
+        }
+    }
+
+    /// Value of the multiline note `name`, e.g., the notes of the entry.
+    pub fn note(&self, name: &str) -> Option<&str> {
+        self.note_index(name).map(|i| self.fields[i].value.as_str())
+    }
+
+    /// Replace the note `name`. Empty notes are removed. An existing single line field with the
+    /// same name becomes a note.
+    pub fn set_note(&mut self, name: &str, value: &str) {
+        let mut value = value.to_string();
+        if !value.is_empty() && !value.ends_with('\n') {
+            value.push('\n');
+        }
+        match (self.note_index(name), value.trim().is_empty()) {
+            (Some(i), true) => self.remove_field(i),
+            (Some(i), false) => {
+                self.fields[i].kind = FieldKind::Note;
+                self.fields[i].value = value;
+            }
+            (None, true) => {}
+            (None, false) => self.fields.push(Field::note(name, value)),
+        }
+    }
+
+    /// Edit the note `name` in the editor of the user, and write the entry. If `gui` is set, the
+    /// editor is opened in a new window.
+    pub fn edit_note(&mut self, name: &str, gui: bool) -> Result<()> {
+        let mut old = self.note(name).unwrap_or_default().to_string();
+        if !old.is_empty() && !old.ends_with('\n') {
+            old.push('\n');
+        }
+        let new = get_backend().edit_text(&old, gui)?;
+        if new == old {
+            println!("{} of {} unchanged.", name, self.uuid);
+            return Ok(());
+        }
+        self.set_note(name, &new);
+        self.write()
+    }
+
+    /// Name of the note shown as `line` in rofi.
+    pub fn note_at_line(&self, line: &str) -> Option<String> {
+        self.field_at_line(line)
+            .map(|i| &self.fields[i])
+            .filter(|f| f.kind == FieldKind::Note)
+            .map(|f| f.name.clone())
+    }
+
+    fn note_index(&self, name: &str) -> Option<usize> {
+        let key = name.to_lowercase();
+        self.fields.iter().position(|f| f.key() == key)
+    }
+
+    /// Names of the files attached to the entry.
+    pub fn attachments(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|f| f.key() == def::ATTACHMENT_FIELD)
+            .map(|f| f.value.trim().to_string())
+            .collect()
+    }
+
+    /// Name of the blob storing the attachment `file_name`.
+    fn attachment_name(&self, file_name: &str) -> String {
+        format!("{}/{}", index::attachments_folder(self.uuid), file_name)
+    }
+
+    /// Attach `content` as `file_name`, replacing a previous attachment with the same name. The
+    /// blob is encrypted like the entry, and written together with the entry.
+    pub fn attach(&mut self, file_name: &str, content: &[u8]) -> Result<()> {
+        if file_name.is_empty() || file_name.contains('/') || file_name.starts_with('.') {
+            return Err(Error::Attachment(format!(
+                "Invalid attachment name: {}",
+                file_name
+            )));
+        }
+        let mut tx = Transaction::begin(format!("Attach {} to {}.", file_name, self.uuid))?;
+        tx.write(self.attachment_name(file_name), BASE64.encode(content));
+        if !self.attachments().iter().any(|a| a == file_name) {
+            self.push_field(def::ATTACHMENT_FIELD, file_name);
+        }
+        self.write_in(&mut tx);
+        tx.commit()
+    }
+
+    /// Decrypt the attachment `file_name`.
+    pub fn extract(&self, file_name: &str) -> Result<Vec<u8>> {
+        if !self.attachments().iter().any(|a| a == file_name) {
+            return Err(Error::Attachment(format!(
+                "{} has no attachment named {}",
+                self.uuid, file_name
+            )));
+        }
+        let content = get_backend().read(&self.attachment_name(file_name))?;
+        BASE64
+            .decode(content.trim())
+            .map_err(|e| Error::Attachment(format!("{} is corrupted: {}", file_name, e)))
+    }
+
+    /// Remove the custom field at `index`, keeping the position of all other lines.
+    pub fn remove_field(&mut self, index: usize) {
+        self.fields.remove(index);
+        if let Some(pos) = self
+            .layout
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| **s == Slot::Field)
+            .nth(index)
+            .map(|(pos, _)| pos)
+        {
+            self.layout.remove(pos);
+        }
+    }
+
+    /// Move the entry to `new_path`. Nothing is changed if the path is used by another entry.
     pub fn change_path(&mut self, new_path: String) -> Result<()> {
-        self.change_path_keep_index(new_path.clone())?;
+        let mut tx = Transaction::begin(format!("Move {} to {}.", self.uuid, new_path))?;
+        self.change_path_in(new_path, &mut tx);
+        tx.commit()
+    }
 
-        // change index file
-        index::mv(self.uuid, new_path)
+    /// Stage creating the entry in the transaction `tx`.
+    pub fn create_in(&self, tx: &mut Transaction) -> Result<()> {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Err(Error::EntryWithoutPath(format!("{}", self.uuid))),
+        };
+        tx.write(self.name(), self.raw_content());
+        let mut record = IndexRecord::new(self.uuid, path);
+        self.update_record(&mut record);
+        tx.insert(record);
+        Ok(())
+    }
+
+    /// Stage writing the entry in the transaction `tx`.
+    pub fn write_in(&self, tx: &mut Transaction) {
+        tx.write(self.name(), self.raw_content());
+        tx.update(self.uuid, |record| self.update_record(record));
+    }
+
+    /// Stage moving the entry to `new_path` in the transaction `tx`.
+    pub fn change_path_in(&mut self, new_path: String, tx: &mut Transaction) {
+        self.path = Some(new_path.clone());
+        self.write_in(tx);
+        tx.mv(self.uuid, new_path);
     }
 
     pub fn change_path_keep_index(&mut self, new_path: String) -> Result<()> {
@@ -331,8 +840,15 @@ impl Entry {
         self.write()
     }
 
+    /// Lines shown in rofi. The username and url are only shown if they are set or part of the
+    /// entry type, and the fields of the type are shown before all other custom fields.
     pub fn get_rofi_lines(&self) -> Vec<String> {
         let mut result: Vec<String> = Vec::with_capacity(5);
+        let template = self.template();
+        let (show_username, show_url) = match template.as_ref() {
+            Some(t) => (t.username, t.url),
+            None => (true, true),
+        };
 
         result.push(format!(
             "{}{}",
@@ -346,14 +862,16 @@ impl Entry {
             self.uuid
         ));
 
-        result.push(format!(
-            "{}{}",
-            def::format_small(def::DISPLAY_USER),
-            match self.username.as_ref() {
-                Some(user) => escape_pango(user.clone()),
-                None => def::format_small(def::DISPLAY_EMPTY),
-            }
-        ));
+        if show_username || self.username.is_some() {
+            result.push(format!(
+                "{}{}",
+                def::format_small(def::DISPLAY_USER),
+                match self.username.as_ref() {
+                    Some(user) => escape_pango(user.clone()),
+                    None => def::format_small(def::DISPLAY_EMPTY),
+                }
+            ));
+        }
 
         let hidden_pw: String = match self.hidden {
             true => "*".repeat(self.password.len()),
@@ -367,36 +885,93 @@ impl Entry {
 
         result.push(format!(
             "{}{}",
-            def::format_small(def::DISPLAY_URL),
-            match self.url.as_ref() {
-                Some(url) => escape_pango(url.clone()),
-                None => def::format_small(def::DISPLAY_EMPTY),
-            }
+            def::format_small(def::DISPLAY_STRENGTH),
+            escape_pango(Strength::of(&self.password).to_string())
         ));
 
+        if let Some(warning) = self.expiry_warning() {
+            result.push(format!(
+                "{}{}",
+                def::format_small(def::DISPLAY_DUE),
+                escape_pango(warning)
+            ));
+        }
+
+        if show_url || self.url.is_some() {
+            result.push(format!(
+                "{}{}",
+                def::format_small(def::DISPLAY_URL),
+                match self.url.as_ref() {
+                    Some(url) => escape_pango(url.clone()),
+                    None => def::format_small(def::DISPLAY_EMPTY),
+                }
+            ));
+        }
+
+        if self.otp.is_some() {
+            result.push(format!(
+                "{}{}",
+                def::format_small(def::DISPLAY_OTP),
+                self.display_otp()
+            ));
+        }
+
+        // fields of the entry type, in the order of the type
+        let typed: Vec<&Field> = template
+            .iter()
+            .flat_map(|t| t.fields.iter())
+            .filter_map(|t| {
+                self.fields
+                    .iter()
+                    .find(|f| f.key() == t.name.to_lowercase())
+            })
+            .collect();
+        if !typed.is_empty() {
+            result.push(def::format_small(&format!(
+                "{} ",
+                template.as_ref().unwrap().name
+            )));
+        }
+        let other = self.fields.iter().filter(|f| {
+            !f.is_blank()
+                && (f.key() != def::TYPE_FIELD || template.is_none())
+                && !typed.iter().any(|t| std::ptr::eq(*t, *f))
+        });
+
         let mut raw_str_printed = false;
-        for line in self.raw.lines() {
-            if !raw_str_printed {
+        for (i, field) in typed.iter().copied().chain(other).enumerate() {
+            if !raw_str_printed && i >= typed.len() {
                 raw_str_printed = true;
                 result.push(def::format_small(def::DISPLAY_RAW).as_str().to_string());
             }
-            if let Some((key, value)) = line.split_once(": ") {
+            let value = self.rofi_value(field);
+            if field.name.is_empty() {
+                // normal formatting
+                result.push(escape_pango(value));
+            } else {
                 // nice formatting
                 result.push(format!(
                     "{}{}{}",
-                    def::format_small(key),
+                    def::format_small(&field.name),
                     def::format_small(def::DISPLAY_RAW_SEP),
-                    escape_pango(value.to_string())
+                    escape_pango(value)
                 ))
-            } else {
-                // normal formatting
-                result.push(escape_pango(line.to_string()).to_string());
             }
         }
         result
     }
 }
 
+/// Split `line` after `key`, if it starts with `key` (case insensitive).
+fn strip_key<'a>(line: &'a str, key: &str) -> Option<(&'a str, &'a str)> {
+    match line.get(..key.len()) {
+        Some(prefix) if prefix.to_lowercase() == key.to_lowercase() => {
+            Some((prefix, &line[key.len()..]))
+        }
+        _ => None,
+    }
+}
+
 fn escape_pango(s: String) -> String {
     s.replace("&", "&amp;")
         .replace(">", "&gt;")
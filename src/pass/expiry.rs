@@ -0,0 +1,121 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Expiry of passwords. The password of an entry is due at the date of its `expires` field, or,
+//! if it only has a `rotate-every` field, that long after the password was last changed.
+
+use std::convert::TryFrom;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+use crate::def;
+use crate::errors::{Error, Result};
+use crate::pass::index::IndexRecord;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Parse a duration like `30d` into seconds. The units are `s`, `m`, `h`, `d` and `w`.
+pub fn parse_duration(duration: &str) -> Result<u64> {
+    let duration = duration.trim();
+    let unit = match duration.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => return Err(Error::InvalidInput("Durations need a unit, e.g., 30d")),
+    };
+    match duration[..duration.len() - 1].parse::<u64>() {
+        Ok(value) => value
+            .checked_mul(unit)
+            .ok_or(Error::InvalidInput("Duration is too long")),
+        Err(_) => Err(Error::InvalidInput(
+            "Durations are written as a number and a unit, e.g., 30d",
+        )),
+    }
+}
+
+/// Parse a date like `2024-12-31`.
+pub fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), DATE_FORMAT)
+        .map_err(|_| Error::InvalidInput("Dates are written as YYYY-MM-DD"))
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Date `rotate_every` after `date`. Durations shorter than a day are rounded down. Fails if the
+/// date would be out of range.
+pub fn rotated(date: NaiveDate, rotate_every: &str) -> Result<NaiveDate> {
+    let days = parse_duration(rotate_every)? / (24 * 60 * 60);
+    i64::try_from(days)
+        .ok()
+        .and_then(Duration::try_days)
+        .and_then(|days| date.checked_add_signed(days))
+        .ok_or(Error::InvalidInput("Rotation period is too long"))
+}
+
+/// Date at which a password is due, given the values of the `expires` and `rotate-every` fields
+/// and the time the password was last changed in seconds since the unix epoch. Invalid values are ignored, and
+/// a rotation period too long to be represented means the password is never due.
+pub fn due_date(
+    expires: Option<&str>,
+    rotate_every: Option<&str>,
+    changed: Option<u64>,
+) -> Option<NaiveDate> {
+    if let Some(date) = expires.and_then(|e| parse_date(e).ok()) {
+        return Some(date);
+    }
+    let changed = DateTime::from_timestamp(changed? as i64, 0)?
+        .with_timezone(&Local)
+        .date_naive();
+    rotated(changed, rotate_every?).ok()
+}
+
+/// Due date of the entry stored in the index.
+pub fn record_due_date(record: &IndexRecord) -> Option<NaiveDate> {
+    let fields = record.fields.as_ref()?;
+    due_date(
+        fields.get(def::EXPIRES_FIELD).map(String::as_str),
+        fields.get(def::ROTATE_FIELD).map(String::as_str),
+        record.password_since(),
+    )
+}
+
+/// Whether a password with the given due date must be changed within `days` days.
+pub fn is_due(due: NaiveDate, days: i64) -> bool {
+    match Duration::try_days(days).and_then(|days| today().checked_add_signed(days)) {
+        Some(limit) => due <= limit,
+        // The limit is beyond any representable date
+        None => days > 0,
+    }
+}
+
+/// Describe when the password is due, e.g., `expires in 3 days (2024-12-31)`.
+pub fn describe(due: NaiveDate) -> String {
+    match (due - today()).num_days() {
+        0 => "expires today".to_string(),
+        1 => format!("expires tomorrow ({})", format_date(due)),
+        -1 => format!("expired yesterday ({})", format_date(due)),
+        days if days < 0 => format!("expired {} days ago ({})", -days, format_date(due)),
+        days => format!("expires in {} days ({})", days, format_date(due)),
+    }
+}
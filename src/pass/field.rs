@@ -0,0 +1,184 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Custom fields of an entry. Every line of the entry which is not the password, username, url,
+//! path or uuid is a field. A field is written as `name: value`, but lines without a name (even
+//! empty lines) are kept as well. Multiline notes are written as `name: |`, followed by the lines
+//! of the note, each indented by two spaces:
+//!
+//! ```text
+//! recovery: |
+//!   Call the support, and tell them
+//!   the name of your first pet.
+//! ```
+
+use std::iter::Peekable;
+
+use crate::config::CFG;
+use crate::pass::otp::OTP_SCHEME;
+
+/// Value of a field starting a note.
+const NOTE_MARKER: &str = "|";
+/// Indentation of the lines of a note.
+const NOTE_INDENT: &str = "  ";
+
+/// Type of a field, deciding how it is displayed and handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Secret,
+    Url,
+    Email,
+    Otp,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Name of the field as written in the file. Empty for lines without a name.
+    pub name: String,
+    /// Value of the field. The value of a note ends with a newline.
+    pub value: String,
+    pub kind: FieldKind,
+    /// Separator between the name and the value, as written in the file.
+    sep: String,
+}
+
+impl Field {
+    /// New multiline note.
+    pub fn note(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            kind: FieldKind::Note,
+            sep: ": ".to_string(),
+        }
+    }
+
+    /// Parse the field starting at `line`. If the field is a note, its lines are taken from
+    /// `rest`.
+    pub fn parse<'a, I>(line: &str, rest: &mut Peekable<I>) -> Self
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let (name, sep, value) = split_line(line);
+        let mut field = Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            kind: FieldKind::detect(name, value),
+            sep: sep.to_string(),
+        };
+        if !name.is_empty() && value == NOTE_MARKER {
+            field.kind = FieldKind::Note;
+            field.value = String::new();
+            while let Some(line) = rest.peek().and_then(|l| l.strip_prefix(NOTE_INDENT)) {
+                field.value.push_str(line);
+                field.value.push('\n');
+                rest.next();
+            }
+        }
+        field
+    }
+
+    /// Parse all fields of `text`.
+    pub fn parse_all(text: &str) -> Vec<Self> {
+        let mut lines = text.split_terminator('\n').peekable();
+        let mut fields = Vec::new();
+        while let Some(line) = lines.next() {
+            fields.push(Self::parse(line, &mut lines));
+        }
+        fields
+    }
+
+    /// Name and separator, i.e., everything before the value.
+    pub fn prefix(&self) -> String {
+        format!("{}{}", self.name, self.sep)
+    }
+
+    /// The field with its value replaced by stars, if it is a secret.
+    pub fn masked(&self) -> Self {
+        let mut field = self.clone();
+        if field.kind == FieldKind::Secret {
+            field.value = "*".repeat(field.value.chars().count());
+        }
+        field
+    }
+
+    /// Lowercase name of the field, used to look it up.
+    pub fn key(&self) -> String {
+        self.name.trim().to_lowercase()
+    }
+
+    /// Whether the line is empty.
+    pub fn is_blank(&self) -> bool {
+        self.name.is_empty() && self.value.trim().is_empty()
+    }
+
+    /// The field as written in the file, without the trailing newline.
+    pub fn to_text(&self) -> String {
+        if self.kind != FieldKind::Note && !self.value.contains('\n') {
+            return format!("{}{}{}", self.name, self.sep, self.value);
+        }
+        let mut text = format!("{}{}{}", self.name, self.sep, NOTE_MARKER);
+        for line in self.value.split_terminator('\n') {
+            text.push('\n');
+            text.push_str(NOTE_INDENT);
+            text.push_str(line);
+        }
+        text
+    }
+}
+
+impl FieldKind {
+    /// Kind of a single line field, using the field names configured in the `[fields]` section.
+    pub fn detect(name: &str, value: &str) -> Self {
+        let key = name.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+        let is = |names: &[&str]| names.iter().any(|n| n.to_lowercase() == key);
+        if value.starts_with(OTP_SCHEME) {
+            FieldKind::Otp
+        } else if is(&CFG.fields.secret)
+            || (!CFG.fields.secret_prefix.is_empty()
+                && name.trim_start().starts_with(CFG.fields.secret_prefix))
+        {
+            FieldKind::Secret
+        } else if is(&CFG.fields.email) {
+            FieldKind::Email
+        } else if is(&CFG.fields.url)
+            || value.starts_with("https://")
+            || value.starts_with("http://")
+        {
+            FieldKind::Url
+        } else {
+            FieldKind::Text
+        }
+    }
+}
+
+/// Split a line into the name, the separator and the value, such that the concatenation is the
+/// line again. Lines without a name (e.g., `https://...`) have an empty name and separator.
+fn split_line(line: &str) -> (&str, &str, &str) {
+    match line.find(':') {
+        Some(pos) if !line[..pos].trim().is_empty() && !line[pos..].starts_with("://") => {
+            let value_start = line[pos + 1..]
+                .find(|c: char| c != ' ' && c != '\t')
+                .map(|i| pos + 1 + i)
+                .unwrap_or_else(|| line.len());
+            (&line[..pos], &line[pos..value_start], &line[value_start..])
+        }
+        _ => ("", "", line),
+    }
+}
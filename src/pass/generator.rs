@@ -0,0 +1,281 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Random passwords and passphrases, generated with the random number generator of the operating
+//! system. How a password looks is decided by a policy. The policy of an entry is stored in its
+//! `policy:` field, and otherwise chosen by the folder of the entry. Additional policies can be
+//! defined in the `[policies]` table of the config, which replace built-in policies of the same
+//! name:
+//!
+//! ```text
+//! [policies.bank]
+//! length = 12
+//! symbols = false
+//! exclude_ambiguous = true
+//! folders = ["finance"]
+//! ```
+//!
+//! Rules of a single site are stored in the `pwrule:` field of the entry, and override the
+//! policy, e.g., `pwrule: len=16 charset=alnum`.
+
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+
+use crate::config::{ConfigPolicy, CFG};
+use crate::def;
+use crate::errors::{Error, Result};
+
+/// Policy used when no other policy is selected.
+pub const DEFAULT_POLICY: &str = "default";
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+/// Characters which are easily confused with each other.
+const AMBIGUOUS: &str = "0Oo1lI|`'\"";
+
+/// Words of passphrases (the BIP39 English wordlist).
+pub const WORDLIST: &str = include_str!("wordlist.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    pub name: String,
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Never use characters which are easily confused, like `l`, `1` and `I`.
+    pub exclude_ambiguous: bool,
+    /// Generate a passphrase of this many words instead of a password.
+    pub words: Option<usize>,
+    pub separator: String,
+    /// Folders whose entries use this policy by default.
+    pub folders: Vec<String>,
+}
+
+impl Policy {
+    fn builtin() -> Vec<Self> {
+        let policy = |name: &str, length, letters, symbols, words| Self {
+            name: name.to_string(),
+            length,
+            lowercase: letters,
+            uppercase: letters,
+            digits: true,
+            symbols,
+            exclude_ambiguous: false,
+            words,
+            separator: "-".to_string(),
+            folders: Vec::new(),
+        };
+        vec![
+            policy(DEFAULT_POLICY, 20, true, true, None),
+            policy("alphanumeric", 20, true, false, None),
+            policy("pin", 6, false, false, None),
+            policy("passphrase", 0, false, false, Some(6)),
+        ]
+    }
+
+    fn from_config(policy: &ConfigPolicy) -> Self {
+        Self {
+            name: policy.name.to_string(),
+            length: policy.length,
+            lowercase: policy.lowercase,
+            uppercase: policy.uppercase,
+            digits: policy.digits,
+            symbols: policy.symbols,
+            exclude_ambiguous: policy.exclude_ambiguous,
+            words: policy.words,
+            separator: policy.separator.to_string(),
+            folders: policy.folders.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    /// All policies. User-defined policies replace the built-in policies with the same name.
+    pub fn all() -> Vec<Self> {
+        let mut all: Vec<Self> = Self::builtin()
+            .into_iter()
+            .filter(|p| !CFG.policies.iter().any(|c| c.name == p.name))
+            .collect();
+        all.extend(CFG.policies.iter().map(Self::from_config));
+        all
+    }
+
+    /// The policy `name` (case insensitive).
+    pub fn get(name: &str) -> Result<Self> {
+        let name = name.trim().to_lowercase();
+        Self::all()
+            .into_iter()
+            .find(|p| p.name.to_lowercase() == name)
+            .ok_or(Error::UnknownPolicy(name))
+    }
+
+    /// Policy of an entry at `path`: the policy `name` if given, otherwise the policy of the
+    /// closest folder containing `path`, or the default policy.
+    pub fn select(name: Option<&str>, path: &str) -> Result<Self> {
+        if let Some(name) = name {
+            return Self::get(name);
+        }
+        let closest = Self::all()
+            .into_iter()
+            .filter_map(|p| Some((p.folder_of(path)?.len(), p)))
+            .max_by_key(|(depth, _)| *depth);
+        match closest {
+            Some((_, policy)) => Ok(policy),
+            None => Self::get(DEFAULT_POLICY),
+        }
+    }
+
+    /// The deepest of the folders of the policy which contains `path`.
+    pub fn folder_of(&self, path: &str) -> Option<&str> {
+        self.folders
+            .iter()
+            .map(|f| f.trim_matches('/'))
+            .filter(|f| {
+                path.strip_prefix(f)
+                    .map(|rest| rest.starts_with('/'))
+                    .unwrap_or(false)
+            })
+            .max_by_key(|f| f.len())
+    }
+
+    /// The policy changed by the rule `rule`, written as space separated `key=value` pairs:
+    /// `len` (number of characters), `charset` (comma separated classes: `lower`, `upper`,
+    /// `digits`, `symbols`, `alpha`, `alnum` or `all`), `words` (number of words of a
+    /// passphrase), `sep` (separator of the words) and `ambiguous` (`yes` or `no`).
+    pub fn with_rule(mut self, rule: &str) -> Result<Self> {
+        let invalid = |reason| Err(Error::InvalidField(def::PWRULE_FIELD.to_string(), reason));
+        for part in rule.split_whitespace() {
+            let (key, value) = match part.split_once('=') {
+                Some(x) => x,
+                None => return invalid("must be written as KEY=VALUE"),
+            };
+            match key.to_lowercase().as_str() {
+                "len" | "length" => match value.parse() {
+                    Ok(length) => {
+                        self.length = length;
+                        self.words = None;
+                    }
+                    Err(_) => return invalid("len must be a number"),
+                },
+                "charset" => {
+                    let (mut lower, mut upper, mut digits, mut symbols) =
+                        (false, false, false, false);
+                    for class in value.to_lowercase().split(',') {
+                        match class {
+                            "lower" => lower = true,
+                            "upper" => upper = true,
+                            "digits" => digits = true,
+                            "symbols" => symbols = true,
+                            "alpha" => {
+                                lower = true;
+                                upper = true;
+                            }
+                            "alnum" => {
+                                lower = true;
+                                upper = true;
+                                digits = true;
+                            }
+                            "all" => {
+                                lower = true;
+                                upper = true;
+                                digits = true;
+                                symbols = true;
+                            }
+                            _ => return invalid("charset has an unknown character class"),
+                        }
+                    }
+                    self.lowercase = lower;
+                    self.uppercase = upper;
+                    self.digits = digits;
+                    self.symbols = symbols;
+                    self.words = None;
+                }
+                "words" => match value.parse() {
+                    Ok(words) => self.words = Some(words),
+                    Err(_) => return invalid("words must be a number"),
+                },
+                "sep" | "separator" => self.separator = value.to_string(),
+                "ambiguous" => match value.to_lowercase().as_str() {
+                    "yes" | "true" => self.exclude_ambiguous = false,
+                    "no" | "false" => self.exclude_ambiguous = true,
+                    _ => return invalid("ambiguous must be yes or no"),
+                },
+                _ => return invalid("has an unknown key"),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Characters of all enabled classes, each class without the excluded characters.
+    fn classes(&self) -> Vec<Vec<char>> {
+        [
+            (self.lowercase, LOWERCASE),
+            (self.uppercase, UPPERCASE),
+            (self.digits, DIGITS),
+            (self.symbols, SYMBOLS),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, chars)| {
+            chars
+                .chars()
+                .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+                .collect()
+        })
+        .collect()
+    }
+
+    /// Check that the policy can generate a password.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason| Err(Error::InvalidPolicy(self.name.clone(), reason));
+        match self.words {
+            Some(0) => invalid("needs at least one word"),
+            Some(_) => Ok(()),
+            None if self.classes().is_empty() => invalid("needs at least one character class"),
+            None if self.length < self.classes().len() => {
+                invalid("is too short to contain every character class")
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Generate a new random password. It contains at least one character of every enabled
+    /// class. Passphrases consist of random words from the wordlist.
+    pub fn generate(&self) -> Result<String> {
+        self.validate()?;
+        let mut rng = OsRng;
+        if let Some(words) = self.words {
+            let wordlist: Vec<&str> = WORDLIST.lines().collect();
+            let words: Vec<&str> = (0..words)
+                .map(|_| *wordlist.choose(&mut rng).unwrap())
+                .collect();
+            return Ok(words.join(&self.separator));
+        }
+        let classes = self.classes();
+        let all: Vec<char> = classes.iter().flatten().copied().collect();
+        let mut password: Vec<char> = classes
+            .iter()
+            .map(|class| *class.choose(&mut rng).unwrap())
+            .collect();
+        while password.len() < self.length {
+            password.push(*all.choose(&mut rng).unwrap());
+        }
+        password.shuffle(&mut rng);
+        Ok(password.into_iter().collect())
+    }
+}
@@ -0,0 +1,116 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Offline breach check against a local copy of the Have I Been Pwned password dump. The dump
+//! has one line per password, written as the uppercase SHA-1 hash of the password and the number
+//! of breaches it appeared in (e.g., `5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004`), sorted
+//! by the hash. The dump is far too large to be read entirely, so every password is looked up
+//! with a binary search in the file.
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use sha1::{Digest, Sha1};
+
+use crate::config::{expand_path, CFG};
+use crate::errors::{Error, Result};
+
+/// Length of a SHA-1 hash written in hex.
+const HASH_LEN: usize = 40;
+
+/// Open password dump, sorted by the hash.
+pub struct Breaches {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+impl Breaches {
+    pub fn open(file: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(file.as_ref()).map_err(|e| {
+            Error::Breaches(format!("cannot open {}: {}", file.as_ref().display(), e))
+        })?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// Open the dump configured as `hibp_file`, if any.
+    pub fn configured() -> Result<Option<Self>> {
+        CFG.main
+            .hibp_file
+            .map(|file| Self::open(expand_path(file)))
+            .transpose()
+    }
+
+    /// Number of breaches `password` appeared in, or 0 if it is not in the dump.
+    pub fn count(&mut self, password: &str) -> Result<u64> {
+        let hash: String = Sha1::digest(password.as_bytes())
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        self.lookup(&hash)
+    }
+
+    /// Binary search for the count of `hash`. Lines starting in `lo..hi` are not searched yet.
+    fn lookup(&mut self, hash: &str) -> Result<u64> {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (start, line) = self.line_after(mid)?;
+            if start >= hi || line.is_empty() {
+                hi = mid;
+                continue;
+            }
+            let (line_hash, count) = parse_line(&line)?;
+            match line_hash.cmp(hash) {
+                Ordering::Equal => return Ok(count),
+                Ordering::Less => lo = start + line.len() as u64,
+                Ordering::Greater => hi = mid,
+            }
+        }
+        Ok(0)
+    }
+
+    /// First line starting at `pos` or later, with its start.
+    fn line_after(&mut self, pos: u64) -> Result<(u64, String)> {
+        let mut start = pos;
+        if pos > 0 {
+            // skip the rest of the line containing the byte before `pos`
+            self.reader.seek(SeekFrom::Start(pos - 1))?;
+            let mut rest = Vec::new();
+            start += self.reader.read_until(b'\n', &mut rest)? as u64 - 1;
+        } else {
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        Ok((start, line))
+    }
+}
+
+/// Hash and count of a line of the dump.
+fn parse_line(line: &str) -> Result<(&str, u64)> {
+    let invalid = || Error::Breaches(format!("invalid line: {}", line.trim_end()));
+    let (hash, count) = line.trim_end().split_once(':').ok_or_else(invalid)?;
+    if hash.len() != HASH_LEN {
+        return Err(invalid());
+    }
+    Ok((hash, count.parse().map_err(|_| invalid())?))
+}
@@ -0,0 +1,176 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Previous versions of an entry, read from the git history of the store.
+
+use std::fs;
+use std::process::Command;
+
+use uuid::Uuid;
+
+use crate::config::CFG;
+use crate::def;
+use crate::errors::{Error, Result};
+use crate::pass::backend::{get_backend, run_checked};
+use crate::pass::entry::Entry;
+
+/// Commit of the store which changed the file of an entry.
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// Number of the version, starting with 1 for the oldest one.
+    pub number: usize,
+    pub commit: String,
+    /// Time of the commit, in seconds since the unix epoch.
+    pub time: u64,
+    /// Time of the commit, as formatted by git.
+    pub date: String,
+    pub message: String,
+}
+
+/// File of the entry `id`, relative to the root of the store.
+fn entry_file(id: Uuid) -> String {
+    format!(
+        "{}/{}.{}",
+        CFG.main.uuid_folder,
+        id,
+        get_backend().extension()
+    )
+}
+
+/// All versions of the entry `id`, starting with the oldest one. Commits which deleted the entry
+/// are skipped.
+pub fn versions(id: Uuid) -> Result<Vec<Version>> {
+    let backend = get_backend();
+    let root = backend.root();
+    if !root.join(def::GIT_FOLDER).is_dir() {
+        return Err(Error::NoHistory(format!(
+            "{}: the store is not a git repository",
+            id
+        )));
+    }
+    let output = run_checked(
+        Command::new("git")
+            .args([
+                "log",
+                "--reverse",
+                "--format=%H%x09%at%x09%ad%x09%s",
+                "--date=format:%Y-%m-%d %H:%M",
+                "--diff-filter=AM",
+                "--",
+            ])
+            .arg(entry_file(id))
+            .current_dir(root),
+    )?;
+    let versions: Vec<Version> = String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, '\t');
+            Some((
+                parts.next()?,
+                parts.next()?.parse().ok()?,
+                parts.next()?,
+                parts.next().unwrap_or_default(),
+            ))
+        })
+        .enumerate()
+        .map(|(i, (commit, time, date, message))| Version {
+            number: i + 1,
+            commit: commit.to_string(),
+            time,
+            date: date.to_string(),
+            message: message.to_string(),
+        })
+        .collect();
+    match versions.is_empty() {
+        true => Err(Error::NoHistory(id.to_string())),
+        false => Ok(versions),
+    }
+}
+
+/// Decrypted content of the entry `id` in `version`.
+pub fn read(id: Uuid, version: &Version) -> Result<String> {
+    let backend = get_backend();
+    let root = backend.root();
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("{}:{}", version.commit, entry_file(id)))
+        .current_dir(root)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::EntryRead(format!("{} ({})", id, version.commit)));
+    }
+
+    // the backend decrypts files, so store the (still encrypted) content in the git folder
+    let file = root.join(def::GIT_FOLDER).join(format!(
+        "rpass-{}-{}.{}",
+        id,
+        version.number,
+        backend.extension()
+    ));
+    fs::write(&file, output.stdout)?;
+    let content = backend.read_file(&file);
+    fs::remove_file(&file)?;
+    content
+}
+
+/// The entry `id` in `version`.
+pub fn entry(id: Uuid, version: &Version) -> Result<Entry> {
+    Entry::parse(&id.to_string(), &read(id, version)?)
+}
+
+/// Time since which the password of `version` was used, i.e., the time of the oldest version
+/// before it with the same password. Versions which cannot be read end the search.
+pub fn password_since(id: Uuid, versions: &[Version], version: &Version) -> Result<u64> {
+    let password = entry(id, version)?.password;
+    let mut since = version.time;
+    for older in versions.iter().rev().filter(|v| v.number < version.number) {
+        match entry(id, older) {
+            Ok(older_entry) if older_entry.password == password => since = older.time,
+            _ => break,
+        }
+    }
+    Ok(since)
+}
+
+/// Values which differ between two versions of an entry, as `(name, old, new)`. The values are
+/// shown like by `Entry::named_values`, such that secrets are masked if the entries are hidden.
+pub fn changes(old: Option<&Entry>, new: &Entry) -> Vec<(String, Option<String>, Option<String>)> {
+    let old_values = old.map(|e| e.named_values()).unwrap_or_default();
+    let new_values = new.named_values();
+    let mut names: Vec<&String> = old_values.iter().map(|(n, _, _)| n).collect();
+    for (name, _, _) in new_values.iter() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let find = |values: &[(String, String, String)], name: &str| {
+        values
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, value, shown)| (value.clone(), shown.clone()))
+    };
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let before = find(&old_values, name);
+            let after = find(&new_values, name);
+            match before.as_ref().map(|b| &b.0) == after.as_ref().map(|a| &a.0) {
+                true => None,
+                false => Some((name.clone(), before.map(|b| b.1), after.map(|a| a.1))),
+            }
+        })
+        .collect()
+}
@@ -15,32 +15,175 @@
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::io::Write;
-use std::panic;
-use std::process::{Command, Stdio};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::SystemTime;
 
+use fs2::FileExt;
 use itertools::Itertools;
 use petgraph::graph::{Graph, NodeIndex};
+use serde::{Deserialize, Serialize};
 
 use uuid::Uuid;
 
 use crate::config::{self, CFG};
+use crate::def;
 use crate::errors::{Error, Result};
+use crate::pass::agent;
+use crate::pass::backend::{get_backend, git_stage, run_checked};
 use crate::Loading;
 
 thread_local! {
     pub static INDEX: RefCell<Index> = RefCell::new(Index::default());
+    static LOCKED: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
 }
 
+/// Version of the index format which is written by rpass.
+pub const INDEX_VERSION: u32 = 2;
+
 #[derive(Debug)]
 pub struct Index {
     timestamp: SystemTime,
-    index: Vec<(Uuid, String)>,
+    index: Vec<IndexRecord>,
+}
+
+/// First line of the index, describing its format.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexHeader {
+    version: u32,
+}
+
+/// Metadata of an entry stored in the index, such that it can be shown without decrypting the
+/// entry itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexRecord {
+    pub uuid: Uuid,
+    pub path: String,
+    /// Further paths under which the entry is listed, e.g., a single login used for multiple
+    /// services.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Creation time in seconds since the unix epoch, unknown for migrated entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    /// Last modification time in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    /// Time the password was last changed in seconds since the unix epoch, unknown for entries
+    /// created before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_changed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub entry_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Raw `key: value` lines of the entry, used for searching. `None` if they were not
+    /// collected yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<String, String>>,
 }
 
+impl IndexRecord {
+    /// Create a record of an entry which was just created.
+    pub fn new(uuid: Uuid, path: impl Into<String>) -> Self {
+        let now = now();
+        Self {
+            uuid,
+            path: path.into(),
+            aliases: Vec::new(),
+            created: Some(now),
+            modified: Some(now),
+            password_changed: Some(now),
+            tags: Vec::new(),
+            username: None,
+            entry_type: None,
+            url: None,
+            fields: None,
+        }
+    }
+
+    /// Time since which the current password is used. Falls back to the last modification for
+    /// entries which do not record when the password was changed.
+    pub fn password_since(&self) -> Option<u64> {
+        self.password_changed.or(self.modified)
+    }
+
+    /// Path of the entry, followed by all its aliases.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.path.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    /// Create a record without any metadata, e.g., when migrating an old index.
+    fn without_metadata(uuid: Uuid, path: impl Into<String>) -> Self {
+        Self {
+            created: None,
+            modified: None,
+            password_changed: None,
+            ..Self::new(uuid, path)
+        }
+    }
+}
+
+/// Advisory lock of the index, which is released when dropped. While it is held, no other rpass
+/// process can modify the index. The lock can be acquired multiple times by the same thread.
+pub struct IndexLock {
+    folder: PathBuf,
+    file: Option<File>,
+}
+
+impl IndexLock {
+    /// Lock the index of the current backend, waiting until other processes release it. The lock
+    /// is held on the uuid folder itself, such that no lock file ends up in the store.
+    pub fn acquire() -> Result<Self> {
+        let folder = get_backend().root().join(CFG.main.uuid_folder);
+        if LOCKED.with(|l| l.borrow().contains(&folder)) {
+            return Ok(Self { folder, file: None });
+        }
+        fs::create_dir_all(&folder)?;
+        let file = File::open(&folder)?;
+        file.lock_exclusive()?;
+        LOCKED.with(|l| l.borrow_mut().push(folder.clone()));
+        Ok(Self {
+            folder,
+            file: Some(file),
+        })
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            LOCKED.with(|l| l.borrow_mut().retain(|f| f != &self.folder));
+            let _ = FileExt::unlock(&file);
+        }
+    }
+}
+
+/// Current time in seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Uuid and path of all entries. Aliases are listed as separate items, right after the path of
+/// their entry.
 pub fn get_index() -> Result<Vec<(Uuid, String)>> {
+    Ok(get_records()?
+        .iter()
+        .flat_map(|r| r.paths().map(move |p| (r.uuid, p.to_string())))
+        .collect())
+}
+
+/// All records of the index, sorted by how frequently they are used.
+pub fn get_records() -> Result<Vec<IndexRecord>> {
     INDEX.with(|index| {
         if index.borrow().is_depricated()? {
             index.replace(Index::read()?);
@@ -92,14 +235,19 @@ impl Index {
     }
 
     fn current_timestamp() -> Result<SystemTime> {
-        let mut index_file = home::home_dir().unwrap();
-        index_file.push(".password-store/uuids/index.gpg");
-        let metadata = std::fs::metadata(&index_file)?;
-        let timestamp = metadata.modified()?;
-        Ok(timestamp)
+        match get_backend().modified(&index_entry()) {
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(Error::NoIndexFile)
+            }
+            result => result,
+        }
     }
 }
 
+fn index_entry() -> String {
+    format!("{}/{}", CFG.main.uuid_folder, CFG.main.index_entry)
+}
+
 fn read_history() -> Vec<(SystemTime, Uuid)> {
     let mut file = home::home_dir().unwrap();
     file.push(config::CFG.main.history_file);
@@ -126,68 +274,138 @@ fn read_history() -> Vec<(SystemTime, Uuid)> {
     frequency
 }
 
-#[allow(dead_code)]
-fn read_index(frequency: &HashMap<Uuid, usize>) -> Result<Vec<(Uuid, String)>> {
+fn read_index(frequency: &HashMap<Uuid, usize>) -> Result<Vec<IndexRecord>> {
     let _loading = Loading::new("Reading the index...")?;
 
-    // execute pass command
-    let output = Command::new("pass")
-        .arg(format!("{}/{}", CFG.main.uuid_folder, CFG.main.index_entry))
-        .output()?;
+    // read the index from the backend
+    let content = match agent::read(&index_entry()) {
+        Ok(content) => content,
+        Err(Error::EntryRead(_)) => return Err(Error::NoIndexFile),
+        Err(e) => return Err(e),
+    };
 
-    if !output.status.success() {
-        return Err(Error::NoIndexFile);
+    let (records, migrated) = parse(&content)?;
+    if migrated {
+        println!("Migrating the index to version {}", INDEX_VERSION);
+        write_records(&records)?;
     }
 
-    let result_utf8 = output.stdout;
-
-    // parse to str
-    let result = String::from_utf8(result_utf8)?;
-
-    // generate the resulting vector
-
-    let index_list: std::result::Result<Vec<(Uuid, String)>, _> = panic::catch_unwind(|| {
-        result
-            .lines()
-            .map(|s| s.split(' ').collect())
-            .map(|v: Vec<&str>| (Uuid::parse_str(v[0]).unwrap(), v[1].to_string()))
-            .collect()
-    });
-
-    let Ok(list) = index_list else {
-        return Err(Error::Other("UUID Error: cannot parse uuid!".to_string()));
-    };
-
     // sort the list according to the frequency, and then alphabetically
-    Ok(list
+    Ok(records
         .into_iter()
-        .map(|(uuid, path)| {
+        .map(|r| {
             (
-                uuid,
-                frequency.get(&uuid).copied().unwrap_or(0),
-                path.to_lowercase(),
-                path,
+                frequency.get(&r.uuid).copied().unwrap_or(0),
+                r.path.to_lowercase(),
+                r,
             )
         })
-        .sorted_by(|(_, f1, n1, _), (_, f2, n2, _)| f2.cmp(f1).then_with(|| n1.cmp(n2)))
-        .map(|(uuid, _, _, path)| (uuid, path))
+        .sorted_by(|(f1, n1, _), (f2, n2, _)| f2.cmp(f1).then_with(|| n1.cmp(n2)))
+        .map(|(_, _, r)| r)
         .collect())
 }
 
+/// Parse the content of the index. Lines that cannot be parsed are reported and skipped. Indices
+/// in the old format, storing `uuid path` on each line, are converted to records. The returned
+/// flag tells if the index was stored in the old format.
+pub fn parse(content: &str) -> Result<(Vec<IndexRecord>, bool)> {
+    let mut lines = content.lines().enumerate().peekable();
+    let header = lines
+        .peek()
+        .and_then(|(_, l)| serde_json::from_str::<IndexHeader>(l).ok());
+    let migrated = match header {
+        Some(header) if header.version > INDEX_VERSION => {
+            return Err(Error::IndexVersion(header.version))
+        }
+        Some(_) => {
+            lines.next();
+            false
+        }
+        None => true,
+    };
+
+    let mut records = Vec::new();
+    for (i, line) in lines.filter(|(_, l)| !l.trim().is_empty()) {
+        let record = match migrated {
+            true => parse_old_line(line),
+            false => serde_json::from_str::<IndexRecord>(line).ok(),
+        };
+        match record {
+            Some(record) => records.push(record),
+            None => eprintln!(
+                "[Warning] skipping invalid line {} of the index: {}",
+                i + 1,
+                line
+            ),
+        }
+    }
+    Ok((records, migrated))
+}
+
+fn parse_old_line(line: &str) -> Option<IndexRecord> {
+    let (uuid, path) = line.split_once(' ')?;
+    let uuid = Uuid::parse_str(uuid).ok()?;
+    match path.is_empty() {
+        true => None,
+        false => Some(IndexRecord::without_metadata(uuid, path)),
+    }
+}
+
+/// Map every uuid to its path. Aliases, which follow the path of their entry, are skipped.
 pub fn to_hashmap<'a>(index_list: &'a [(Uuid, String)]) -> HashMap<Uuid, &'a str> {
     let mut map: HashMap<Uuid, &'a str> = HashMap::new();
     for (id, path) in index_list {
-        map.insert(*id, path);
+        map.entry(*id).or_insert(path);
     }
     map
 }
 
-pub fn to_hashmap_reverse<'a>(index_list: &'a [(Uuid, String)]) -> HashMap<&'a str, Uuid> {
+/// Map every path and alias to its uuid. Fails if two entries claim the same path.
+pub fn to_hashmap_reverse<'a>(index_list: &'a [(Uuid, String)]) -> Result<HashMap<&'a str, Uuid>> {
     let mut map: HashMap<&'a str, Uuid> = HashMap::new();
     for (id, path) in index_list {
-        map.insert(path, *id);
+        match map.insert(path, *id) {
+            Some(other) if other != *id => {
+                return Err(Error::PathConflict(path.to_string(), other, *id))
+            }
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+/// All paths claimed by more than one entry, together with two of those entries.
+fn conflicts(records: &[IndexRecord]) -> Vec<(String, Uuid, Uuid)> {
+    let mut owners: HashMap<&str, Uuid> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for record in records {
+        for path in record.paths() {
+            match owners.insert(path, record.uuid) {
+                Some(other) if other != record.uuid => {
+                    conflicts.push((path.to_string(), other, record.uuid))
+                }
+                _ => {}
+            }
+        }
+    }
+    conflicts
+}
+
+/// Fail if `records` contain a path claimed by two entries, which was not claimed twice in
+/// `previous` already. Existing conflicts must not prevent fixing them.
+fn check_conflicts(previous: &[(String, Uuid, Uuid)], records: &[IndexRecord]) -> Result<()> {
+    match conflicts(records)
+        .into_iter()
+        .find(|(path, _, _)| !previous.iter().any(|(p, _, _)| p == path))
+    {
+        Some((path, a, b)) => Err(Error::PathConflict(path, a, b)),
+        None => Ok(()),
     }
-    map
+}
+
+/// Paths of the index claimed by more than one entry, together with two of those entries.
+pub fn get_conflicts() -> Result<Vec<(String, Uuid, Uuid)>> {
+    Ok(conflicts(&get_records()?))
 }
 
 pub fn to_graph<'a>(index_list: &'a [(Uuid, String)]) -> (Graph<&'a str, ()>, NodeIndex) {
@@ -251,51 +469,322 @@ pub fn to_graph<'a>(index_list: &'a [(Uuid, String)]) -> (Graph<&'a str, ()>, No
     (g, root)
 }
 
-pub fn write(index_list: &[(Uuid, String)]) -> Result<()> {
-    let mut p = Command::new("pass")
-        .arg("insert")
-        .arg("--multiline")
-        .arg(format!("{}/{}", CFG.main.uuid_folder, CFG.main.index_entry))
-        .stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .spawn()?;
-
-    if let Some(mut writer) = p.stdin.take() {
-        for (id, path) in index_list {
-            writer.write_all(&format!("{} {}", id, path).into_bytes())?;
-            writer.write_all("\n".as_bytes())?;
-        }
+/// Content of the index storing `records`.
+fn serialize(records: &[IndexRecord]) -> String {
+    let header = IndexHeader {
+        version: INDEX_VERSION,
+    };
+    let mut content = serde_json::to_string(&header).unwrap();
+    content.push('\n');
+    for record in records {
+        content.push_str(&serde_json::to_string(record).unwrap());
+        content.push('\n');
     }
+    content
+}
 
-    p.wait()?;
+pub fn write_records(records: &[IndexRecord]) -> Result<()> {
+    let _lock = IndexLock::acquire()?;
+    agent::write(&index_entry(), &serialize(records))?;
 
+    // invalidate the cache, as the modification time might not have changed.
+    INDEX.with(|index| index.replace(Index::default()));
     Ok(())
 }
 
 pub fn insert(id: Uuid, path: &str) -> Result<()> {
-    let mut index_list = get_index()?;
-    index_list.push((id, path.to_string()));
-    touch_entry(id);
-    write(&index_list)
+    insert_record(IndexRecord::new(id, path))
+}
+
+pub fn insert_record(record: IndexRecord) -> Result<()> {
+    touch_entry(record.uuid);
+    modify(|records| {
+        records.push(record);
+        Ok(true)
+    })
+}
+
+/// Read, modify and write the index while holding the lock, such that changes of concurrent
+/// processes are never lost. The index is only written if `f` returns `true`.
+fn modify(f: impl FnOnce(&mut Vec<IndexRecord>) -> Result<bool>) -> Result<()> {
+    let _lock = IndexLock::acquire()?;
+    // another process might have changed the index within the resolution of the timestamp
+    INDEX.with(|index| index.replace(Index::default()));
+    let mut records = get_records()?;
+    let previous = conflicts(&records);
+    if f(&mut records)? {
+        check_conflicts(&previous, &records)?;
+        write_records(&records)?;
+    }
+    Ok(())
+}
+
+/// Modify the record of an entry, and update its modification time. Nothing is written if the
+/// entry is not part of the index, or if there is no index yet.
+pub fn update(id: Uuid, f: impl FnOnce(&mut IndexRecord)) -> Result<()> {
+    let result = modify(|records| match records.iter_mut().find(|r| r.uuid == id) {
+        Some(record) => {
+            f(record);
+            record.modified = Some(now());
+            Ok(true)
+        }
+        None => Ok(false),
+    });
+    match result {
+        Err(Error::NoIndexFile) => Ok(()),
+        result => result,
+    }
 }
 
 pub fn remove(id: Uuid) -> Result<()> {
-    let index_list: Vec<(Uuid, String)> = get_index()?.into_iter().filter(|x| x.0 != id).collect();
+    modify(|records| {
+        records.retain(|r| r.uuid != id);
+
+        // remove the pass entry and its attachments
+        let backend = get_backend();
+        backend.delete(&format!("{}/{}", CFG.main.uuid_folder, id))?;
+        for blob in attachment_blobs(id)? {
+            backend.delete(&blob)?;
+        }
+        Ok(true)
+    })
+}
 
-    // remove the pass entry
-    Command::new("pass")
-        .arg("rm")
-        .arg("--force")
-        .arg(format!("{}/{}", CFG.main.uuid_folder, id))
-        .output()?;
+/// Folder storing the attachments of the entry `id`, relative to the root of the store.
+pub fn attachments_folder(id: Uuid) -> String {
+    format!(
+        "{}/{}/{}",
+        CFG.main.uuid_folder,
+        def::ATTACHMENTS_FOLDER,
+        id
+    )
+}
 
-    write(&index_list)
+/// Names of all attachment blobs stored for the entry `id`.
+pub fn attachment_blobs(id: Uuid) -> Result<Vec<String>> {
+    let folder = attachments_folder(id);
+    let backend = get_backend();
+    match backend.root().join(&folder).is_dir() {
+        true => backend.list(&folder),
+        false => Ok(Vec::new()),
+    }
 }
 
 pub fn mv(id: Uuid, dst: String) -> Result<()> {
-    let mut index_list: Vec<(Uuid, String)> =
-        get_index()?.into_iter().filter(|x| x.0 != id).collect();
+    modify(|records| {
+        match records.iter_mut().find(|r| r.uuid == id) {
+            Some(record) => {
+                record.aliases.retain(|a| a != &dst);
+                record.path = dst;
+                record.modified = Some(now());
+            }
+            None => records.push(IndexRecord::new(id, dst)),
+        }
+        Ok(true)
+    })
+}
+
+/// Changes to multiple entries and to the index, which are applied together. The index is locked
+/// until the transaction is dropped. Nothing is written before `commit`, which either applies all
+/// staged changes as a single git commit, or restores all files if any change fails.
+pub struct Transaction {
+    _lock: IndexLock,
+    message: String,
+    records: Vec<IndexRecord>,
+    /// Paths which were claimed by multiple entries before the transaction.
+    conflicts: Vec<(String, Uuid, Uuid)>,
+    /// Staged content of entries, where `None` deletes the entry.
+    staged: Vec<(String, Option<String>)>,
+    changed: bool,
+}
+
+impl Transaction {
+    /// Lock and read the index. A missing index is treated as an empty one.
+    pub fn begin(message: impl Into<String>) -> Result<Self> {
+        let lock = IndexLock::acquire()?;
+        INDEX.with(|index| index.replace(Index::default()));
+        let records = match get_records() {
+            Ok(records) => records,
+            Err(Error::NoIndexFile) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            _lock: lock,
+            message: message.into(),
+            conflicts: conflicts(&records),
+            records,
+            staged: Vec::new(),
+            changed: false,
+        })
+    }
+
+    /// Records of the index, including the staged changes.
+    pub fn records(&self) -> &[IndexRecord] {
+        &self.records
+    }
+
+    /// Stage writing `content` to the entry `name`.
+    pub fn write(&mut self, name: impl Into<String>, content: impl Into<String>) {
+        self.stage(name.into(), Some(content.into()));
+    }
+
+    /// Stage deleting the entry `name`.
+    pub fn delete(&mut self, name: impl Into<String>) {
+        self.stage(name.into(), None);
+    }
+
+    fn stage(&mut self, name: String, content: Option<String>) {
+        self.staged.retain(|(n, _)| n != &name);
+        self.staged.push((name, content));
+        self.changed = true;
+    }
 
-    index_list.push((id, dst));
-    write(&index_list)
+    pub fn insert(&mut self, record: IndexRecord) {
+        self.records.push(record);
+        self.changed = true;
+    }
+
+    /// Modify the record of an entry and update its modification time, if it is indexed.
+    pub fn update(&mut self, id: Uuid, f: impl FnOnce(&mut IndexRecord)) {
+        self.update_metadata(id, |record| {
+            f(record);
+            record.modified = Some(now());
+        })
+    }
+
+    /// Modify the record of an entry without changing its modification time, if it is indexed.
+    pub fn update_metadata(&mut self, id: Uuid, f: impl FnOnce(&mut IndexRecord)) {
+        if let Some(record) = self.records.iter_mut().find(|r| r.uuid == id) {
+            f(record);
+            self.changed = true;
+        }
+    }
+
+    /// Remove the entry `id` from the index, and delete it together with its attachments.
+    pub fn remove(&mut self, id: Uuid) -> Result<()> {
+        self.records.retain(|r| r.uuid != id);
+        self.delete(format!("{}/{}", CFG.main.uuid_folder, id));
+        for blob in attachment_blobs(id)? {
+            self.delete(blob);
+        }
+        Ok(())
+    }
+
+    pub fn mv(&mut self, id: Uuid, dst: String) {
+        match self.records.iter_mut().find(|r| r.uuid == id) {
+            Some(record) => {
+                record.aliases.retain(|a| a != &dst);
+                record.path = dst;
+                record.modified = Some(now());
+            }
+            None => self.records.push(IndexRecord::new(id, dst)),
+        }
+        self.changed = true;
+    }
+
+    /// Apply all staged changes. If one of them fails, all files are restored.
+    pub fn commit(mut self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        check_conflicts(&self.conflicts, &self.records)?;
+        let backend = get_backend();
+        let content = serialize(&self.records);
+        self.stage(index_entry(), Some(content));
+
+        let root = backend.root();
+        let repo = root.join(def::GIT_FOLDER).is_dir();
+        let base = match repo {
+            true => git_head(root),
+            false => None,
+        };
+        let files: Vec<PathBuf> = self.staged.iter().map(|(n, _)| backend.file(n)).collect();
+        let backups: Vec<Option<Vec<u8>>> = files.iter().map(|f| fs::read(f).ok()).collect();
+
+        let index = index_entry();
+        let result = self
+            .staged
+            .iter()
+            .try_for_each(|(name, content)| match content {
+                Some(content) if name == &index => agent::write(name, content),
+                Some(content) => backend.write(name, content),
+                None => backend.delete(name),
+            });
+        INDEX.with(|index| index.replace(Index::default()));
+
+        match result {
+            Ok(()) if repo => commit_squashed(root, base.as_deref(), &files, &self.message),
+            Ok(()) => Ok(()),
+            Err(e) => {
+                for (file, backup) in files.iter().zip(backups) {
+                    let _ = match backup {
+                        Some(content) => fs::create_dir_all(file.parent().unwrap())
+                            .and_then(|_| fs::write(file, content)),
+                        None => fs::remove_file(file),
+                    };
+                }
+                if repo {
+                    reset(root, base.as_deref(), &files)?;
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+fn git(root: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(root);
+    cmd
+}
+
+/// Current commit of the repository, or `None` if nothing was committed yet.
+fn git_head(root: &Path) -> Option<String> {
+    let output = git(root)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => None,
+    }
+}
+
+/// Move the branch back to `base`, keeping the work tree. Commits of the backend during the
+/// transaction are undone this way.
+fn reset_branch(root: &Path, base: Option<&str>) -> Result<()> {
+    match (base, git_head(root)) {
+        (Some(base), _) => run_checked(git(root).args(["reset", "--quiet", "--soft", base]))?,
+        (None, Some(_)) => run_checked(git(root).args(["update-ref", "-d", "HEAD"]))?,
+        (None, None) => return Ok(()),
+    };
+    Ok(())
+}
+
+/// Replace all commits since `base` by a single commit of `files`.
+fn commit_squashed(
+    root: &Path,
+    base: Option<&str>,
+    files: &[PathBuf],
+    message: &str,
+) -> Result<()> {
+    reset_branch(root, base)?;
+    git_stage(root, files)?;
+    // the changes might cancel each other out
+    if git(root)
+        .args(["diff", "--cached", "--quiet"])
+        .status()?
+        .success()
+    {
+        return Ok(());
+    }
+    run_checked(git(root).args(["commit", "--quiet", "-m", message]))?;
+    Ok(())
+}
+
+/// Undo all commits since `base`, and unstage the changes to `files`.
+fn reset(root: &Path, base: Option<&str>, files: &[PathBuf]) -> Result<()> {
+    reset_branch(root, base)?;
+    run_checked(git(root).args(["reset", "--quiet", "--"]).args(files))?;
+    Ok(())
 }
@@ -14,5 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see http://www.gnu.org/licenses/
 
+pub mod agent;
+pub mod backend;
 pub mod entry;
+pub mod expiry;
+pub mod field;
+pub mod generator;
+pub mod hibp;
+pub mod history;
 pub mod index;
+pub mod otp;
+pub mod store;
+pub mod strength;
+pub mod template;
+pub mod trash;
@@ -0,0 +1,164 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Time-based one-time passwords (RFC 6238), configured by `otpauth://totp/...` URIs.
+
+use std::convert::TryInto;
+use std::time::SystemTime;
+
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::errors::{Error, Result};
+
+/// Scheme of the lines storing the otp configuration of an entry.
+pub const OTP_SCHEME: &str = "otpauth://";
+
+/// Hash function used to compute the codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Totp {
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+impl Totp {
+    pub fn new(secret: Vec<u8>, algorithm: Algorithm, digits: u32, period: u64) -> Self {
+        Self {
+            secret,
+            algorithm,
+            digits,
+            period,
+        }
+    }
+
+    /// Parse an URI like `otpauth://totp/Label?secret=JBSWY3DPEHPK3PXP&digits=6`.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::Otp(format!("{} in {}", reason, uri));
+        let rest = uri
+            .trim()
+            .strip_prefix(OTP_SCHEME)
+            .ok_or_else(|| invalid("invalid scheme"))?;
+        if !rest.to_lowercase().starts_with("totp/") {
+            return Err(invalid("only totp is supported"));
+        }
+        let query = rest.split_once('?').map(|(_, q)| q).unwrap_or_default();
+
+        let mut secret = None;
+        let mut totp = Self::new(Vec::new(), Algorithm::Sha1, 6, 30);
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            match key.to_lowercase().as_str() {
+                "secret" => {
+                    secret = Some(base32_decode(value).ok_or_else(|| invalid("bad secret"))?)
+                }
+                "digits" => totp.digits = value.parse().map_err(|_| invalid("bad digits"))?,
+                "period" => totp.period = value.parse().map_err(|_| invalid("bad period"))?,
+                "algorithm" => {
+                    totp.algorithm = match value.to_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        _ => return Err(invalid("unknown algorithm")),
+                    }
+                }
+                _ => {}
+            }
+        }
+        totp.secret = secret.ok_or_else(|| invalid("missing secret"))?;
+        if !(1..=10).contains(&totp.digits) || totp.period == 0 {
+            return Err(invalid("bad parameters"));
+        }
+        Ok(totp)
+    }
+
+    /// Code at `time`, given in seconds since the unix epoch.
+    pub fn code_at(&self, time: u64) -> String {
+        let counter = (time / self.period).to_be_bytes();
+        let mac = hmac(self.algorithm, &self.secret, &counter);
+        let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes(mac[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+        let code = binary as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Current code.
+    pub fn code(&self) -> String {
+        self.code_at(now())
+    }
+
+    /// Seconds until the current code expires.
+    pub fn remaining(&self) -> u64 {
+        self.period - now() % self.period
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// HMAC (RFC 2104) of `message`.
+fn hmac(algorithm: Algorithm, key: &[u8], message: &[u8]) -> Vec<u8> {
+    match algorithm {
+        Algorithm::Sha1 => mac::<Hmac<Sha1>>(key, message),
+        Algorithm::Sha256 => mac::<Hmac<Sha256>>(key, message),
+        Algorithm::Sha512 => mac::<Hmac<Sha512>>(key, message),
+    }
+}
+
+fn mac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    // HMAC accepts keys of any length
+    let mut mac = <M as KeyInit>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Decode base32 (RFC 4648), ignoring the case, padding and whitespace.
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in input.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u32 - 'A' as u32,
+            c @ '2'..='7' => c as u32 - '2' as u32 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    match result.is_empty() {
+        true => None,
+        false => Some(result),
+    }
+}
@@ -0,0 +1,136 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Named password stores, configured in the `[stores]` table. Unless a single store is selected
+//! with `--store`, entries are searched in all of them, and their paths are prefixed with the
+//! name of the store (e.g., `team/web/github`).
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use uuid::Uuid;
+
+use crate::config::CFG;
+use crate::errors::{Error, Result};
+use crate::pass::backend::{get_backend, new_backend, replace_backend, Backend};
+use crate::pass::index;
+
+thread_local! {
+    static STORES: RefCell<Vec<Store>> = RefCell::new(from_config());
+    static SELECTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Password store with a name.
+#[derive(Clone)]
+pub struct Store {
+    pub name: String,
+    pub backend: Rc<dyn Backend>,
+}
+
+fn from_config() -> Vec<Store> {
+    CFG.stores
+        .iter()
+        .map(|s| Store {
+            name: s.name.to_string(),
+            backend: new_backend(s.backend.unwrap_or(CFG.main.backend), s.root()),
+        })
+        .collect()
+}
+
+/// Replace the configured stores.
+#[cfg(test)]
+pub fn set_stores(stores: Vec<Store>) {
+    STORES.with(|s| s.replace(stores));
+    SELECTED.with(|s| s.set(false));
+}
+
+fn find(name: &str) -> Result<Store> {
+    STORES.with(|stores| {
+        stores
+            .borrow()
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownStore(name.to_string()))
+    })
+}
+
+/// Only use the store `name` from now on.
+pub fn select(name: &str) -> Result<()> {
+    replace_backend(find(name)?.backend);
+    SELECTED.with(|s| s.set(true));
+    Ok(())
+}
+
+/// Returns `true` if entries are searched in all configured stores.
+pub fn is_combined() -> bool {
+    !SELECTED.with(|s| s.get()) && STORES.with(|s| !s.borrow().is_empty())
+}
+
+/// Call `f` for every store, while its backend is used, and collect the results.
+pub fn map_stores<T>(mut f: impl FnMut(&Store) -> Result<Vec<T>>) -> Result<Vec<T>> {
+    let previous = get_backend();
+    let stores = STORES.with(|s| s.borrow().clone());
+    let result = stores
+        .iter()
+        .map(|store| {
+            replace_backend(store.backend.clone());
+            f(store)
+        })
+        .collect::<Result<Vec<_>>>();
+    replace_backend(previous);
+    Ok(result?.into_iter().flatten().collect())
+}
+
+/// Index of all stores, where every path is prefixed with the name of its store.
+pub fn get_index() -> Result<Vec<(Uuid, String)>> {
+    map_stores(|store| {
+        Ok(index::get_index()?
+            .into_iter()
+            .map(|(id, path)| (id, format!("{}/{}", store.name, path)))
+            .collect())
+    })
+}
+
+/// Use the store whose name is the first component of `path`, and return the path of the entry
+/// within that store.
+pub fn enter(path: &str) -> Result<String> {
+    let (name, rest) = path
+        .split_once('/')
+        .ok_or_else(|| Error::UnknownPath(path.to_string()))?;
+    let store = find(name).map_err(|_| Error::UnknownPath(path.to_string()))?;
+    replace_backend(store.backend);
+    Ok(rest.to_string())
+}
+
+/// Use the store which contains the entry `id`.
+pub fn enter_uuid(id: Uuid) -> Result<()> {
+    let name = format!("{}/{}", CFG.main.uuid_folder, id);
+    let store = STORES.with(|stores| {
+        stores
+            .borrow()
+            .iter()
+            .find(|s| s.backend.file(&name).is_file())
+            .cloned()
+    });
+    match store {
+        Some(store) => {
+            replace_backend(store.backend);
+            Ok(())
+        }
+        None => Err(Error::EntryRead(id.to_string())),
+    }
+}
@@ -0,0 +1,288 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Offline estimation of the strength of a password, similar to zxcvbn. The password is split
+//! into the cheapest sequence of patterns an attacker would try: common passwords and dictionary
+//! words (also with capital letters and leetspeak), repeated characters, sequences like `abc`,
+//! keyboard rows and years. Everything else is guessed character by character. The strength is
+//! the number of bits needed to guess the password this way.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use lazy_static::lazy_static;
+
+use crate::pass::generator::WORDLIST;
+
+/// Commonly used passwords, most common first.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Smallest number of bits of each score from 1 to 4.
+const SCORE_BITS: [f64; 4] = [20.0, 35.0, 50.0, 70.0];
+
+const LABELS: [&str; 5] = ["very weak", "weak", "fair", "strong", "very strong"];
+
+lazy_static! {
+    /// Bits needed to guess each word of the dictionary: its rank among the common passwords,
+    /// or the size of the wordlist.
+    static ref DICTIONARY: HashMap<&'static str, (f64, Pattern)> = {
+        let mut dictionary = HashMap::new();
+        let words = WORDLIST.lines().count() as f64;
+        for word in WORDLIST.lines() {
+            dictionary.insert(word, (words.log2(), Pattern::Word));
+        }
+        for (rank, password) in COMMON_PASSWORDS.lines().enumerate() {
+            let bits = ((rank + 1) as f64).log2();
+            dictionary.insert(password, (bits, Pattern::CommonPassword));
+        }
+        dictionary
+    };
+    static ref LONGEST_WORD: usize = DICTIONARY.keys().map(|w| w.len()).max().unwrap_or(0);
+}
+
+/// Kind of pattern found in a password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    CommonPassword,
+    Word,
+    Repeat,
+    Sequence,
+    Keyboard,
+    Year,
+}
+
+impl Pattern {
+    fn warning(self) -> &'static str {
+        match self {
+            Pattern::CommonPassword => "is a commonly used password",
+            Pattern::Word => "contains a dictionary word",
+            Pattern::Repeat => "contains repeated characters",
+            Pattern::Sequence => "contains a sequence like abc or 123",
+            Pattern::Keyboard => "contains a row of the keyboard",
+            Pattern::Year => "contains a year",
+        }
+    }
+}
+
+/// Estimated strength of a password.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strength {
+    /// Score from 0 (very weak) to 4 (very strong).
+    pub score: u8,
+    /// Number of bits needed to guess the password.
+    pub bits: f64,
+    /// Patterns found in the password, which make it easier to guess.
+    pub patterns: Vec<Pattern>,
+}
+
+impl Strength {
+    /// Estimate the strength of `password`.
+    pub fn of(password: &str) -> Self {
+        let chars: Vec<char> = password.chars().collect();
+        let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+        let char_bits = (cardinality(&chars) as f64).log2();
+
+        // cheapest guess of every prefix, and the pattern and start of its last part
+        let mut best: Vec<(f64, Option<Pattern>, usize)> =
+            vec![(f64::INFINITY, None, 0); chars.len() + 1];
+        best[0].0 = 0.0;
+        for start in 0..chars.len() {
+            let bits = best[start].0;
+            let mut parts = vec![(start + 1, char_bits, None)];
+            parts.extend(matches(&chars, &lower, start, char_bits));
+            for (end, cost, pattern) in parts {
+                if bits + cost < best[end].0 {
+                    best[end] = (bits + cost, pattern, start);
+                }
+            }
+        }
+
+        let mut patterns = Vec::new();
+        let mut end = chars.len();
+        while end > 0 {
+            let (_, pattern, start) = best[end];
+            if let Some(pattern) = pattern {
+                if !patterns.contains(&pattern) {
+                    patterns.insert(0, pattern);
+                }
+            }
+            end = start;
+        }
+        let bits = best[chars.len()].0;
+        Self {
+            score: SCORE_BITS.iter().filter(|b| bits >= **b).count() as u8,
+            bits,
+            patterns,
+        }
+    }
+
+    /// Name of the score, e.g., `strong`.
+    pub fn label(&self) -> &'static str {
+        LABELS[self.score as usize]
+    }
+
+    /// Why the password is easy to guess, e.g., `contains a dictionary word`. Strong passwords
+    /// have no warning, even if they contain patterns.
+    pub fn warning(&self) -> Option<&'static str> {
+        match self.score {
+            0..=2 => self.patterns.first().map(|p| p.warning()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}/4, {:.0} bits)",
+            self.label(),
+            self.score,
+            self.bits
+        )?;
+        if let Some(warning) = self.warning() {
+            write!(f, ", {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of characters an attacker has to try for every single character of the password.
+fn cardinality(chars: &[char]) -> usize {
+    let has = |f: fn(&char) -> bool| chars.iter().any(f);
+    let mut result = 0;
+    if has(char::is_ascii_lowercase) {
+        result += 26;
+    }
+    if has(char::is_ascii_uppercase) {
+        result += 26;
+    }
+    if has(char::is_ascii_digit) {
+        result += 10;
+    }
+    if has(char::is_ascii_punctuation) || has(|c| *c == ' ') {
+        result += 33;
+    }
+    if has(|c| !c.is_ascii()) {
+        result += 100;
+    }
+    result.max(1)
+}
+
+fn leet(c: char, one: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' => one,
+        '!' | '|' => 'i',
+        '0' => 'o',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        _ => c,
+    }
+}
+
+/// All patterns starting at `start`, with the position after their end and their bits.
+fn matches(
+    chars: &[char],
+    lower: &[char],
+    start: usize,
+    char_bits: f64,
+) -> Vec<(usize, f64, Option<Pattern>)> {
+    let mut result = Vec::new();
+
+    // dictionary words, with capital letters and leetspeak
+    let last = chars.len().min(start + *LONGEST_WORD);
+    for end in start + 3..=last {
+        let upper = chars[start..end]
+            .iter()
+            .filter(|c| c.is_uppercase())
+            .count();
+        let upper_bits = match upper {
+            0 => 0.0,
+            u if u == end - start || (u == 1 && chars[start].is_uppercase()) => 1.0,
+            u => u as f64,
+        };
+        for one in ['i', 'l'] {
+            let word: String = lower[start..end].iter().map(|c| leet(*c, one)).collect();
+            let substituted = word
+                .chars()
+                .zip(lower[start..end].iter())
+                .filter(|(a, b)| a != *b)
+                .count();
+            let plain: String = lower[start..end].iter().collect();
+            for (candidate, leet_bits) in [(&plain, 0.0), (&word, substituted as f64)] {
+                if let Some((bits, pattern)) = DICTIONARY.get(candidate.as_str()) {
+                    let bits = bits + upper_bits + leet_bits;
+                    result.push((end, bits, Some(*pattern)));
+                }
+            }
+        }
+    }
+
+    // repeated characters
+    let repeat = chars[start..]
+        .iter()
+        .take_while(|c| **c == chars[start])
+        .count();
+    for end in start + 3..=start + repeat {
+        result.push((
+            end,
+            char_bits + ((end - start) as f64).log2(),
+            Some(Pattern::Repeat),
+        ));
+    }
+
+    // sequences like abc, 987 or xyz: the first character, the direction and the length
+    if start + 1 < chars.len() {
+        let step = lower[start + 1] as i32 - lower[start] as i32;
+        if step == 1 || step == -1 {
+            let mut end = start + 2;
+            while end < chars.len() && lower[end] as i32 - lower[end - 1] as i32 == step {
+                end += 1;
+                let bits = char_bits + 1.0 + ((end - start) as f64).log2();
+                result.push((end, bits, Some(Pattern::Sequence)));
+            }
+        }
+    }
+
+    // rows of the keyboard, in both directions: the row, the first key and the length
+    let typed: String = lower[start..].iter().collect();
+    for row in KEYBOARD_ROWS.iter() {
+        let reversed: String = row.chars().rev().collect();
+        for row in [row.to_string(), reversed] {
+            let len = (4..=typed.len().min(row.len()))
+                .rev()
+                .find(|len| typed.is_char_boundary(*len) && row.contains(&typed[..*len]));
+            if let Some(len) = len {
+                let bits = 6.0 + (len as f64).log2();
+                result.push((start + len, bits, Some(Pattern::Keyboard)));
+            }
+        }
+    }
+
+    // years from 1900 to 2099
+    if start + 4 <= chars.len() {
+        let year: String = chars[start..start + 4].iter().collect();
+        if let Ok(1900..=2099) = year.parse::<u32>() {
+            result.push((start + 4, 200f64.log2(), Some(Pattern::Year)));
+        }
+    }
+
+    result
+}
@@ -0,0 +1,221 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Entry types, like logins or credit cards. A type decides which values are asked for when
+//! inserting an entry, how its fields are validated, and how they are shown. The type of an entry
+//! is stored in its `type:` field, and entries without one are logins. Additional types can be
+//! defined in the `[templates]` table of the config, which replace built-in types of the same
+//! name:
+//!
+//! ```text
+//! [templates.server]
+//! username = true
+//! url = false
+//! fields = [{ name = "host" }, { name = "port", format = "digits", optional = true }]
+//! ```
+
+use crate::config::{ConfigTemplate, FieldFormat, CFG};
+use crate::errors::{Error, Result};
+
+/// Type of entries without a `type:` field.
+pub const DEFAULT_TYPE: &str = "login";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateField {
+    pub name: String,
+    pub format: FieldFormat,
+    pub optional: bool,
+    /// The field is secret, independent of its name.
+    pub secret: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    /// Name of the password shown when asking for it, e.g., `PIN`.
+    pub password: String,
+    /// Offer to generate a random password.
+    pub generate: bool,
+    pub username: bool,
+    pub url: bool,
+    pub fields: Vec<TemplateField>,
+}
+
+impl Template {
+    fn builtin() -> Vec<Self> {
+        let field = |name: &str, format, optional, secret| TemplateField {
+            name: name.to_string(),
+            format,
+            optional,
+            secret,
+        };
+        let template = |name: &str, password: &str, generate, username, url, fields| Self {
+            name: name.to_string(),
+            password: password.to_string(),
+            generate,
+            username,
+            url,
+            fields,
+        };
+        vec![
+            template(DEFAULT_TYPE, "password", true, true, true, vec![]),
+            template(
+                "card",
+                "PIN",
+                false,
+                false,
+                false,
+                vec![
+                    field("cardholder", FieldFormat::Text, false, false),
+                    field("card number", FieldFormat::Card, false, true),
+                    field("expiry", FieldFormat::Expiry, false, false),
+                    field("cvv", FieldFormat::Digits, false, true),
+                ],
+            ),
+            template(
+                "ssh",
+                "passphrase",
+                true,
+                true,
+                false,
+                vec![
+                    field("host", FieldFormat::Text, true, false),
+                    field("private key", FieldFormat::Note, false, true),
+                ],
+            ),
+            template(
+                "wifi",
+                "password",
+                true,
+                false,
+                false,
+                vec![
+                    field("ssid", FieldFormat::Text, false, false),
+                    field("security", FieldFormat::Text, true, false),
+                ],
+            ),
+            template("api", "token", false, true, true, vec![]),
+        ]
+    }
+
+    fn from_config(template: &ConfigTemplate) -> Self {
+        Self {
+            name: template.name.to_string(),
+            password: template.password.to_string(),
+            generate: template.generate,
+            username: template.username,
+            url: template.url,
+            fields: template
+                .fields
+                .iter()
+                .map(|f| TemplateField {
+                    name: f.name.to_string(),
+                    format: f.format,
+                    optional: f.optional,
+                    secret: f.secret,
+                })
+                .collect(),
+        }
+    }
+
+    /// All entry types. User-defined templates replace the built-in types with the same name.
+    pub fn all() -> Vec<Self> {
+        let mut all: Vec<Self> = Self::builtin()
+            .into_iter()
+            .filter(|t| !CFG.templates.iter().any(|c| c.name == t.name))
+            .collect();
+        all.extend(CFG.templates.iter().map(Self::from_config));
+        all
+    }
+
+    /// The entry type `name` (case insensitive).
+    pub fn get(name: &str) -> Result<Self> {
+        let name = name.trim().to_lowercase();
+        Self::all()
+            .into_iter()
+            .find(|t| t.name.to_lowercase() == name)
+            .ok_or(Error::UnknownType(name))
+    }
+
+    /// Field `name` of the type (case insensitive).
+    pub fn field(&self, name: &str) -> Option<&TemplateField> {
+        let name = name.trim().to_lowercase();
+        self.fields.iter().find(|f| f.name.to_lowercase() == name)
+    }
+}
+
+impl FieldFormat {
+    /// Check `value` and return it in its normalized form, or describe why it is invalid.
+    pub fn validate(self, value: &str) -> std::result::Result<String, &'static str> {
+        let value = value.trim();
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        match self {
+            FieldFormat::Text | FieldFormat::Note => Ok(value.to_string()),
+            FieldFormat::Digits if is_digits(value) => Ok(value.to_string()),
+            FieldFormat::Digits => Err("must only contain digits"),
+            FieldFormat::Card => {
+                let number: String = value.chars().filter(|c| *c != ' ' && *c != '-').collect();
+                if !is_digits(&number) || !(12..=19).contains(&number.len()) {
+                    Err("must have between 12 and 19 digits")
+                } else if !luhn(&number) {
+                    Err("has an invalid check digit")
+                } else {
+                    Ok(number)
+                }
+            }
+            FieldFormat::Expiry => match value.split_once('/') {
+                Some((month, year))
+                    if is_digits(month)
+                        && is_digits(year)
+                        && matches!(month.parse::<u32>(), Ok(1..=12))
+                        && (year.len() == 2 || year.len() == 4) =>
+                {
+                    Ok(format!("{:0>2}/{}", month, &year[year.len() - 2..]))
+                }
+                _ => Err("must be written as MM/YY"),
+            },
+        }
+    }
+
+    /// `value` as it is shown while the entry is hidden. Card numbers only show the last 4
+    /// digits, all other values are masked entirely.
+    pub fn mask(self, value: &str) -> String {
+        let len = value.chars().count();
+        match self {
+            FieldFormat::Card if len > 4 => {
+                let last: String = value.chars().skip(len - 4).collect();
+                format!("{}{}", "*".repeat(len - 4), last)
+            }
+            _ => "*".repeat(len),
+        }
+    }
+}
+
+/// Check the last digit of a card number with the Luhn algorithm.
+fn luhn(number: &str) -> bool {
+    let sum: u32 = number
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
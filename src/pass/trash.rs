@@ -0,0 +1,205 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Deleted entries, which are kept out of the index until the trash is emptied. The trash
+//! mirrors the uuid folder: the entry `uuids/[uuid]` is moved to `uuids/trash/[uuid]`, and its
+//! attachments to `uuids/trash/attachments/[uuid]/`. The path of every trashed entry and the time
+//! when it was deleted are stored in the encrypted `uuids/trash/index`, one JSON record per line.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::CFG;
+use crate::def;
+use crate::errors::{Error, Result};
+use crate::pass::backend::get_backend;
+use crate::pass::entry::Entry;
+use crate::pass::index::{self, now, Transaction};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TrashRecord {
+    pub uuid: Uuid,
+    pub path: String,
+    /// Aliases of the entry, which are restored unless another entry uses them by then.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Time since which the password of the entry was used, which is kept when it is restored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_changed: Option<u64>,
+    /// Time of the deletion, in seconds since the unix epoch.
+    pub deleted: u64,
+}
+
+/// Name of the file in the trash, which stores the file `name` of the uuid folder.
+fn trashed(name: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        CFG.main.uuid_folder,
+        def::TRASH_FOLDER,
+        &name[CFG.main.uuid_folder.len() + 1..]
+    )
+}
+
+fn trash_index() -> String {
+    trashed(&format!(
+        "{}/{}",
+        CFG.main.uuid_folder, CFG.main.index_entry
+    ))
+}
+
+fn entry_name(id: Uuid) -> String {
+    format!("{}/{}", CFG.main.uuid_folder, id)
+}
+
+/// All trashed entries, starting with the one deleted first. Fails if a line of the trash index
+/// cannot be parsed, instead of forgetting that entry when the trash index is written again.
+pub fn records() -> Result<Vec<TrashRecord>> {
+    let backend = get_backend();
+    let name = trash_index();
+    if !backend.file(&name).is_file() {
+        return Ok(Vec::new());
+    }
+    backend
+        .read(&name)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| Error::TrashIndex(format!("line {}: {}", i + 1, e)))
+        })
+        .collect()
+}
+
+/// Stage writing the trash index, or deleting it if the trash is empty.
+fn write_records(records: &[TrashRecord], tx: &mut Transaction) {
+    match records.is_empty() {
+        true if get_backend().file(&trash_index()).is_file() => tx.delete(trash_index()),
+        true => {}
+        false => tx.write(
+            trash_index(),
+            records
+                .iter()
+                .map(|r| serde_json::to_string(r).unwrap() + "\n")
+                .collect::<String>(),
+        ),
+    }
+}
+
+/// Move the entry `id` together with its attachments into the trash.
+pub fn trash(id: Uuid) -> Result<()> {
+    let backend = get_backend();
+    let mut tx = Transaction::begin(format!("Move {} to the trash.", id))?;
+    let (path, aliases, password_changed) = match tx.records().iter().find(|r| r.uuid == id) {
+        Some(record) => (
+            record.path.clone(),
+            record.aliases.clone(),
+            record.password_since(),
+        ),
+        None => return Err(Error::UnknownPath(id.to_string())),
+    };
+
+    tx.write(trashed(&entry_name(id)), backend.read(&entry_name(id))?);
+    for blob in index::attachment_blobs(id)? {
+        tx.write(trashed(&blob), backend.read(&blob)?);
+    }
+    tx.remove(id)?;
+
+    let mut records = records()?;
+    records.retain(|r| r.uuid != id);
+    records.push(TrashRecord {
+        uuid: id,
+        path,
+        aliases,
+        password_changed,
+        deleted: now(),
+    });
+    write_records(&records, &mut tx);
+    tx.commit()
+}
+
+/// Move the entry `id` back from the trash to its previous path.
+pub fn restore(id: Uuid) -> Result<()> {
+    let backend = get_backend();
+    let mut tx = Transaction::begin(format!("Restore {} from the trash.", id))?;
+    let mut records = records()?;
+    let record = match records.iter().position(|r| r.uuid == id) {
+        Some(i) => records.remove(i),
+        None => return Err(Error::UnknownPath(id.to_string())),
+    };
+    let taken = |path: &str| tx.records().iter().any(|r| r.paths().any(|p| p == path));
+    if taken(&record.path) {
+        return Err(Error::Other(format!(
+            "Cannot restore {}, another entry is stored at {}",
+            id, record.path
+        )));
+    }
+    let aliases: Vec<String> = record.aliases.into_iter().filter(|a| !taken(a)).collect();
+    let password_changed = record.password_changed;
+
+    let name = trashed(&entry_name(id));
+    let mut entry = Entry::parse(&name, &backend.read(&name)?)?;
+    entry.uuid = id;
+    entry.path = Some(record.path);
+    entry.create_in(&mut tx)?;
+    tx.update_metadata(id, |r| {
+        r.aliases = aliases;
+        r.password_changed = password_changed;
+    });
+    tx.delete(name);
+    for blob in trashed_blobs(id)? {
+        let original = format!("{}/{}", index::attachments_folder(id), blob_file(&blob));
+        tx.write(original, backend.read(&blob)?);
+        tx.delete(blob);
+    }
+    write_records(&records, &mut tx);
+    tx.commit()
+}
+
+/// Permanently delete all entries, which were moved to the trash at least `age` seconds ago.
+/// Returns the removed entries.
+pub fn empty(age: u64) -> Result<Vec<TrashRecord>> {
+    let mut tx = Transaction::begin("Empty the trash.")?;
+    let (removed, kept): (Vec<TrashRecord>, Vec<TrashRecord>) = records()?
+        .into_iter()
+        .partition(|r| r.deleted.saturating_add(age) <= now());
+    for record in removed.iter() {
+        tx.delete(trashed(&entry_name(record.uuid)));
+        for blob in trashed_blobs(record.uuid)? {
+            tx.delete(blob);
+        }
+    }
+    if !removed.is_empty() {
+        write_records(&kept, &mut tx);
+    }
+    tx.commit()?;
+    Ok(removed)
+}
+
+/// Attachments of the trashed entry `id`.
+fn trashed_blobs(id: Uuid) -> Result<Vec<String>> {
+    let folder = trashed(&index::attachments_folder(id));
+    let backend = get_backend();
+    match backend.root().join(&folder).is_dir() {
+        true => backend.list(&folder),
+        false => Ok(Vec::new()),
+    }
+}
+
+/// File name of an attachment blob.
+fn blob_file(blob: &str) -> &str {
+    &blob[blob.rfind('/').unwrap() + 1..]
+}
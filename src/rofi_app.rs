@@ -19,6 +19,8 @@ use crate::commands::{edit, get, insert};
 use crate::config::CFG;
 use crate::def;
 use crate::errors::Result;
+use crate::pass::generator::Policy;
+use crate::pass::template::Template;
 
 use rofi::{Format, Rofi, Width};
 
@@ -84,17 +86,48 @@ fn action_wrapper(action: Action) {
 }
 
 fn action_new() -> Result<()> {
-    let random_pw = match confirm("Generate a random password?", true) {
-        true => Some(20),
+    let types: Vec<String> = Template::all().into_iter().map(|t| t.name).collect();
+    let entry_type = Rofi::new(&types)
+        .prompt("Type")
+        .theme(CFG.theme.theme_name)
+        .run()?;
+    let template = Template::get(&entry_type)?;
+    let random_pw = template.generate && confirm("Generate a random password?", true);
+    let policy = match random_pw {
+        true => choose_policy()?,
         false => None,
     };
-    insert(None, None, None, None, random_pw, true)
+    insert(
+        None,
+        None,
+        None,
+        None,
+        random_pw,
+        policy.as_deref(),
+        true,
+        Some(&template.name),
+        &[],
+    )
+}
+
+/// Let the user choose a password policy, or `None` to use the policy of the folder.
+fn choose_policy() -> Result<Option<String>> {
+    let mut options = vec![def::DISPLAY_POLICY_FOLDER.to_string()];
+    options.extend(Policy::all().into_iter().map(|p| p.name));
+    let choice = Rofi::new(&options)
+        .prompt("Policy")
+        .theme(CFG.theme.theme_name)
+        .run()?;
+    match choice.as_str() {
+        def::DISPLAY_POLICY_FOLDER => Ok(None),
+        _ => Ok(Some(choice)),
+    }
 }
 
 fn action_get() -> Result<()> {
-    get(None, None, true, false, false)
+    get(None, None, true, false, false, false)
 }
 
 fn action_edit() -> Result<()> {
-    edit(None, None, true)
+    edit(None, None, true, false)
 }
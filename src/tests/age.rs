@@ -0,0 +1,218 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+
+use age::secrecy::ExposeSecret;
+use age::x25519::Identity;
+
+use super::TestStore;
+use crate::commands::{edit, get, insert, list, sync};
+use crate::config::CFG;
+use crate::errors::Error;
+use crate::pass::backend::{get_backend, Age, Backend};
+
+/// Create a new identity in `file` and return its recipient.
+fn new_identity(file: &Path) -> String {
+    let identity = Identity::generate();
+    std::fs::write(file, identity.to_string().expose_secret()).unwrap();
+    identity.to_public().to_string()
+}
+
+/// Create a store encrypted with age. The identity is stored outside of the store. Git cannot
+/// call `rpass textconv` from the tests, so the files are only encoded to show their diff.
+fn age_store() -> TestStore {
+    let store = TestStore::with_backend(|root| {
+        let identity_file = root.with_extension("key");
+        let recipient = new_identity(&identity_file);
+        std::fs::write(root.join(".age-recipients"), format!("{}\n", recipient)).unwrap();
+        Age::new(root, identity_file).with_textconv("base64")
+    });
+    store.git("", &["init", "--quiet"]);
+    store
+}
+
+fn insert_entry(path: &str, user: &str, password: &str) {
+    insert(
+        Some(path),
+        Some(user),
+        Some(password),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+}
+
+#[test]
+fn age_roundtrip() {
+    let store = age_store();
+    let backend = get_backend();
+    backend
+        .write("uuids/entry", "secret\nuser: alice\n")
+        .unwrap();
+
+    let raw = std::fs::read(store.root().join("uuids/entry.age")).unwrap();
+    assert!(raw.starts_with(b"age-encryption.org/v1"));
+    assert!(!String::from_utf8_lossy(&raw).contains("secret"));
+    assert_eq!(
+        backend.read("uuids/entry").unwrap(),
+        "secret\nuser: alice\n"
+    );
+    assert_eq!(
+        backend
+            .read_file(&store.root().join("uuids/entry.age"))
+            .unwrap(),
+        "secret\nuser: alice\n"
+    );
+    assert_eq!(
+        backend.list("uuids").unwrap(),
+        vec!["uuids/entry", "uuids/index"]
+    );
+}
+
+#[test]
+fn age_honours_nested_recipients() {
+    let store = age_store();
+    let other_identity = store.root().join("other.key");
+    let other = new_identity(&other_identity);
+    store.write("team/.age-recipients", &format!("# the team\n{}\n", other));
+    let backend = get_backend();
+    backend.write("private", "a").unwrap();
+    backend.write("team/shared", "b").unwrap();
+
+    // the other identity can only decrypt the shared entry
+    let other_backend = Age::new(store.root(), &other_identity);
+    assert_eq!(other_backend.read("team/shared").unwrap(), "b");
+    assert!(matches!(
+        other_backend.read("private"),
+        Err(Error::EntryRead(_))
+    ));
+}
+
+#[test]
+fn age_without_recipients() {
+    let store = age_store();
+    std::fs::remove_file(store.root().join(".age-recipients")).unwrap();
+    assert!(matches!(
+        get_backend().write("entry", "secret"),
+        Err(Error::NoRecipients(_))
+    ));
+}
+
+#[test]
+fn age_invalid_recipient() {
+    let store = age_store();
+    store.write(".age-recipients", "not-a-recipient\n");
+    assert!(matches!(
+        get_backend().write("entry", "secret"),
+        Err(Error::Encryption(_))
+    ));
+}
+
+#[test]
+fn age_missing_identity() {
+    let store = age_store();
+    let backend = Age::new(store.root(), store.root().join("missing.key"));
+    assert!(matches!(
+        backend.read("uuids/index"),
+        Err(Error::AgeIdentity(_))
+    ));
+}
+
+#[test]
+fn age_commit_failures_are_reported() {
+    let store = age_store();
+    let hook = store.root().join(".git/hooks/pre-commit");
+    std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let written = get_backend().write("uuids/entry", "secret\n");
+    assert!(matches!(written, Err(Error::Command(_))));
+}
+
+#[test]
+fn age_commands() {
+    let store = age_store();
+    insert_entry("web/github", "alice", "pw1");
+    insert_entry("mail", "bob", "pw2");
+
+    get(Some("web/github"), None, false, false, false, false).unwrap();
+    list().unwrap();
+    assert_eq!(store.paths(), vec!["mail", "web/github"]);
+
+    std::env::set_var("EDITOR", "sed -i s/pw1/pw3/");
+    edit(Some("web/github"), None, false, false).unwrap();
+    assert_eq!(store.entry("web/github").password, "pw3");
+    assert_eq!(store.entry("mail").password, "pw2");
+
+    let log = Command::new("git")
+        .args(["log", "--format=%s"])
+        .current_dir(store.root())
+        .output()
+        .unwrap();
+    // the edit is followed by the update of the index
+    let log = String::from_utf8(log.stdout).unwrap();
+    let mut log = log.lines();
+    let index = format!("{}/{}", CFG.main.uuid_folder, CFG.main.index_entry);
+    assert!(log.next().unwrap().contains(&index));
+    assert!(log.next().unwrap().starts_with("Edit password for uuids/"));
+}
+
+#[test]
+fn age_sync() {
+    let store = age_store();
+    insert_entry("web/github", "alice", "pw1");
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "initial"]);
+
+    sync::init().unwrap();
+    let github = store.entry("web/github");
+    assert_eq!(
+        std::fs::read(store.root().join(".sync/web/github.age")).unwrap(),
+        std::fs::read(get_backend().file(&format!("uuids/{}", github.uuid))).unwrap()
+    );
+    assert_eq!(store.read(".gitattributes"), "*.age diff=age\n");
+    assert_eq!(store.read(".sync/.gitattributes"), "*.age diff=age\n");
+    assert_eq!(
+        store.read(".sync/.age-recipients"),
+        store.read(".age-recipients")
+    );
+
+    // new entries on both sides
+    insert_entry("mail", "bob", "pw2");
+    get_backend()
+        .write(".sync/phone/pin", "1234\nuser: me\n")
+        .unwrap();
+    store.git(".sync", &["add", "--all"]);
+    store.git(".sync", &["commit", "--quiet", "-m", "slave"]);
+
+    sync::sync(true).unwrap();
+
+    assert_eq!(store.paths(), vec!["mail", "phone/pin", "web/github"]);
+    assert_eq!(store.entry("phone/pin").password, "1234");
+    assert_eq!(
+        get_backend().read(".sync/mail").unwrap(),
+        get_backend()
+            .read(&format!("uuids/{}", store.entry("mail").uuid))
+            .unwrap()
+    );
+}
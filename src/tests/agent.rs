@@ -0,0 +1,143 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::TestStore;
+use crate::commands::{insert, lock};
+use crate::pass::agent::{self, Request, Response};
+use crate::pass::backend::get_backend;
+
+const INDEX: &str = "uuids/index";
+
+/// Start an agent in the folder of the store, and use it from the current thread.
+fn start_agent(store: &TestStore, timeout: Duration) -> (PathBuf, JoinHandle<()>) {
+    let socket = store.root().join("agent/agent.sock");
+    let listener = agent::bind(&socket).unwrap();
+    let server_socket = socket.clone();
+    let handle = thread::spawn(move || agent::serve(listener, &server_socket, timeout).unwrap());
+    agent::set_socket(Some(socket.clone()));
+    (socket, handle)
+}
+
+fn cached(name: &str) -> Option<String> {
+    let backend = get_backend();
+    match agent::request(&Request::Get {
+        key: backend.file(name).to_string_lossy().into_owned(),
+        modified: backend.modified(name).unwrap(),
+    }) {
+        Some(Response::Content(content)) => content,
+        _ => None,
+    }
+}
+
+#[test]
+fn agent_caches_index() {
+    let store = TestStore::new();
+    let (socket, handle) = start_agent(&store, Duration::from_secs(60));
+
+    assert_eq!(cached(INDEX), None);
+    assert_eq!(agent::read(INDEX).unwrap(), store.read("uuids/index.txt"));
+    assert_eq!(cached(INDEX), Some(store.read("uuids/index.txt")));
+
+    // writing the index passes the new content to the agent
+    insert(
+        Some("web/github"),
+        Some("alice"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    assert!(store.read("uuids/index.txt").contains("web/github"));
+    assert_eq!(cached(INDEX), Some(store.read("uuids/index.txt")));
+    assert_eq!(store.paths(), vec!["web/github"]);
+
+    // the content of the agent is used if the file was not modified
+    let backend = get_backend();
+    agent::request(&Request::Put {
+        key: backend.file(INDEX).to_string_lossy().into_owned(),
+        modified: backend.modified(INDEX).unwrap(),
+        content: "cached".to_string(),
+    });
+    assert_eq!(agent::read(INDEX).unwrap(), "cached");
+
+    lock().unwrap();
+    handle.join().unwrap();
+    assert!(!socket.exists());
+    assert_eq!(agent::read(INDEX).unwrap(), store.read("uuids/index.txt"));
+}
+
+#[test]
+fn agent_ignores_modified_files() {
+    let store = TestStore::new();
+    let (_socket, _handle) = start_agent(&store, Duration::from_secs(60));
+
+    let backend = get_backend();
+    agent::request(&Request::Put {
+        key: backend.file(INDEX).to_string_lossy().into_owned(),
+        modified: SystemTime::UNIX_EPOCH,
+        content: "outdated".to_string(),
+    });
+    assert_eq!(cached(INDEX), None);
+    assert_eq!(agent::read(INDEX).unwrap(), store.read("uuids/index.txt"));
+    lock().unwrap();
+}
+
+#[test]
+fn agent_idle_timeout() {
+    let store = TestStore::new();
+    let (socket, handle) = start_agent(&store, Duration::from_millis(100));
+    agent::read(INDEX).unwrap();
+    handle.join().unwrap();
+    assert!(!socket.exists());
+    assert_eq!(cached(INDEX), None);
+}
+
+#[test]
+fn agent_socket_is_private() {
+    let store = TestStore::new();
+    let (socket, _handle) = start_agent(&store, Duration::from_secs(60));
+    let mode = |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode(&socket) & 0o777, 0o600);
+    assert_eq!(mode(socket.parent().unwrap()) & 0o777, 0o700);
+    lock().unwrap();
+}
+
+#[test]
+fn agent_serves_clients_concurrently() {
+    let store = TestStore::new();
+    let (socket, handle) = start_agent(&store, Duration::from_secs(60));
+
+    // a client which never sends its request must not block the others
+    let idle = UnixStream::connect(&socket).unwrap();
+    let start = Instant::now();
+    agent::read(INDEX).unwrap();
+    assert_eq!(cached(INDEX), Some(store.read("uuids/index.txt")));
+    assert!(start.elapsed() < Duration::from_millis(500));
+    drop(idle);
+
+    lock().unwrap();
+    handle.join().unwrap();
+}
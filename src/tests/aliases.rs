@@ -0,0 +1,144 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::fix_index::check_index;
+use crate::commands::utils::choose_entry;
+use crate::commands::{alias, delete, insert, mv, passwd, trash};
+use crate::errors::Error;
+use crate::pass::index::{self, IndexRecord};
+
+fn insert_entry(path: &str) {
+    insert(
+        Some(path),
+        Some("alice"),
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+}
+
+fn is_conflict<T>(result: crate::errors::Result<T>) -> bool {
+    matches!(result, Err(Error::PathConflict(..)))
+}
+
+#[test]
+fn aliases_point_to_the_same_entry() {
+    let store = TestStore::new();
+    insert_entry("sso/google");
+    alias::add(Some("sso/google"), None, "work/mail").unwrap();
+    alias::add(Some("sso/google"), None, "/calendar/").unwrap();
+    assert_eq!(store.paths(), vec!["calendar", "sso/google", "work/mail"]);
+
+    let entry = store.entry("sso/google");
+    assert_eq!(store.entry("work/mail").uuid, entry.uuid);
+    assert_eq!(store.entry("calendar").path.as_deref(), Some("sso/google"));
+
+    // edits through an alias are applied to the entry itself
+    passwd(Some("work/mail"), None, Some("new"), false, None, false).unwrap();
+    assert_eq!(store.entry("calendar").password, "new");
+    assert!(check_index().unwrap().iter().all(|(_, p)| p.is_none()));
+    assert_eq!(check_index().unwrap().len(), 1);
+
+    // the entry keeps its aliases when it is moved
+    mv(Some("sso/google"), None, Some("google"), false).unwrap();
+    assert_eq!(store.paths(), vec!["calendar", "google", "work/mail"]);
+
+    alias::remove("calendar").unwrap();
+    assert_eq!(store.paths(), vec!["google", "work/mail"]);
+    assert!(alias::remove("calendar").is_err());
+    assert!(alias::remove("google").is_err());
+}
+
+#[test]
+fn conflicting_paths_are_rejected() {
+    let store = TestStore::new();
+    insert_entry("a");
+    insert_entry("b");
+    alias::add(Some("b"), None, "c").unwrap();
+
+    assert!(is_conflict(alias::add(Some("a"), None, "b")));
+    assert!(is_conflict(alias::add(Some("a"), None, "c")));
+    assert!(is_conflict(insert(
+        Some("c"),
+        None,
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )));
+    assert!(is_conflict(mv(Some("a"), None, Some("c"), false)));
+    assert_eq!(store.paths(), vec!["a", "b", "c"]);
+    assert!(check_index().unwrap().iter().all(|(_, p)| p.is_none()));
+
+    // an alias of the entry itself is no conflict
+    mv(Some("b"), None, Some("c"), false).unwrap();
+    assert_eq!(store.paths(), vec!["a", "c"]);
+}
+
+#[test]
+fn existing_conflicts_are_reported() {
+    let store = TestStore::new();
+    insert_entry("a");
+    insert_entry("b");
+    let a = store.entry("a").uuid;
+    let b = store.entry("b").uuid;
+    let mut records = index::get_records().unwrap();
+    records.iter_mut().find(|r| r.uuid == b).unwrap().aliases = vec!["a".to_string()];
+    index::write_records(&records).unwrap();
+
+    match choose_entry(Some("a"), None, false) {
+        Err(Error::PathConflict(path, _, _)) => assert_eq!(path, "a"),
+        _ => panic!("the conflict must be reported"),
+    }
+    let list = index::get_index().unwrap();
+    assert!(index::to_hashmap_reverse(&list).is_err());
+    assert_eq!(index::get_conflicts().unwrap().len(), 1);
+
+    // the conflict can be resolved
+    alias::remove("a").unwrap();
+    assert!(index::get_conflicts().unwrap().is_empty());
+    assert_eq!(store.entry("a").uuid, a);
+    assert_eq!(
+        IndexRecord::new(a, "x").paths().collect::<Vec<_>>(),
+        vec!["x"]
+    );
+}
+
+#[test]
+fn trashed_entries_keep_their_aliases() {
+    let store = TestStore::new();
+    insert_entry("sso");
+    alias::add(Some("sso"), None, "mail").unwrap();
+    alias::add(Some("sso"), None, "chat").unwrap();
+    delete(Some("mail"), None, true, false, false).unwrap();
+    assert!(store.paths().is_empty());
+
+    // aliases which are used by now are dropped
+    insert_entry("chat");
+    trash::restore(Some("sso"), None).unwrap();
+    assert_eq!(store.paths(), vec!["chat", "mail", "sso"]);
+    assert_eq!(store.entry("mail").path.as_deref(), Some("sso"));
+    assert_ne!(store.entry("chat").uuid, store.entry("sso").uuid);
+}
@@ -0,0 +1,110 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::os::unix::fs::PermissionsExt;
+
+use super::TestStore;
+use crate::commands::fix_index::check_index;
+use crate::commands::{attach, delete, extract, insert};
+use crate::errors::Error;
+use crate::pass::index;
+
+/// Content of the attached file, which is not valid utf-8.
+const CONTENT: &[u8] = &[0, 159, 146, 150, 255, b'\n', b'x'];
+
+fn setup() -> TestStore {
+    let store = TestStore::new();
+    insert(
+        Some("bank"),
+        Some("alice"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    store.write("files/key.bin", "");
+    std::fs::write(store.root().join("files/key.bin"), CONTENT).unwrap();
+    store
+}
+
+#[test]
+fn attach_and_extract() {
+    let store = setup();
+    let file = store.root().join("files/key.bin");
+    attach(Some("bank"), None, file.to_str().unwrap(), None).unwrap();
+
+    let entry = store.entry("bank");
+    assert_eq!(entry.attachments(), vec!["key.bin"]);
+    assert!(store
+        .entry_file(entry.uuid)
+        .contains("attachment: key.bin\n"));
+    assert_eq!(entry.extract("key.bin").unwrap(), CONTENT);
+    assert!(entry.extract("other.bin").is_err());
+
+    // the blob is stored in the uuid folder, and does not confuse the index checks
+    let blob = format!("{}/key.bin.txt", index::attachments_folder(entry.uuid));
+    assert!(store.root().join(blob).is_file());
+    assert!(check_index().unwrap().iter().all(|(_, p)| p.is_none()));
+
+    // attaching the file again replaces it
+    std::fs::write(&file, b"new").unwrap();
+    attach(Some("bank"), None, file.to_str().unwrap(), None).unwrap();
+    let entry = store.entry("bank");
+    assert_eq!(entry.attachments(), vec!["key.bin"]);
+    assert_eq!(entry.extract("key.bin").unwrap(), b"new");
+
+    // extracting never overwrites files
+    let output = store.root().join("files/out.bin");
+    let output = output.to_str().unwrap();
+    extract(Some("bank"), None, "key.bin", Some(output)).unwrap();
+    assert_eq!(std::fs::read(output).unwrap(), b"new");
+    let mode = std::fs::metadata(output).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert!(matches!(
+        extract(Some("bank"), None, "key.bin", Some(output)),
+        Err(Error::Attachment(_))
+    ));
+}
+
+#[test]
+fn attachments_are_removed_with_the_entry() {
+    let store = setup();
+    let file = store.root().join("files/key.bin");
+    attach(Some("bank"), None, file.to_str().unwrap(), Some("a.bin")).unwrap();
+    attach(Some("bank"), None, file.to_str().unwrap(), Some("b.bin")).unwrap();
+    let entry = store.entry("bank");
+    assert_eq!(index::attachment_blobs(entry.uuid).unwrap().len(), 2);
+
+    delete(Some("bank"), None, true, true, false).unwrap();
+    assert!(index::attachment_blobs(entry.uuid).unwrap().is_empty());
+    assert!(!store
+        .root()
+        .join(index::attachments_folder(entry.uuid))
+        .exists());
+}
+
+#[test]
+fn invalid_attachment_names() {
+    let store = setup();
+    let mut entry = store.entry("bank");
+    assert!(entry.attach("", CONTENT).is_err());
+    assert!(entry.attach("../escape", CONTENT).is_err());
+    assert!(entry.attachments().is_empty());
+}
@@ -0,0 +1,122 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::audit::{audit_report, Incomplete};
+use crate::commands::insert;
+use crate::pass::backend::get_backend;
+
+fn insert_login(path: &str, password: &str, url: Option<&str>, fields: &[&str]) {
+    insert(
+        Some(path),
+        Some("alice"),
+        Some(password),
+        url,
+        false,
+        None,
+        false,
+        None,
+        fields,
+    )
+    .unwrap();
+}
+
+#[test]
+fn clean_store() {
+    let _store = TestStore::new();
+    for i in 0..20 {
+        let password = format!("{}-Xq7#mZ2!vK9@pW4$", i);
+        insert_login(&format!("web/site{}", i), &password, Some("url"), &[]);
+    }
+    let report = audit_report(365, None).unwrap();
+    assert_eq!(report.entries, 20);
+    assert_eq!(report.problems(), 0, "{:?}", report);
+}
+
+#[test]
+fn problems_are_reported() {
+    let store = TestStore::new();
+    let strong = "Xq7#mZ2!vK9@pW4$";
+    insert_login("web/github", strong, Some("url"), &[]);
+    insert_login("web/gitlab", strong, Some("url"), &[]);
+    insert_login("mail", "hunter2", Some("url"), &[]);
+    insert_login("shop", "Shop-8#kD2!xQ5@", None, &[]);
+    insert_login(
+        "bank",
+        "Bank-3$pL9!wZ6#",
+        Some("url"),
+        &["expires=2000-01-01"],
+    );
+    insert(
+        Some("cards/visa"),
+        None,
+        Some("1234"),
+        None,
+        false,
+        None,
+        false,
+        Some("card"),
+        &[
+            "cardholder=Alice",
+            "card number=4111 1111 1111 1111",
+            "expiry=03/31",
+            "cvv=123",
+        ],
+    )
+    .unwrap();
+
+    // path of the entry differs from the index
+    let mut moved = store.entry("bank");
+    moved
+        .change_path_keep_index("money/bank".to_string())
+        .unwrap();
+
+    let report = audit_report(365, None).unwrap();
+    assert_eq!(report.entries, 6);
+    assert_eq!(report.reused, vec![vec!["web/github", "web/gitlab"]]);
+    let weak: Vec<&str> = report.weak.iter().map(|w| w.path.as_str()).collect();
+    assert_eq!(weak, vec!["mail"]);
+    assert_eq!(
+        report.incomplete,
+        vec![Incomplete {
+            path: "shop".to_string(),
+            missing: vec!["url"],
+        }]
+    );
+    assert_eq!(report.not_rotated.len(), 1);
+    assert_eq!(report.not_rotated[0].path, "bank");
+    assert_eq!(report.index.len(), 1);
+    assert_eq!(report.index[0].path, "bank");
+    assert!(report.index[0].problem.contains("money/bank"));
+    assert_eq!(report.problems(), 5);
+
+    // passwords never appear in the report
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(!json.contains(strong) && !json.contains("hunter2"));
+}
+
+#[test]
+fn unreadable_entries_are_reported() {
+    let store = TestStore::new();
+    insert_login("web/github", "Xq7#mZ2!vK9@pW4$", Some("url"), &[]);
+    let id = store.entry("web/github").uuid;
+    get_backend()
+        .delete(&format!("{}/{}", crate::config::CFG.main.uuid_folder, id))
+        .unwrap();
+    let report = audit_report(365, None).unwrap();
+    assert_eq!(report.index.len(), 1);
+    assert!(report.index[0].problem.starts_with("cannot be read"));
+}
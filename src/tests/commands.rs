@@ -0,0 +1,396 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::bulk_rename::{apply_renames, find_renames};
+use crate::commands::find::find_matches;
+use crate::commands::fix_index::{check_index, Problem};
+use crate::commands::init::init_with;
+use crate::commands::{delete, edit, get, insert, mv, passwd};
+use crate::errors::Error;
+use crate::pass::backend::Plaintext;
+use crate::pass::entry::Entry;
+use crate::pass::index;
+
+fn insert_github(store: &TestStore) -> Entry {
+    insert(
+        Some("web/github"),
+        Some("alice"),
+        Some("hunter2"),
+        Some("https://github.com"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    store.entry("web/github")
+}
+
+#[test]
+fn insert_writes_entry_and_index() {
+    let store = TestStore::new();
+    let entry = insert_github(&store);
+
+    assert_eq!(entry.username.as_deref(), Some("alice"));
+    assert_eq!(entry.password, "hunter2");
+    assert_eq!(entry.url.as_deref(), Some("https://github.com"));
+    assert_eq!(entry.path.as_deref(), Some("web/github"));
+    assert_eq!(
+        store.entry_file(entry.uuid),
+        format!(
+            "hunter2\nuser: alice\nurl: https://github.com\npath: web/github\nuuid: {}\n",
+            entry.uuid
+        )
+    );
+    assert_eq!(store.paths(), vec!["web/github"]);
+}
+
+#[test]
+fn insert_generates_password() {
+    let store = TestStore::new();
+    insert(
+        Some("mail"),
+        Some("bob"),
+        None,
+        Some("mail.com"),
+        true,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(store.entry("mail").password.len(), 20);
+}
+
+#[test]
+fn insert_multiple_entries() {
+    let store = TestStore::new();
+    for path in ["a", "b/c", "b/d", "e/f/g"].iter() {
+        insert(
+            Some(path),
+            Some("user"),
+            Some("pw"),
+            Some("url"),
+            false,
+            None,
+            false,
+            None,
+            &[],
+        )
+        .unwrap();
+    }
+    assert_eq!(store.paths(), vec!["a", "b/c", "b/d", "e/f/g"]);
+}
+
+#[test]
+fn get_by_path_and_uuid() {
+    let store = TestStore::new();
+    let entry = insert_github(&store);
+    let id = entry.uuid.to_string();
+
+    get(Some("web/github"), None, false, false, false, false).unwrap();
+    get(None, Some(&id), false, true, false, false).unwrap();
+    get(None, Some(&id), false, false, true, false).unwrap();
+    assert!(matches!(
+        get(Some("web/gitlab"), None, false, false, false, false),
+        Err(Error::UnknownPath(_))
+    ));
+}
+
+#[test]
+fn get_without_username() {
+    let store = TestStore::new();
+    let mut entry = insert_github(&store);
+    entry.username = None;
+    entry.write().unwrap();
+
+    assert!(get(Some("web/github"), None, false, false, true, false).is_err());
+}
+
+#[test]
+fn mv_updates_entry_and_index() {
+    let store = TestStore::new();
+    let entry = insert_github(&store);
+
+    mv(Some("web/github"), None, Some("code/github"), false).unwrap();
+
+    assert_eq!(store.paths(), vec!["code/github"]);
+    let moved = store.entry("code/github");
+    assert_eq!(moved.uuid, entry.uuid);
+    assert_eq!(moved.path.as_deref(), Some("code/github"));
+    assert_eq!(moved.password, "hunter2");
+}
+
+#[test]
+fn passwd_only_changes_password() {
+    let store = TestStore::new();
+    let mut entry = insert_github(&store);
+    entry
+        .change_raw_line(None, Some("pin: 1234".to_string()))
+        .unwrap();
+
+    passwd(
+        Some("web/github"),
+        None,
+        Some("correct horse"),
+        false,
+        None,
+        false,
+    )
+    .unwrap();
+
+    let changed = store.entry("web/github");
+    assert_eq!(changed.password, "correct horse");
+    assert_eq!(changed.username.as_deref(), Some("alice"));
+    assert_eq!(changed.raw(), "pin: 1234\n");
+    assert_eq!(store.paths(), vec!["web/github"]);
+}
+
+#[test]
+fn passwd_generates_password() {
+    let store = TestStore::new();
+    insert_github(&store);
+    passwd(Some("web/github"), None, None, false, Some("pin"), false).unwrap();
+    let password = store.entry("web/github").password;
+    assert_eq!(password.len(), 6);
+    assert!(password.chars().all(|c| c.is_ascii_digit()));
+}
+
+#[test]
+fn edit_moves_entry_if_path_changed() {
+    let store = TestStore::with_backend(|root| {
+        Plaintext::new(root).with_editor(|content| {
+            content
+                .replace("hunter2", "hunter3")
+                .replace("path: web/github", "path: code/github")
+        })
+    });
+    let entry = insert_github(&store);
+
+    edit(Some("web/github"), None, false, false).unwrap();
+
+    assert_eq!(store.paths(), vec!["code/github"]);
+    let edited = store.entry("code/github");
+    assert_eq!(edited.uuid, entry.uuid);
+    assert_eq!(edited.password, "hunter3");
+}
+
+#[test]
+fn edit_updates_the_index() {
+    let store = TestStore::with_backend(|root| {
+        Plaintext::new(root).with_editor(|content| content.replace("alice", "carol"))
+    });
+    let entry = insert_github(&store);
+
+    edit(Some("web/github"), None, false, false).unwrap();
+
+    assert!(find_matches(&["user:alice"]).unwrap().is_empty());
+    assert_eq!(
+        find_matches(&["user:carol"]).unwrap(),
+        vec![("web/github".to_string(), entry.uuid)]
+    );
+}
+
+#[test]
+fn rm_removes_entry_and_index() {
+    let store = TestStore::new();
+    let entry = insert_github(&store);
+    insert(
+        Some("mail"),
+        Some("bob"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+
+    delete(Some("web/github"), None, true, false, false).unwrap();
+
+    assert_eq!(store.paths(), vec!["mail"]);
+    assert!(Entry::get(entry.uuid).is_err());
+    assert!(!store
+        .root()
+        .join("uuids")
+        .join(format!("{}.txt", entry.uuid))
+        .exists());
+}
+
+#[test]
+fn bulk_rename_finds_and_applies_renames() {
+    let store = TestStore::new();
+    let github = insert_github(&store);
+    insert(
+        Some("web/gitlab"),
+        Some("bob"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    insert(
+        Some("mail"),
+        Some("carol"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    let gitlab = store.entry("web/gitlab");
+    let mail = store.entry("mail");
+
+    let original = format!(
+        "{} web/github\n{} web/gitlab\n{} mail\n",
+        github.uuid, gitlab.uuid, mail.uuid
+    );
+    // rename github, keep gitlab, and delete the line of mail (which must be ignored)
+    let edited = format!(
+        "{} code/github\n{} web/gitlab\ngarbage\n",
+        github.uuid, gitlab.uuid
+    );
+
+    let renames = find_renames(&original, &edited);
+    assert_eq!(
+        renames,
+        vec![(
+            github.uuid,
+            "web/github".to_string(),
+            "code/github".to_string()
+        )]
+    );
+
+    apply_renames(renames).unwrap();
+    assert_eq!(store.paths(), vec!["code/github", "mail", "web/gitlab"]);
+    assert_eq!(
+        store.entry("code/github").path.as_deref(),
+        Some("code/github")
+    );
+}
+
+#[test]
+fn fix_index_detects_problems() {
+    let store = TestStore::new();
+    let github = insert_github(&store);
+    insert(
+        Some("mail"),
+        Some("bob"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    let mail = store.entry("mail");
+
+    // path of the entry differs from the index
+    let mut moved = github.clone();
+    moved
+        .change_path_keep_index("web/moved".to_string())
+        .unwrap();
+
+    // entry which knows its path, but is missing in the index
+    let lost = Entry::new(None, "pw".to_string(), None, "lost".to_string());
+    lost.write().unwrap();
+
+    // entry which neither knows its path, nor is in the index
+    let mut orphan = Entry::new(None, "pw".to_string(), None, String::new());
+    orphan.path = None;
+    orphan.write().unwrap();
+
+    // an unreadable entry is skipped
+    store.write(format!("uuids/{}.txt", uuid::Uuid::new_v4()), "");
+
+    let mut problems: Vec<_> = check_index()
+        .unwrap()
+        .into_iter()
+        .map(|(e, p)| (e.uuid, p))
+        .collect();
+    problems.sort_by_key(|(id, _)| *id);
+    let mut expected = vec![
+        (
+            github.uuid,
+            Some(Problem::PathMismatch {
+                index_path: "web/github".to_string(),
+            }),
+        ),
+        (mail.uuid, None),
+        (lost.uuid, Some(Problem::NotIndexed)),
+        (orphan.uuid, Some(Problem::Orphaned)),
+    ];
+    expected.sort_by_key(|(id, _)| *id);
+    assert_eq!(problems, expected);
+}
+
+#[test]
+fn fix_index_consistent_store() {
+    let store = TestStore::new();
+    insert_github(&store);
+    assert!(check_index().unwrap().iter().all(|(_, p)| p.is_none()));
+}
+
+#[test]
+fn init_indexes_existing_entries() {
+    let store = TestStore::new();
+    store.write("web/github.txt", "hunter2\nuser: alice\n");
+    store.write(
+        "mail.txt",
+        "secret\nuser: bob\nurl: mail.com\nrecovery: 42\n",
+    );
+    store.write("uuids/ignored/entry.txt", "ignored\n");
+    store.write(".sync/ignored.txt", "ignored\n");
+
+    // nothing is indexed without confirmation
+    let declined = init_with(true, |count| {
+        assert_eq!(count, 2);
+        false
+    });
+    assert!(matches!(declined, Err(Error::Interrupted)));
+    assert!(store.paths().is_empty());
+
+    init_with(true, |_| true).unwrap();
+
+    assert_eq!(store.paths(), vec!["mail", "web/github"]);
+    let mail = store.entry("mail");
+    assert_eq!(mail.password, "secret");
+    assert_eq!(mail.username.as_deref(), Some("bob"));
+    assert_eq!(mail.url.as_deref(), Some("mail.com"));
+    assert_eq!(mail.path.as_deref(), Some("mail"));
+    assert_eq!(mail.raw(), "recovery: 42\n");
+    assert!(store
+        .entry_file(mail.uuid)
+        .contains(&format!("uuid: {}", mail.uuid)));
+
+    // running init again must not index anything twice
+    init_with(true, |_| true).unwrap();
+    assert_eq!(index::get_index().unwrap().len(), 2);
+}
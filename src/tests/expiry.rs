@@ -0,0 +1,228 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use chrono::Duration;
+
+use super::TestStore;
+use crate::commands::expiring::due_entries;
+use crate::commands::{edit, insert, passwd};
+use crate::pass::backend::Plaintext;
+use crate::pass::expiry::{self, describe, due_date, format_date, parse_date, today};
+use crate::pass::index;
+
+fn insert_with(path: &str, fields: &[&str]) {
+    insert(
+        Some(path),
+        Some("alice"),
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        fields,
+    )
+    .unwrap();
+}
+
+fn in_days(days: i64) -> String {
+    format_date(today() + Duration::days(days))
+}
+
+#[test]
+fn due_dates() {
+    let date = parse_date("2024-02-28").unwrap();
+    assert_eq!(
+        due_date(Some("2024-02-28"), Some("30d"), Some(0)),
+        Some(date)
+    );
+    // 2024-01-01 00:00 UTC
+    let modified = 1_704_067_200 + 12 * 60 * 60;
+    assert_eq!(
+        due_date(None, Some("8w"), Some(modified)),
+        parse_date("2024-02-26").ok()
+    );
+    assert_eq!(
+        due_date(Some("soon"), Some("1d"), Some(modified)),
+        parse_date("2024-01-02").ok()
+    );
+    assert_eq!(due_date(None, Some("often"), Some(modified)), None);
+    assert_eq!(due_date(None, None, Some(modified)), None);
+
+    assert_eq!(describe(today()), "expires today");
+    assert_eq!(
+        describe(today() + Duration::days(3)),
+        format!("expires in 3 days ({})", in_days(3))
+    );
+    assert_eq!(
+        describe(today() - Duration::days(5)),
+        format!("expired 5 days ago ({})", in_days(-5))
+    );
+    assert!(expiry::is_due(today() + Duration::days(14), 14));
+    assert!(!expiry::is_due(today() + Duration::days(15), 14));
+}
+
+#[test]
+fn huge_durations_do_not_overflow() {
+    assert!(expiry::parse_duration("99999999999999999w").is_err());
+    let rotate_every = format!("{}d", u64::MAX / (24 * 60 * 60));
+    assert!(expiry::rotated(today(), &rotate_every).is_err());
+    assert_eq!(due_date(None, Some(&rotate_every), Some(0)), None);
+    assert_eq!(due_date(None, Some("999999999d"), Some(0)), None);
+    assert!(expiry::is_due(today(), i64::MAX));
+    assert!(!expiry::is_due(today(), i64::MIN));
+}
+
+#[test]
+fn list_expiring_entries() {
+    let store = TestStore::new();
+    let expired = format!("expires={}", in_days(-1));
+    let later = format!("expires={}", in_days(30));
+    insert_with("expired", &[&expired]);
+    insert_with("later", &[&later]);
+    insert_with("weekly", &["rotate-every=7d"]);
+    insert_with("quarterly", &["rotate-every=90d"]);
+    insert_with("forever", &[]);
+
+    let paths = |days| -> Vec<String> {
+        due_entries(days)
+            .unwrap()
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect()
+    };
+    assert_eq!(paths(14), vec!["expired", "weekly"]);
+    assert_eq!(paths(60), vec!["expired", "weekly", "later"]);
+    assert_eq!(paths(0), vec!["expired"]);
+
+    let entry = store.entry("expired");
+    assert_eq!(
+        entry.expiry_warning().unwrap(),
+        format!("Password expired yesterday ({})", in_days(-1))
+    );
+    assert!(format!("{:?}", entry).contains("    due:      Password expired yesterday"));
+    assert!(store.entry("later").expiry_warning().is_none());
+    assert!(!format!("{:?}", store.entry("later")).contains("due:"));
+}
+
+#[test]
+fn passwd_resets_the_timer() {
+    let store = TestStore::new();
+    let expired = format!("expires={}", in_days(-1));
+    insert_with("rotated", &[&expired, "rotate-every=30d"]);
+    insert_with("weekly", &["rotate-every=1w"]);
+    insert_with("fixed", &[&expired]);
+
+    passwd(Some("rotated"), None, Some("new"), false, None, false).unwrap();
+    let entry = store.entry("rotated");
+    assert_eq!(entry.password, "new");
+    assert_eq!(entry.field_value("expires"), Some(in_days(30).as_str()));
+    assert!(entry.expiry_warning().is_none());
+
+    passwd(Some("weekly"), None, Some("new"), false, None, false).unwrap();
+    assert_eq!(
+        store.entry("weekly").field_value("expires"),
+        Some(in_days(7).as_str())
+    );
+
+    // without rotate-every, the expiry date is fixed
+    passwd(Some("fixed"), None, Some("new"), false, None, false).unwrap();
+    assert!(store.entry("fixed").expiry_warning().is_some());
+}
+
+#[test]
+fn only_password_changes_reset_the_rotation() {
+    // the first edit changes the username, the second one the password
+    let store = TestStore::with_backend(|root| {
+        Plaintext::new(root).with_editor(|content| match content.contains("bob") {
+            true => content.replace("bob", "carol"),
+            false => content.replacen("pw\n", "new\n", 1),
+        })
+    });
+    insert_with("weekly", &["rotate-every=1w"]);
+
+    // pretend the password was set ten days ago
+    let ten_days_ago = index::now() - 10 * 24 * 60 * 60;
+    let records: Vec<_> = index::get_records()
+        .unwrap()
+        .into_iter()
+        .map(|r| index::IndexRecord {
+            password_changed: Some(ten_days_ago),
+            ..r
+        })
+        .collect();
+    index::write_records(&records).unwrap();
+    assert_eq!(due_entries(0).unwrap().len(), 1);
+
+    let mut entry = store.entry("weekly");
+    entry.change_username(Some("bob".to_string())).unwrap();
+    edit(Some("weekly"), None, false, false).unwrap();
+    assert!(store.entry("weekly").expiry_warning().is_some());
+    assert_eq!(due_entries(0).unwrap().len(), 1);
+
+    edit(Some("weekly"), None, false, false).unwrap();
+    assert_eq!(store.entry("weekly").password, "new");
+    assert_eq!(
+        store.entry("weekly").due_date(),
+        parse_date(&in_days(7)).ok()
+    );
+    assert!(due_entries(0).unwrap().is_empty());
+}
+
+#[test]
+fn editing_the_password_rotates_it() {
+    let store = TestStore::with_backend(|root| {
+        Plaintext::new(root).with_editor(|content| match content.contains("pw\n") {
+            true => content.replacen("pw\n", "new\n", 1),
+            false => content
+                .replacen("new\n", "newer\n", 1)
+                .replace(&in_days(30), "2099-01-01"),
+        })
+    });
+    insert_with("bank", &["rotate-every=30d", "expires=2020-02-02"]);
+
+    edit(Some("bank"), None, false, false).unwrap();
+    let entry = store.entry("bank");
+    assert_eq!(entry.password, "new");
+    assert_eq!(entry.field_value("expires"), Some(in_days(30).as_str()));
+    assert!(due_entries(0).unwrap().is_empty());
+
+    // a due date set in the editor is kept
+    edit(Some("bank"), None, false, false).unwrap();
+    let entry = store.entry("bank");
+    assert_eq!(entry.password, "newer");
+    assert_eq!(entry.field_value("expires"), Some("2099-01-01"));
+}
+
+#[test]
+fn invalid_expiry_fields_are_rejected() {
+    let store = TestStore::new();
+    for field in ["expires=tomorrow", "Rotate-Every=90"] {
+        assert!(insert(
+            Some("bank"),
+            Some("alice"),
+            Some("pw"),
+            None,
+            false,
+            None,
+            false,
+            None,
+            &[field],
+        )
+        .is_err());
+    }
+    assert!(store.paths().is_empty());
+}
@@ -0,0 +1,315 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::TestStore;
+use crate::commands::find::{find_matches, grep_matches};
+use crate::commands::{edit, passwd};
+use crate::pass::backend::Plaintext;
+use crate::pass::entry::Entry;
+use crate::pass::field::{Field, FieldKind};
+
+/// Number of random entries checked by every property.
+const CASES: usize = 2000;
+
+/// Building blocks of the random entries, covering known keys in different spellings, notes,
+/// blank lines, and lines which only look like fields.
+const LINES: &[&str] = &[
+    "",
+    " ",
+    "user: alice",
+    "User: bob",
+    "user:",
+    "url: https://example.com",
+    "URL: example.com",
+    "path: web/example",
+    "uuid: 5b7aa9de-7f35-4bb9-a8c1-3b4ab8a2a1c2",
+    "UUID: 5B7AA9DE7F354BB9A8C13B4AB8A2A1C2",
+    "uuid: not-a-uuid",
+    "otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP",
+    "2fa: otpauth://totp/Example?secret=GEZDGNBV",
+    "pin: 1234",
+    "PIN:1234",
+    "email:\talice@example.com",
+    "https://example.com/login",
+    "just some text",
+    ":leading colon",
+    "key: value: with colon",
+    "trailing spaces:   ",
+    "carriage return: yes\r",
+    "ünïcödé: ✓",
+    "recovery: |",
+    "notes: |",
+    "  indented line",
+    "  ",
+    "    deeper indented: line",
+    "|",
+];
+
+fn random_content(rng: &mut StdRng) -> String {
+    let mut lines = vec![rng.gen_range(0..1000).to_string()];
+    for _ in 0..rng.gen_range(0..12) {
+        lines.push(LINES.choose(rng).unwrap().to_string());
+    }
+    let mut content = lines.join("\n");
+    if rng.gen_bool(0.8) {
+        content.push('\n');
+    }
+    content
+}
+
+#[test]
+fn unchanged_entries_are_byte_identical() {
+    let _store = TestStore::new();
+    let mut rng = StdRng::seed_from_u64(12);
+    for _ in 0..CASES {
+        let content = random_content(&mut rng);
+        let entry = Entry::parse("test", &content).unwrap();
+        assert_eq!(entry.raw_content(), content);
+    }
+}
+
+#[test]
+fn changes_only_touch_their_line() {
+    let _store = TestStore::new();
+    let mut rng = StdRng::seed_from_u64(6238);
+    for _ in 0..CASES {
+        let content = random_content(&mut rng);
+        let entry = Entry::parse("test", &content).unwrap();
+
+        let mut changed = entry.clone();
+        changed.username = Some("carol".to_string());
+        changed.password = "new password".to_string();
+        let new_content = changed.raw_content();
+        let parsed = Entry::parse("test", &new_content).unwrap();
+        assert_eq!(parsed.username.as_deref(), Some("carol"));
+        assert_eq!(parsed.password, "new password");
+        assert_eq!(parsed.url, entry.url);
+        assert_eq!(parsed.path, entry.path);
+        assert_eq!(parsed.otp, entry.otp);
+        assert_eq!(parsed.fields, entry.fields);
+        assert_eq!(parsed.raw_content(), new_content);
+
+        // all lines except for the password and the username stay where they are
+        if entry.username.is_some() {
+            let old: Vec<&str> = content.split('\n').collect();
+            let new: Vec<&str> = new_content.split('\n').collect();
+            assert_eq!(old.len(), new.len());
+            let changed: Vec<usize> = (0..old.len()).filter(|i| old[*i] != new[*i]).collect();
+            assert_eq!(changed[0], 0);
+            assert!(changed.len() <= 2);
+            assert!(changed[1..].iter().all(|i| new[*i].ends_with("carol")));
+        }
+    }
+}
+
+#[test]
+fn removed_fields_keep_the_order() {
+    let _store = TestStore::new();
+    let content =
+        "pw\nemail: a@b.c\nuser: alice\npin: 1234\nuuid: 5b7aa9de-7f35-4bb9-a8c1-3b4ab8a2a1c2\n";
+    let mut entry = Entry::parse("test", content).unwrap();
+    entry.remove_field(0);
+    assert_eq!(
+        entry.raw_content(),
+        "pw\nuser: alice\npin: 1234\nuuid: 5b7aa9de-7f35-4bb9-a8c1-3b4ab8a2a1c2\n"
+    );
+    entry.set_raw(&format!("{}new: field\n", entry.raw()));
+    entry.url = Some("example.com".to_string());
+    assert_eq!(
+        entry.raw_content(),
+        "pw\nurl: example.com\nuser: alice\npin: 1234\nnew: field\nuuid: 5b7aa9de-7f35-4bb9-a8c1-3b4ab8a2a1c2\n"
+    );
+}
+
+#[test]
+fn new_entry_layout() {
+    let _store = TestStore::new();
+    let mut entry = Entry::new(
+        Some("alice".to_string()),
+        "pw".to_string(),
+        Some("example.com".to_string()),
+        "web/example".to_string(),
+    );
+    entry.set_raw("pin: 1234\n");
+    assert_eq!(
+        entry.raw_content(),
+        format!(
+            "pw\nuser: alice\nurl: example.com\npath: web/example\npin: 1234\nuuid: {}\n",
+            entry.uuid
+        )
+    );
+}
+
+#[test]
+fn field_kinds() {
+    let _store = TestStore::new();
+    let fields = Field::parse_all(
+        "PIN: 1234\nEmail: a@b.c\nwebsite: example.com\nlogin: https://example.com\n\
+         2fa: otpauth://totp/x?secret=GEZDGNBV\nnote: plain\nhttps://example.com\n",
+    );
+    let kinds: Vec<FieldKind> = fields.iter().map(|f| f.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            FieldKind::Secret,
+            FieldKind::Email,
+            FieldKind::Url,
+            FieldKind::Url,
+            FieldKind::Otp,
+            FieldKind::Text,
+            FieldKind::Url,
+        ]
+    );
+    assert_eq!(fields[0].key(), "pin");
+    assert_eq!(fields[6].name, "");
+}
+
+#[test]
+fn notes() {
+    let _store = TestStore::new();
+    let text = "recovery: |\n  call support\n  \n  ask for bob\nafter: note\n";
+    let fields = Field::parse_all(text);
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].kind, FieldKind::Note);
+    assert_eq!(fields[0].value, "call support\n\nask for bob\n");
+    assert_eq!(fields[1].value, "note");
+    let written: String = fields.iter().map(|f| f.to_text() + "\n").collect();
+    assert_eq!(written, text);
+}
+
+#[test]
+fn passwd_keeps_the_file_layout() {
+    let store = TestStore::new();
+    let content = "old\n\nuser: alice\n\nEmail: alice@example.com\n\nuuid: 5b7aa9de-7f35-4bb9-a8c1-3b4ab8a2a1c2\n";
+    store.write("web/example.txt", content);
+    crate::commands::init::init_with(true, |_| true).unwrap();
+    let entry = store.entry("web/example");
+    let written = store.entry_file(entry.uuid);
+    // init only adds the missing path
+    assert_eq!(
+        written,
+        content.replacen("old\n", "old\npath: web/example\n", 1)
+    );
+
+    passwd(Some("web/example"), None, Some("new"), false, None, false).unwrap();
+    assert_eq!(
+        store.entry_file(entry.uuid),
+        written.replacen("old\n", "new\n", 1)
+    );
+}
+
+#[test]
+fn secret_fields_are_masked() {
+    let _store = TestStore::new();
+    let mut entry = Entry::new(
+        Some("alice".to_string()),
+        "hunter2".to_string(),
+        None,
+        "web/example".to_string(),
+    );
+    entry.set_raw("PIN: 1234\n!recovery: abcd\nquestion: first pet\n");
+    assert_eq!(entry.fields[1].kind, FieldKind::Secret);
+
+    let shown = |e: &Entry| {
+        let mut all = e.get_rofi_lines().join("\n");
+        all.push_str(&format!("{}{:?}", e, e));
+        all
+    };
+    let hidden = shown(&entry);
+    assert!(!hidden.contains("1234"));
+    assert!(!hidden.contains("abcd"));
+    assert!(!hidden.contains("hunter2"));
+    assert!(hidden.contains("first pet"));
+
+    entry.hidden = false;
+    let revealed = shown(&entry);
+    assert!(revealed.contains("1234"));
+    assert!(revealed.contains("abcd"));
+
+    // rpass get shows the password, but only reveals secrets on request
+    entry.mask_secrets = true;
+    let get = format!("{:?}", entry);
+    assert!(get.contains("hunter2"));
+    assert!(!get.contains("1234"));
+    assert!(!get.contains("abcd"));
+    entry.mask_secrets = false;
+    entry.hidden = true;
+
+    // the masked line shown in rofi still refers to the secret
+    let line = format!("PIN{}****", crate::def::DISPLAY_RAW_SEP);
+    assert_eq!(entry.field_at_line(&line), Some(0));
+    assert_eq!(entry.fields[0].value, "1234");
+    entry.create().unwrap();
+    entry
+        .change_raw_line(Some(line), Some("PIN: 4321".to_string()))
+        .unwrap();
+    assert_eq!(Entry::get(entry.uuid).unwrap().fields[0].value, "4321");
+}
+
+#[test]
+fn notes_are_edited_in_the_editor() {
+    let store = TestStore::with_backend(|root| {
+        Plaintext::new(root)
+            .with_editor(|content| format!("{}name of the first pet\nis rex\n", content))
+    });
+    let mut entry = Entry::new(
+        Some("alice".to_string()),
+        "hunter2".to_string(),
+        None,
+        "web/example".to_string(),
+    );
+    // notes written by older versions are a single line
+    entry.set_raw("notes: call support\n");
+    entry.create().unwrap();
+
+    edit(Some("web/example"), None, false, true).unwrap();
+    let entry = store.entry("web/example");
+    assert_eq!(
+        entry.note("notes"),
+        Some("call support\nname of the first pet\nis rex\n")
+    );
+    assert!(store
+        .entry_file(entry.uuid)
+        .contains("notes: |\n  call support\n  name of the first pet\n  is rex\n"));
+    assert!(format!("{:?}", entry).contains("    notes:\n        call support\n        name"));
+
+    // notes are searchable
+    assert_eq!(find_matches(&["notes:first pet"]).unwrap().len(), 1);
+    assert_eq!(grep_matches("^notes: is rex$").unwrap().len(), 1);
+    assert!(grep_matches("^notes: call support name")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn empty_notes_are_removed() {
+    let _store = TestStore::new();
+    let mut entry = Entry::parse("test", "pw\nnotes: |\n  some\n  text\nuser: alice\n").unwrap();
+    assert_eq!(entry.note("notes"), Some("some\ntext\n"));
+    entry.set_note("notes", "other");
+    assert_eq!(entry.raw_content(), "pw\nnotes: |\n  other\nuser: alice\n");
+    entry.set_note("notes", "\n");
+    assert_eq!(entry.raw_content(), "pw\nuser: alice\n");
+    entry.set_note("recovery", "first\nsecond");
+    assert_eq!(
+        entry.raw_content(),
+        "pw\nuser: alice\nrecovery: |\n  first\n  second\n"
+    );
+}
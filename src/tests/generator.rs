@@ -0,0 +1,241 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::{insert, passwd};
+use crate::config::ConfigBuilder;
+use crate::def;
+use crate::errors::Error;
+use crate::pass::generator::{Policy, DEFAULT_POLICY};
+
+fn policy(length: usize, lowercase: bool, uppercase: bool, digits: bool, symbols: bool) -> Policy {
+    Policy {
+        length,
+        lowercase,
+        uppercase,
+        digits,
+        symbols,
+        ..Policy::get(DEFAULT_POLICY).unwrap()
+    }
+}
+
+#[test]
+fn every_class_is_used() {
+    let policy = policy(4, true, true, true, true);
+    for _ in 0..200 {
+        let password = policy.generate().unwrap();
+        assert_eq!(password.chars().count(), 4);
+        assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+        assert!(password.chars().any(|c| c.is_ascii_punctuation()));
+    }
+}
+
+#[test]
+fn disabled_and_ambiguous_characters_are_excluded() {
+    let mut policy = policy(64, true, false, true, false);
+    policy.exclude_ambiguous = true;
+    for _ in 0..50 {
+        let password = policy.generate().unwrap();
+        assert!(password
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert!(!password.contains(|c| "0Oo1lI".contains(c)));
+    }
+}
+
+#[test]
+fn passwords_differ() {
+    let policy = Policy::get(DEFAULT_POLICY).unwrap();
+    assert_ne!(policy.generate().unwrap(), policy.generate().unwrap());
+}
+
+#[test]
+fn passphrases() {
+    let mut policy = Policy::get("passphrase").unwrap();
+    policy.words = Some(5);
+    policy.separator = " ".to_string();
+    let passphrase = policy.generate().unwrap();
+    let words: Vec<&str> = passphrase.split(' ').collect();
+    assert_eq!(words.len(), 5);
+    assert!(words
+        .iter()
+        .all(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_lowercase())));
+}
+
+#[test]
+fn invalid_policies() {
+    let is_invalid = |policy: Policy| matches!(policy.generate(), Err(Error::InvalidPolicy(..)));
+    assert!(is_invalid(policy(20, false, false, false, false)));
+    assert!(is_invalid(policy(3, true, true, true, true)));
+    let mut passphrase = Policy::get("passphrase").unwrap();
+    passphrase.words = Some(0);
+    assert!(is_invalid(passphrase));
+    assert!(matches!(Policy::get("nope"), Err(Error::UnknownPolicy(_))));
+}
+
+#[test]
+fn folders_select_the_policy() {
+    let mut policy = Policy::get("pin").unwrap();
+    policy.folders = vec!["bank".to_string(), "bank/cards/".to_string()];
+    assert_eq!(policy.folder_of("bank/cards/visa"), Some("bank/cards"));
+    assert_eq!(policy.folder_of("bank/online"), Some("bank"));
+    assert_eq!(policy.folder_of("banking/online"), None);
+    assert_eq!(policy.folder_of("bank"), None);
+    assert_eq!(
+        Policy::select(None, "web/mail").unwrap().name,
+        DEFAULT_POLICY
+    );
+    assert_eq!(Policy::select(Some("PIN"), "web/mail").unwrap().name, "pin");
+}
+
+#[test]
+fn insert_stores_the_policy() {
+    let store = TestStore::new();
+    insert(
+        Some("phone"),
+        None,
+        None,
+        None,
+        false,
+        Some("pin"),
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    let entry = store.entry("phone");
+    assert_eq!(entry.password.len(), 6);
+    assert_eq!(entry.field_value(def::POLICY_FIELD), Some("pin"));
+
+    // later passwords follow the policy of the entry
+    passwd(Some("phone"), None, None, true, None, false).unwrap();
+    let entry = store.entry("phone");
+    assert_eq!(entry.password.len(), 6);
+    assert!(entry.password.chars().all(|c| c.is_ascii_digit()));
+
+    assert!(insert(
+        Some("other"),
+        None,
+        None,
+        None,
+        true,
+        None,
+        false,
+        None,
+        &["policy=unknown"],
+    )
+    .is_err());
+}
+
+#[test]
+fn config_policies() {
+    let config = toml::from_str::<ConfigBuilder>(
+        "[policies.bank]\nlength = 12\nsymbols = false\nfolders = [\"finance\"]\n\
+         [policies.words]\nwords = 4\nseparator = \".\"\n",
+    )
+    .unwrap()
+    .build();
+    let bank = &config.policies[0];
+    assert_eq!(bank.name, "bank");
+    assert_eq!(bank.length, 12);
+    assert!(!bank.symbols);
+    assert!(bank.lowercase && bank.uppercase && bank.digits);
+    assert!(!bank.exclude_ambiguous);
+    assert_eq!(bank.words, None);
+    assert_eq!(bank.folders, vec!["finance"]);
+    let words = &config.policies[1];
+    assert_eq!(words.words, Some(4));
+    assert_eq!(words.separator, ".");
+}
+
+#[test]
+fn rules_override_the_policy() {
+    let policy = Policy::get(DEFAULT_POLICY)
+        .unwrap()
+        .with_rule("len=16 charset=alnum")
+        .unwrap();
+    assert_eq!(policy.length, 16);
+    assert!(policy.lowercase && policy.uppercase && policy.digits);
+    assert!(!policy.symbols);
+    let password = policy.generate().unwrap();
+    assert_eq!(password.len(), 16);
+    assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    let passphrase = Policy::get(DEFAULT_POLICY)
+        .unwrap()
+        .with_rule("words=3 sep=_")
+        .unwrap()
+        .generate()
+        .unwrap();
+    assert_eq!(passphrase.split('_').count(), 3);
+
+    let is_invalid = |rule| {
+        matches!(
+            Policy::get(DEFAULT_POLICY).unwrap().with_rule(rule),
+            Err(Error::InvalidField(..))
+        )
+    };
+    assert!(is_invalid("len=many"));
+    assert!(is_invalid("charset=emoji"));
+    assert!(is_invalid("max=3"));
+    assert!(is_invalid("alnum"));
+}
+
+#[test]
+fn passwd_follows_the_rules_of_the_entry() {
+    let store = TestStore::new();
+    insert(
+        Some("shop"),
+        Some("bob"),
+        None,
+        None,
+        true,
+        None,
+        false,
+        None,
+        &["pwrule=len=12 charset=lower,digits"],
+    )
+    .unwrap();
+    let is_compliant = |pw: &str| {
+        pw.len() == 12
+            && pw
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    };
+    assert!(is_compliant(&store.entry("shop").password));
+
+    passwd(Some("shop"), None, None, true, None, false).unwrap();
+    assert!(is_compliant(&store.entry("shop").password));
+
+    // the rules of the site also apply to an explicit policy
+    passwd(Some("shop"), None, None, false, Some("alphanumeric"), false).unwrap();
+    assert!(is_compliant(&store.entry("shop").password));
+
+    assert!(insert(
+        Some("other"),
+        None,
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        &["pwrule=len"],
+    )
+    .is_err());
+}
@@ -0,0 +1,332 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use lazy_static::lazy_static;
+use tempfile::TempDir;
+
+use super::TestStore;
+use crate::commands::{delete, insert, mv};
+use crate::errors::Error;
+use crate::pass::backend::{get_backend, Backend, Gpg};
+
+const ALICE: &str = "alice@rpass.test";
+const BOB: &str = "bob@rpass.test";
+const CAROL: &str = "carol@rpass.test";
+
+lazy_static! {
+    /// GnuPG home with throwaway keys for alice and bob (Curve25519), and carol (RSA), shared
+    /// between all tests.
+    static ref GNUPGHOME: PathBuf = {
+        let home = TempDir::new().unwrap().keep();
+        let keys = [
+            (ALICE, "future-default", "default"),
+            (BOB, "future-default", "default"),
+            (CAROL, "rsa2048", "sign,encr"),
+        ];
+        for (user, algo, usage) in keys.iter() {
+            let status = Command::new("gpg")
+                .env("GNUPGHOME", &home)
+                .args(["--batch", "--quiet", "--passphrase", "", "--pinentry-mode", "loopback"])
+                .args(["--quick-generate-key", user, algo, usage, "never"])
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success());
+        }
+        home
+    };
+}
+
+fn gpg_store() -> TestStore {
+    let store = TestStore::with_backend(|root| {
+        std::fs::write(root.join(".gpg-id"), format!("{}\n", ALICE)).unwrap();
+        Gpg::new(root).with_homedir(GNUPGHOME.as_path())
+    });
+    store.git("", &["init", "--quiet"]);
+    store
+}
+
+fn gpg(args: &[&str]) -> Command {
+    let mut cmd = Command::new("gpg");
+    cmd.env("GNUPGHOME", GNUPGHOME.as_path())
+        .arg("--batch")
+        .arg("--quiet")
+        .args(args);
+    cmd
+}
+
+/// Encrypt `content` with gpg itself, and store it in `file` of the store.
+fn gpg_encrypt(store: &TestStore, file: &str, recipient: &str, args: &[&str], content: &str) {
+    let mut p = gpg(args)
+        .args(["--yes", "--encrypt", "--recipient", recipient, "--output"])
+        .arg(store.root().join(file))
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    p.stdin
+        .take()
+        .unwrap()
+        .write_all(content.as_bytes())
+        .unwrap();
+    assert!(p.wait().unwrap().success());
+}
+
+/// Decrypt `file` of the store with gpg itself.
+fn gpg_decrypt(store: &TestStore, file: &str) -> String {
+    let output = gpg(&["--decrypt"])
+        .arg(store.root().join(file))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Key ids of all recipients the file is encrypted for.
+fn recipients_of(store: &TestStore, file: &str) -> String {
+    let output = Command::new("gpg")
+        .env("GNUPGHOME", GNUPGHOME.as_path())
+        .args(["--batch", "--list-packets"])
+        .arg(store.root().join(file))
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn key_id(user: &str) -> String {
+    let output = Command::new("gpg")
+        .env("GNUPGHOME", GNUPGHOME.as_path())
+        .args(["--batch", "--with-colons", "--list-keys", user])
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .find(|l| {
+            let fields: Vec<&str> = l.split(':').collect();
+            (fields[0] == "pub" || fields[0] == "sub") && fields[11].contains('e')
+        })
+        .map(|l| l.split(':').nth(4).unwrap().to_string())
+        .unwrap()
+}
+
+#[test]
+fn gpg_roundtrip() {
+    let store = gpg_store();
+    let backend = get_backend();
+    backend
+        .write("uuids/entry", "secret\nuser: alice\n")
+        .unwrap();
+
+    let raw = std::fs::read(store.root().join("uuids/entry.gpg")).unwrap();
+    assert!(!String::from_utf8_lossy(&raw).contains("secret"));
+    assert_eq!(
+        backend.read("uuids/entry").unwrap(),
+        "secret\nuser: alice\n"
+    );
+    assert_eq!(
+        backend.list("uuids").unwrap(),
+        vec!["uuids/entry", "uuids/index"]
+    );
+}
+
+#[test]
+fn gpg_honours_nested_gpg_id() {
+    let store = gpg_store();
+    store.write(
+        "team/.gpg-id",
+        &format!("{} # the team\n\n{}\n", BOB, ALICE),
+    );
+    let backend = Gpg::new(store.root()).with_homedir(GNUPGHOME.as_path());
+
+    assert_eq!(backend.recipients("uuids/entry").unwrap(), vec![ALICE]);
+    assert_eq!(
+        backend.recipients("team/sub/entry").unwrap(),
+        vec![BOB, ALICE]
+    );
+
+    backend.write("private", "a").unwrap();
+    backend.write("team/sub/shared", "b").unwrap();
+    let private = recipients_of(&store, "private.gpg");
+    let shared = recipients_of(&store, "team/sub/shared.gpg");
+    assert!(private.contains(&key_id(ALICE)));
+    assert!(!private.contains(&key_id(BOB)));
+    assert!(shared.contains(&key_id(ALICE)));
+    assert!(shared.contains(&key_id(BOB)));
+}
+
+#[test]
+fn gpg_without_gpg_id() {
+    let store = gpg_store();
+    std::fs::remove_file(store.root().join(".gpg-id")).unwrap();
+    assert!(matches!(
+        get_backend().write("entry", "secret"),
+        Err(Error::NoRecipients(_))
+    ));
+}
+
+#[test]
+fn gpg_read_missing_entry() {
+    let _store = gpg_store();
+    assert!(matches!(
+        get_backend().read("missing"),
+        Err(Error::EntryRead(_))
+    ));
+}
+
+#[test]
+fn gpg_commands_and_git_history() {
+    let store = gpg_store();
+    insert(
+        Some("web/github"),
+        Some("alice"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    insert(
+        Some("mail"),
+        Some("bob"),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    mv(Some("mail"), None, Some("email"), false).unwrap();
+    delete(Some("web/github"), None, true, false, false).unwrap();
+
+    assert_eq!(store.paths(), vec!["email"]);
+    let email = store.entry("email");
+    assert_eq!(email.username.as_deref(), Some("bob"));
+    assert_eq!(email.path.as_deref(), Some("email"));
+
+    // every modification is committed, and the working tree is clean
+    let status = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(store.root())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(status.stdout).unwrap(), "?? .gpg-id\n");
+}
+
+#[test]
+fn gpg_failed_write_keeps_previous_version() {
+    let store = gpg_store();
+    let backend = get_backend();
+    backend.write("entry", "old").unwrap();
+
+    store.write(".gpg-id", "unknown@rpass.test\n");
+    assert!(matches!(
+        backend.write("entry", "new"),
+        Err(Error::Encryption(_))
+    ));
+    assert_eq!(backend.read("entry").unwrap(), "old");
+    assert!(!store.root().join(".entry.gpg.tmp").exists());
+}
+
+#[test]
+fn gpg_reads_entries_without_starting_gpg() {
+    let store = gpg_store();
+    let backend = Gpg::new(store.root()).with_homedir(GNUPGHOME.as_path());
+    let names: Vec<String> = (0..20).map(|i| format!("uuids/entry{}", i)).collect();
+    for name in names.iter() {
+        backend
+            .write(name, &format!("secret of {}\n", name))
+            .unwrap();
+    }
+    assert_eq!(backend.read(&names[0]).unwrap(), "secret of uuids/entry0\n");
+
+    // the keys are looked up once, independent of the number of entries
+    let started = backend.processes();
+    assert!(started <= 5, "{} gpg processes", started);
+    let contents = backend.read_all(&names);
+    assert_eq!(contents.len(), 20);
+    for (name, content) in names.iter().zip(contents) {
+        assert_eq!(content.unwrap(), format!("secret of {}\n", name));
+    }
+    backend.write("uuids/entry3", "changed\n").unwrap();
+    assert_eq!(backend.read("uuids/entry3").unwrap(), "changed\n");
+    assert_eq!(backend.processes(), started);
+}
+
+#[test]
+fn gpg_is_compatible_with_gpg() {
+    let store = gpg_store();
+    store.write(".gpg-id", &format!("{}\n{}\n", CAROL, ALICE));
+    let backend = Gpg::new(store.root()).with_homedir(GNUPGHOME.as_path());
+
+    // RSA and Curve25519 keys can read what rpass writes
+    backend.write("entry", "secret\n").unwrap();
+    let recipients = recipients_of(&store, "entry.gpg");
+    assert!(recipients.contains(&key_id(CAROL)));
+    assert!(recipients.contains(&key_id(ALICE)));
+    assert_eq!(gpg_decrypt(&store, "entry.gpg"), "secret\n");
+    store.write(".gpg-id", &format!("{}\n", BOB));
+    backend.write("entry", "secret\n").unwrap();
+    assert_eq!(gpg_decrypt(&store, "entry.gpg"), "secret\n");
+
+    // rpass reads what gpg writes, whether it is compressed, armored or for an RSA key
+    let messages = [
+        (ALICE, vec!["--compress-algo=zip"]),
+        (ALICE, vec!["--compress-algo=zlib", "--armor"]),
+        (CAROL, vec!["--compress-algo=none"]),
+        (BOB, vec!["--cipher-algo=AES128"]),
+    ];
+    backend.read("entry").unwrap();
+    let started = backend.processes();
+    for (i, (recipient, args)) in messages.iter().enumerate() {
+        let content = format!("message {}\nwith: {}\n", i, recipient);
+        gpg_encrypt(&store, "entry.gpg", recipient, args, &content);
+        assert_eq!(backend.read("entry").unwrap(), content, "{:?}", args);
+    }
+    assert_eq!(backend.processes(), started);
+
+    // messages that rpass cannot decrypt by itself are left to gpg
+    gpg_encrypt(
+        &store,
+        "entry.gpg",
+        ALICE,
+        &["--cipher-algo=CAMELLIA256"],
+        "camellia\n",
+    );
+    assert_eq!(backend.read("entry").unwrap(), "camellia\n");
+    assert_eq!(backend.processes(), started + 1);
+}
+
+#[test]
+fn gpg_detects_modified_entries() {
+    let store = gpg_store();
+    let backend = Gpg::new(store.root()).with_homedir(GNUPGHOME.as_path());
+    backend.write("entry", "secret\n").unwrap();
+    let file = store.root().join("entry.gpg");
+    let mut content = std::fs::read(&file).unwrap();
+    let last = content.len() - 1;
+    content[last] ^= 1;
+    std::fs::write(&file, content).unwrap();
+    assert!(matches!(backend.read("entry"), Err(Error::EntryRead(_))));
+}
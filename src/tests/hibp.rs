@@ -0,0 +1,177 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha1::{Digest, Sha1};
+use tempfile::TempDir;
+
+use super::TestStore;
+use crate::commands::audit::{audit_report, Breached, Unchecked};
+use crate::commands::insert;
+use crate::errors::Error;
+use crate::pass::hibp::Breaches;
+
+fn sha1(password: &str) -> String {
+    Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// Write a sorted dump with `breached` and many random hashes, with lines of varying length.
+fn write_dump(dir: &TempDir, breached: &[(&str, u64)], newline: &str) -> std::path::PathBuf {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut lines: Vec<(String, u64)> = (0..2000)
+        .map(|_| {
+            let hash: String = (0..20)
+                .map(|_| format!("{:02X}", rng.gen::<u8>()))
+                .collect();
+            let digits = rng.gen_range(1..8);
+            (hash, rng.gen_range(1..10u64.pow(digits)))
+        })
+        .collect();
+    lines.extend(breached.iter().map(|(pw, count)| (sha1(pw), *count)));
+    lines.sort();
+    let content: String = lines
+        .iter()
+        .map(|(hash, count)| format!("{}:{}{}", hash, count, newline))
+        .collect();
+    let file = dir.path().join("pwned-passwords-sha1-ordered-by-hash.txt");
+    fs::write(&file, content).unwrap();
+    file
+}
+
+#[test]
+fn lookup() {
+    let dir = TempDir::new().unwrap();
+    for newline in ["\n", "\r\n"] {
+        let file = write_dump(
+            &dir,
+            &[("password", 10434004), ("hunter2", 17043), ("abc", 1)],
+            newline,
+        );
+        let mut breaches = Breaches::open(&file).unwrap();
+        assert_eq!(breaches.count("password").unwrap(), 10434004);
+        assert_eq!(breaches.count("hunter2").unwrap(), 17043);
+        assert_eq!(breaches.count("abc").unwrap(), 1);
+        assert_eq!(breaches.count("Xq7#mZ2!vK9@pW4$").unwrap(), 0);
+        assert_eq!(breaches.count("").unwrap(), 0);
+    }
+}
+
+#[test]
+fn first_and_last_lines() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("dump.txt");
+    let mut lines = [
+        format!("{}:3", sha1("first")),
+        format!("{}:5", sha1("second")),
+        format!("{}:7", sha1("third")),
+    ];
+    lines.sort();
+    fs::write(&file, lines.join("\n")).unwrap();
+    let mut breaches = Breaches::open(&file).unwrap();
+    for (password, count) in [("first", 3), ("second", 5), ("third", 7), ("fourth", 0)] {
+        assert_eq!(breaches.count(password).unwrap(), count, "{}", password);
+    }
+
+    fs::write(&file, "").unwrap();
+    assert_eq!(Breaches::open(&file).unwrap().count("first").unwrap(), 0);
+
+    fs::write(&file, "not a dump\n").unwrap();
+    assert!(matches!(
+        Breaches::open(&file).unwrap().count("first"),
+        Err(Error::Breaches(_))
+    ));
+    assert!(matches!(
+        Breaches::open(dir.path().join("missing.txt")),
+        Err(Error::Breaches(_))
+    ));
+}
+
+#[test]
+fn audit_lists_breached_entries() {
+    let _store = TestStore::new();
+    for (path, password) in [("mail", "hunter2"), ("web/github", "Xq7#mZ2!vK9@pW4$")] {
+        insert(
+            Some(path),
+            Some("alice"),
+            Some(password),
+            Some("url"),
+            false,
+            None,
+            false,
+            None,
+            &[],
+        )
+        .unwrap();
+    }
+    let dir = TempDir::new().unwrap();
+    let file = write_dump(&dir, &[("hunter2", 17043)], "\n");
+
+    let report = audit_report(365, Some(Breaches::open(&file).unwrap())).unwrap();
+    assert_eq!(
+        report.breached,
+        Some(vec![Breached {
+            path: "mail".to_string(),
+            count: 17043,
+        }])
+    );
+    assert_eq!(audit_report(365, None).unwrap().breached, None);
+}
+
+#[test]
+fn audit_reports_failed_lookups() {
+    let _store = TestStore::new();
+    for path in ["mail", "web/github"] {
+        insert(
+            Some(path),
+            Some("alice"),
+            Some("Xq7#mZ2!vK9@pW4$"),
+            Some("url"),
+            false,
+            None,
+            false,
+            None,
+            &[],
+        )
+        .unwrap();
+    }
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("dump.txt");
+    fs::write(&file, "not a dump\r\n").unwrap();
+
+    let report = audit_report(365, Some(Breaches::open(&file).unwrap())).unwrap();
+    assert_eq!(report.breached, Some(vec![]));
+    let reason = "Breach dump Error: invalid line: not a dump".to_string();
+    assert_eq!(
+        report.unchecked,
+        vec![
+            Unchecked {
+                path: "mail".to_string(),
+                reason: reason.clone(),
+            },
+            Unchecked {
+                path: "web/github".to_string(),
+                reason,
+            },
+        ]
+    );
+    assert_eq!(report.problems(), 3);
+}
@@ -0,0 +1,157 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::{history, insert, mv, passwd, restore};
+use crate::errors::Error;
+use crate::pass::history::{changes, entry, versions};
+use crate::pass::index;
+
+/// Create an entry, and change it twice. Every change is committed.
+fn setup() -> TestStore {
+    let store = TestStore::new();
+    store.git("", &["init", "--quiet"]);
+    insert(
+        Some("web/github"),
+        Some("alice"),
+        Some("first"),
+        Some("github.com"),
+        false,
+        None,
+        false,
+        None,
+        &["pin=1234"],
+    )
+    .unwrap();
+    store.git("", &["add", "--all"]);
+    store.git(
+        "",
+        &[
+            "commit",
+            "--quiet",
+            "--date=2020-01-01T12:00:00",
+            "-m",
+            "create",
+        ],
+    );
+    passwd(Some("web/github"), None, Some("second"), false, None, false).unwrap();
+    store.git("", &["commit", "--quiet", "-am", "passwd"]);
+    let mut entry = store.entry("web/github");
+    entry.change_username(Some("bob".to_string())).unwrap();
+    entry
+        .change_raw_line(Some("pin: 1234".to_string()), Some("pin: 4321".to_string()))
+        .unwrap();
+    store.git("", &["commit", "--quiet", "-am", "edit"]);
+    store
+}
+
+#[test]
+fn history_lists_changes() {
+    let store = setup();
+    let id = store.entry("web/github").uuid;
+    let versions = versions(id).unwrap();
+    let messages: Vec<&str> = versions.iter().map(|v| v.message.as_str()).collect();
+    assert_eq!(messages, vec!["create", "passwd", "edit"]);
+    assert_eq!(versions[2].number, 3);
+
+    let first = entry(id, &versions[0]).unwrap();
+    let second = entry(id, &versions[1]).unwrap();
+    let third = entry(id, &versions[2]).unwrap();
+    assert_eq!(first.password, "first");
+
+    // secrets are masked
+    let diff = changes(Some(&first), &second);
+    assert_eq!(
+        diff,
+        vec![(
+            "password".to_string(),
+            Some("*****".to_string()),
+            Some("******".to_string())
+        )]
+    );
+    let diff = changes(Some(&second), &third);
+    assert_eq!(diff.len(), 2);
+    assert_eq!(
+        diff[0],
+        (
+            "user".to_string(),
+            Some("alice".to_string()),
+            Some("bob".to_string())
+        )
+    );
+    assert_eq!(diff[1].2.as_deref(), Some("****"));
+
+    let mut revealed = third.clone();
+    revealed.hidden = false;
+    assert_eq!(changes(Some(&third), &revealed), vec![]);
+    assert_eq!(changes(None, &revealed).len(), 5);
+    assert!(changes(None, &revealed).contains(&(
+        "pin".to_string(),
+        None,
+        Some("4321".to_string())
+    )));
+
+    history(Some("web/github"), None, false).unwrap();
+}
+
+#[test]
+fn restore_keeps_the_path() {
+    let store = setup();
+    mv(Some("web/github"), None, Some("web/gh"), false).unwrap();
+
+    restore(Some("web/gh"), None, 1).unwrap();
+    let entry = store.entry("web/gh");
+    assert_eq!(entry.password, "first");
+    assert_eq!(entry.username.as_deref(), Some("alice"));
+    assert_eq!(entry.path.as_deref(), Some("web/gh"));
+    assert_eq!(store.paths(), vec!["web/gh"]);
+    // the restored password was set with the first version
+    let first = versions(entry.uuid).unwrap()[0].time;
+    assert_eq!(
+        index::get_records().unwrap()[0].password_changed,
+        Some(first)
+    );
+    // restoring is a new version
+    assert_eq!(versions(entry.uuid).unwrap().len(), 5);
+
+    assert!(restore(Some("web/gh"), None, 9).is_err());
+}
+
+#[test]
+fn no_history_without_git() {
+    let store = TestStore::new();
+    insert(
+        Some("mail"),
+        None,
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    assert!(versions(store.entry("mail").uuid).is_err());
+}
+
+#[test]
+fn failing_git_log_is_reported() {
+    let store = setup();
+    let id = store.entry("web/github").uuid;
+    store.write(".git/HEAD", "broken");
+    assert!(matches!(versions(id), Err(Error::Command(_))));
+}
@@ -0,0 +1,235 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::thread;
+
+use uuid::Uuid;
+
+use super::TestStore;
+use crate::commands::audit::audit_report;
+use crate::errors::Error;
+use crate::pass::agent;
+use crate::pass::backend::{set_backend, Plaintext};
+use crate::pass::entry::Entry;
+use crate::pass::index::{self, IndexRecord};
+
+#[test]
+fn write_and_read_index() {
+    let store = TestStore::new();
+    let a = IndexRecord {
+        tags: vec!["work".to_string()],
+        username: Some("alice".to_string()),
+        entry_type: Some("login".to_string()),
+        ..IndexRecord::new(Uuid::new_v4(), "a/b")
+    };
+    let c = IndexRecord::new(Uuid::new_v4(), "c");
+    index::write_records(&[a.clone(), c.clone()]).unwrap();
+
+    let mut read = index::get_records().unwrap();
+    read.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(read, vec![a.clone(), c]);
+
+    let content = store.read("uuids/index.txt");
+    let mut lines = content.lines();
+    assert_eq!(lines.next(), Some("{\"version\":2}"));
+    assert_eq!(
+        lines.next().unwrap(),
+        format!(
+            "{{\"uuid\":\"{}\",\"path\":\"a/b\",\"created\":{},\"modified\":{},\
+             \"password_changed\":{},\"tags\":[\"work\"],\"username\":\"alice\",\
+             \"type\":\"login\"}}",
+            a.uuid,
+            a.created.unwrap(),
+            a.modified.unwrap(),
+            a.password_changed.unwrap()
+        )
+    );
+}
+
+#[test]
+fn old_index_is_migrated() {
+    let store = TestStore::new();
+    let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+    store.write(
+        "uuids/index.txt",
+        &format!("{} a/b\nnot-a-uuid path\n{}\n{} c d\n", a, b, b),
+    );
+
+    let mut read = index::get_index().unwrap();
+    read.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(read, vec![(a, "a/b".to_string()), (b, "c d".to_string())]);
+
+    // the index is written in the new format, without any timestamps
+    let (records, migrated) = index::parse(&store.read("uuids/index.txt")).unwrap();
+    assert!(!migrated);
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r.created.is_none()));
+}
+
+#[test]
+fn migrated_entries_are_still_due() {
+    let store = TestStore::new();
+    let entry = Entry::new(
+        Some("alice".to_string()),
+        "Xq7#mZ2!vK9@pW4$".to_string(),
+        Some("url".to_string()),
+        "a".to_string(),
+    );
+    entry.create().unwrap();
+    store.write("uuids/index.txt", &format!("{} a\n", entry.uuid));
+
+    let record = index::get_records().unwrap().remove(0);
+    assert_eq!(record.password_changed, None);
+    assert_eq!(record.password_since(), None);
+    let report = audit_report(365, None).unwrap();
+    assert_eq!(report.not_rotated.len(), 1);
+    assert_eq!(report.not_rotated[0].path, "a");
+}
+
+#[test]
+fn invalid_records_are_skipped() {
+    let store = TestStore::new();
+    let id = Uuid::new_v4();
+    store.write(
+        "uuids/index.txt",
+        &format!(
+            "{{\"version\":2}}\n{{\"uuid\":\"{}\",\"path\":\"a\"}}\n{{\"path\":\"b\"}}\n\
+             garbage\n",
+            id
+        ),
+    );
+    assert_eq!(index::get_index().unwrap(), vec![(id, "a".to_string())]);
+}
+
+#[test]
+fn newer_index_is_rejected() {
+    let store = TestStore::new();
+    store.write("uuids/index.txt", "{\"version\":3}\n");
+    assert!(matches!(index::get_index(), Err(Error::IndexVersion(3))));
+}
+
+#[test]
+fn metadata_is_updated() {
+    let _store = TestStore::new();
+    let mut entry = Entry::new(
+        Some("alice".to_string()),
+        "pw".to_string(),
+        None,
+        "a".to_string(),
+    );
+    entry.create().unwrap();
+    let created = index::get_records().unwrap()[0].clone();
+    assert_eq!(created.username.as_deref(), Some("alice"));
+    assert!(created.created.is_some());
+    assert_eq!(created.created, created.modified);
+
+    index::update(entry.uuid, |r| r.tags.push("work".to_string())).unwrap();
+    entry.change_username(Some("bob".to_string())).unwrap();
+    entry.change_path("b".to_string()).unwrap();
+
+    let record = index::get_records().unwrap()[0].clone();
+    assert_eq!(record.path, "b");
+    assert_eq!(record.username.as_deref(), Some("bob"));
+    assert_eq!(record.tags, vec!["work"]);
+    assert_eq!(record.created, created.created);
+}
+
+#[test]
+fn index_cache_is_refreshed() {
+    let store = TestStore::new();
+    assert!(index::get_index().unwrap().is_empty());
+
+    let id = Uuid::new_v4();
+    index::insert(id, "new").unwrap();
+    assert_eq!(store.paths(), vec!["new"]);
+
+    index::mv(id, "moved".to_string()).unwrap();
+    assert_eq!(store.paths(), vec!["moved"]);
+}
+
+#[test]
+fn remove_from_index() {
+    let store = TestStore::new();
+    let a = Entry::new(None, "pw".to_string(), None, "a".to_string());
+    let b = Entry::new(None, "pw".to_string(), None, "b".to_string());
+    a.create().unwrap();
+    b.create().unwrap();
+
+    index::remove(a.uuid).unwrap();
+    assert_eq!(store.paths(), vec!["b"]);
+    assert!(Entry::get(a.uuid).is_err());
+}
+
+#[test]
+fn missing_index() {
+    let store = TestStore::new();
+    std::fs::remove_file(store.root().join("uuids/index.txt")).unwrap();
+    assert!(matches!(index::get_index(), Err(Error::NoIndexFile)));
+}
+
+#[test]
+fn lookup_maps() {
+    let _store = TestStore::new();
+    let list = vec![
+        (Uuid::new_v4(), "a/b".to_string()),
+        (Uuid::new_v4(), "a/c".to_string()),
+    ];
+    let map = index::to_hashmap(&list);
+    let reverse = index::to_hashmap_reverse(&list).unwrap();
+    for (id, path) in list.iter() {
+        assert_eq!(map[id], path);
+        assert_eq!(reverse[path.as_str()], *id);
+    }
+}
+
+#[test]
+fn index_graph() {
+    let _store = TestStore::new();
+    let list = vec![
+        (Uuid::new_v4(), "a/b".to_string()),
+        (Uuid::new_v4(), "a/c".to_string()),
+        (Uuid::new_v4(), "d".to_string()),
+    ];
+    let (g, root) = index::to_graph(&list);
+    let top: Vec<&str> = g.neighbors(root).map(|n| g[n]).collect();
+    assert_eq!(top.len(), 2);
+    assert!(top.contains(&"a") && top.contains(&"d"));
+    // root, a, b, c, d
+    assert_eq!(g.node_count(), 5);
+}
+
+#[test]
+fn concurrent_updates_are_not_lost() {
+    let store = TestStore::new();
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let root = store.root().to_path_buf();
+            thread::spawn(move || {
+                agent::set_socket(None);
+                set_backend(Plaintext::new(root));
+                for i in 0..5 {
+                    index::insert(Uuid::new_v4(), &format!("t{}/e{}", t, i)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(store.paths().len(), 40);
+    assert!(!store.root().join("uuids/.index.txt.tmp").exists());
+}
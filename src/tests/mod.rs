@@ -0,0 +1,137 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+//! Integration tests, running the commands against a plaintext store in a temporary folder.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::Once;
+
+use tempfile::TempDir;
+use uuid::Uuid;
+
+use crate::commands::utils::choose_entry;
+use crate::config::CFG;
+use crate::pass::agent::set_socket;
+use crate::pass::backend::{set_backend, Backend, Plaintext};
+use crate::pass::entry::Entry;
+use crate::pass::index;
+
+mod age;
+mod agent;
+mod aliases;
+mod attachments;
+mod audit;
+mod commands;
+mod expiry;
+mod fields;
+mod generator;
+mod gpg;
+mod hibp;
+mod history;
+mod index_handling;
+mod otp;
+mod search;
+mod stores;
+mod strength;
+mod sync;
+mod templates;
+mod transactions;
+mod trash;
+
+static ENVIRONMENT: Once = Once::new();
+
+/// Temporary password store, which is used as backend by the current thread.
+pub struct TestStore {
+    dir: TempDir,
+}
+
+impl TestStore {
+    /// Create an empty store containing only the index.
+    pub fn new() -> Self {
+        Self::with_backend(|root| Plaintext::new(root))
+    }
+
+    /// Create an empty store, with a backend built from the root folder.
+    pub fn with_backend<B: Backend + 'static>(backend: impl FnOnce(&Path) -> B) -> Self {
+        // make sure that no test touches the home folder of the user.
+        ENVIRONMENT.call_once(|| {
+            let home = TempDir::new().unwrap().keep();
+            std::fs::create_dir_all(home.join(".cache")).unwrap();
+            std::env::set_var("XDG_CONFIG_HOME", home.join(".config"));
+            std::env::set_var("HOME", home);
+            std::env::set_var("GIT_AUTHOR_NAME", "rpass");
+            std::env::set_var("GIT_AUTHOR_EMAIL", "rpass@localhost");
+            std::env::set_var("GIT_COMMITTER_NAME", "rpass");
+            std::env::set_var("GIT_COMMITTER_EMAIL", "rpass@localhost");
+        });
+
+        // never talk to the agent of the user
+        set_socket(None);
+        let dir = TempDir::new().unwrap();
+        set_backend(backend(dir.path()));
+        index::write_records(&[]).unwrap();
+        Self { dir }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Read the plain content of a file relative to the root of the store.
+    pub fn read(&self, file: impl AsRef<Path>) -> String {
+        std::fs::read_to_string(self.root().join(file)).unwrap()
+    }
+
+    /// Write a plain file relative to the root of the store.
+    pub fn write(&self, file: impl AsRef<Path>, content: &str) {
+        let file = self.root().join(file);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+
+    /// Lookup an entry by its path in the index.
+    pub fn entry(&self, path: &str) -> Entry {
+        choose_entry(Some(path), None, false).unwrap()
+    }
+
+    /// Content of the file storing the entry `id`.
+    pub fn entry_file(&self, id: Uuid) -> String {
+        self.read(format!("{}/{}.txt", CFG.main.uuid_folder, id))
+    }
+
+    /// All paths in the index, sorted alphabetically.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = index::get_index()
+            .unwrap()
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Run git in a folder relative to the root of the store.
+    pub fn git(&self, dir: impl AsRef<Path>, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(self.root().join(dir))
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+}
@@ -0,0 +1,116 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::errors::Error;
+use crate::pass::entry::Entry;
+use crate::pass::index;
+use crate::pass::otp::{Algorithm, Totp};
+
+const URI: &str = "otpauth://totp/Example:alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Example";
+
+/// Test vectors of RFC 6238, appendix B.
+#[test]
+fn rfc6238_vectors() {
+    let sha1 = Totp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 8, 30);
+    let sha256 = Totp::new(
+        b"12345678901234567890123456789012".to_vec(),
+        Algorithm::Sha256,
+        8,
+        30,
+    );
+    let sha512 = Totp::new(
+        b"1234567890".repeat(7)[..64].to_vec(),
+        Algorithm::Sha512,
+        8,
+        30,
+    );
+    let vectors = [
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+    for (time, code_sha1, code_sha256, code_sha512) in vectors.iter() {
+        assert_eq!(sha1.code_at(*time), *code_sha1);
+        assert_eq!(sha256.code_at(*time), *code_sha256);
+        assert_eq!(sha512.code_at(*time), *code_sha512);
+    }
+}
+
+#[test]
+fn parse_uri() {
+    let totp = Totp::parse(URI).unwrap();
+    assert_eq!(
+        totp,
+        Totp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 6, 30)
+    );
+    assert_eq!(totp.code_at(59), "287082");
+    assert!(totp.remaining() <= 30);
+
+    let totp = Totp::parse(
+        "otpauth://totp/x?secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&algorithm=SHA256&digits=8&period=60",
+    )
+    .unwrap();
+    assert_eq!(totp.algorithm, Algorithm::Sha256);
+    assert_eq!(totp.digits, 8);
+    assert_eq!(totp.period, 60);
+
+    for uri in [
+        "https://example.com?secret=GEZDGNBV",
+        "otpauth://hotp/x?secret=GEZDGNBV&counter=1",
+        "otpauth://totp/x?issuer=Example",
+        "otpauth://totp/x?secret=not-base32!",
+        "otpauth://totp/x?secret=GEZDGNBV&algorithm=MD5",
+        "otpauth://totp/x?secret=GEZDGNBV&digits=0",
+        "otpauth://totp/x?secret=GEZDGNBV&period=0",
+    ]
+    .iter()
+    {
+        assert!(matches!(Totp::parse(uri), Err(Error::Otp(_))), "{}", uri);
+    }
+}
+
+#[test]
+fn entry_otp_line() {
+    let store = TestStore::new();
+    let mut entry = Entry::new(
+        Some("alice".to_string()),
+        "secret".to_string(),
+        None,
+        "web/example".to_string(),
+    );
+    assert!(matches!(entry.otp_code(), Err(Error::Otp(_))));
+    entry.set_raw(&format!("email: alice@example.com\n{}\n", URI));
+    entry.create().unwrap();
+
+    let id = index::get_records().unwrap()[0].uuid;
+    assert!(store.read(format!("uuids/{}.txt", id)).contains(URI));
+    let entry = Entry::get(id).unwrap();
+    assert_eq!(entry.otp.as_deref(), Some(URI));
+    assert_eq!(entry.raw(), "email: alice@example.com\n");
+    assert_eq!(entry.otp_code().unwrap().len(), 6);
+
+    // the secret is not stored in the index
+    assert!(index::get_records().unwrap()[0]
+        .fields
+        .as_ref()
+        .unwrap()
+        .values()
+        .all(|v| !v.contains("GEZDGNBV")));
+}
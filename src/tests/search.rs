@@ -0,0 +1,195 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::alias;
+use crate::commands::find::{find_matches, grep_matches};
+use crate::errors::Error;
+use crate::pass::entry::Entry;
+use crate::pass::index;
+
+fn create(path: &str, user: &str, url: &str, raw: &str) {
+    let mut entry = Entry::new(
+        Some(user.to_string()),
+        "secret".to_string(),
+        Some(url.to_string()),
+        path.to_string(),
+    );
+    entry.set_raw(raw);
+    entry.create().unwrap();
+}
+
+fn setup() -> TestStore {
+    let store = TestStore::new();
+    create(
+        "web/github",
+        "alice",
+        "https://github.com",
+        "email: alice@corp.com\n",
+    );
+    create("web/gitlab", "bob", "https://gitlab.com", "");
+    create(
+        "mail/corp",
+        "alice.smith",
+        "https://mail.corp.com",
+        "Email: asmith@corp.com\nrecovery code: 1234\n",
+    );
+    store
+}
+
+fn paths(matches: Vec<(String, uuid::Uuid)>) -> Vec<String> {
+    matches.into_iter().map(|(path, _)| path).collect()
+}
+
+#[test]
+fn find_by_field() {
+    let _store = setup();
+    assert_eq!(
+        paths(find_matches(&["user:alice"]).unwrap()),
+        vec!["mail/corp", "web/github"]
+    );
+    assert_eq!(
+        paths(find_matches(&["user:^alice$"]).unwrap()),
+        vec!["web/github"]
+    );
+    assert_eq!(
+        paths(find_matches(&["url:github.com"]).unwrap()),
+        vec!["web/github"]
+    );
+    assert_eq!(
+        paths(find_matches(&["email:asmith"]).unwrap()),
+        vec!["mail/corp"]
+    );
+    assert_eq!(
+        paths(find_matches(&["path:^web/", "user:BOB"]).unwrap()),
+        vec!["web/gitlab"]
+    );
+    assert!(find_matches(&["unknown:alice"]).unwrap().is_empty());
+
+    let github = find_matches(&["github"]).unwrap();
+    assert_eq!(github.len(), 1);
+    assert_eq!(
+        find_matches(&[&format!("uuid:{}", github[0].1)]).unwrap(),
+        github
+    );
+}
+
+#[test]
+fn find_in_any_field() {
+    let _store = setup();
+    assert_eq!(
+        paths(find_matches(&["corp.com"]).unwrap()),
+        vec!["mail/corp", "web/github"]
+    );
+    // the password is never searched
+    assert!(find_matches(&["secret"]).unwrap().is_empty());
+    assert!(matches!(find_matches(&["("]), Err(Error::Regex(_))));
+}
+
+#[test]
+fn find_terms_with_colons() {
+    let _store = setup();
+    create(
+        "notes",
+        "carol",
+        "",
+        "note: see mailto:carol@corp.com or foo:bar\n",
+    );
+    // prefixes which are no field are part of the pattern
+    assert_eq!(
+        paths(find_matches(&["https://git"]).unwrap()),
+        vec!["web/github", "web/gitlab"]
+    );
+    assert_eq!(
+        paths(find_matches(&["mailto:carol"]).unwrap()),
+        vec!["notes"]
+    );
+    assert_eq!(paths(find_matches(&["foo:bar"]).unwrap()), vec!["notes"]);
+    // fields of the entries are used, regardless of their case
+    assert_eq!(
+        paths(find_matches(&["Note:mailto"]).unwrap()),
+        vec!["notes"]
+    );
+    assert!(find_matches(&["note:https"]).unwrap().is_empty());
+}
+
+#[test]
+fn find_by_alias() {
+    let _store = setup();
+    alias::add(Some("web/github"), None, "work/code").unwrap();
+    assert_eq!(
+        find_matches(&["path:^work/"]).unwrap(),
+        find_matches(&["user:^alice$"]).unwrap()
+    );
+    assert_eq!(
+        paths(find_matches(&["work/code"]).unwrap()),
+        vec!["web/github"]
+    );
+    assert_eq!(
+        paths(grep_matches("^path: work/").unwrap()),
+        vec!["web/github"]
+    );
+}
+
+#[test]
+fn grep_lines() {
+    let _store = setup();
+    assert_eq!(
+        paths(grep_matches("^email: .*@corp").unwrap()),
+        vec!["mail/corp", "web/github"]
+    );
+    assert_eq!(
+        paths(grep_matches("^recovery code:").unwrap()),
+        vec!["mail/corp"]
+    );
+    assert!(grep_matches("secret").unwrap().is_empty());
+}
+
+#[test]
+fn search_metadata_is_collected_once() {
+    let store = setup();
+
+    // forget the metadata, as in indices of older versions
+    let records: Vec<_> = index::get_records()
+        .unwrap()
+        .into_iter()
+        .map(|r| index::IndexRecord {
+            fields: None,
+            url: None,
+            ..r
+        })
+        .collect();
+    index::write_records(&records).unwrap();
+
+    assert_eq!(
+        paths(find_matches(&["url:github"]).unwrap()),
+        vec!["web/github"]
+    );
+    assert!(index::get_records()
+        .unwrap()
+        .iter()
+        .all(|r| r.fields.is_some()));
+    assert!(store.read("uuids/index.txt").contains("recovery code"));
+
+    // the metadata is updated when the entry is changed
+    let mut entry = store.entry("web/gitlab");
+    entry.set_raw("email: bob@corp.com\n");
+    entry.write().unwrap();
+    assert_eq!(
+        paths(find_matches(&["email:bob"]).unwrap()),
+        vec!["web/gitlab"]
+    );
+}
@@ -0,0 +1,141 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use tempfile::TempDir;
+
+use super::TestStore;
+use crate::commands::utils::choose_entry;
+use crate::commands::{get, insert, list};
+use crate::errors::Error;
+use crate::pass::backend::{default_root, get_backend, replace_backend, Backend, Plaintext};
+use crate::pass::index;
+use crate::pass::store::{self, Store};
+
+fn insert_entry(path: &str, user: &str) {
+    insert(
+        Some(path),
+        Some(user),
+        Some("pw"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+}
+
+/// Configure the stores `personal` and `team`, each containing one entry. The personal store is
+/// the default one.
+fn setup() -> (TestStore, TempDir) {
+    let personal = TestStore::new();
+    let personal_backend = get_backend();
+    insert_entry("web/github", "alice");
+
+    let team = TempDir::new().unwrap();
+    let team_backend: Rc<dyn Backend> = Rc::new(Plaintext::new(team.path()));
+    replace_backend(team_backend.clone());
+    index::write_records(&[]).unwrap();
+    insert_entry("web/gitlab", "bob");
+
+    replace_backend(personal_backend.clone());
+    store::set_stores(vec![
+        Store {
+            name: "personal".to_string(),
+            backend: personal_backend,
+        },
+        Store {
+            name: "team".to_string(),
+            backend: team_backend,
+        },
+    ]);
+    (personal, team)
+}
+
+#[test]
+fn stores_combined_index() {
+    let (_personal, _team) = setup();
+    assert!(store::is_combined());
+
+    let mut paths: Vec<String> = store::get_index()
+        .unwrap()
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect();
+    paths.sort();
+    assert_eq!(paths, vec!["personal/web/github", "team/web/gitlab"]);
+    list().unwrap();
+}
+
+#[test]
+fn stores_choose_by_path() {
+    let (_personal, team) = setup();
+
+    let entry = choose_entry(Some("team/web/gitlab"), None, false).unwrap();
+    assert_eq!(entry.username.as_deref(), Some("bob"));
+    assert_eq!(entry.path.as_deref(), Some("web/gitlab"));
+    assert_eq!(get_backend().root(), team.path());
+
+    get(Some("personal/web/github"), None, false, false, true, false).unwrap();
+    for path in ["web/github", "other/web/github", "team/web/github"].iter() {
+        assert!(matches!(
+            choose_entry(Some(path), None, false),
+            Err(Error::UnknownPath(_))
+        ));
+    }
+}
+
+#[test]
+fn stores_choose_by_uuid() {
+    let (personal, team) = setup();
+    let id = choose_entry(Some("team/web/gitlab"), None, false)
+        .unwrap()
+        .uuid;
+    replace_backend(Rc::new(Plaintext::new(personal.root())));
+
+    let entry = choose_entry(None, Some(&id.to_string()), false).unwrap();
+    assert_eq!(entry.username.as_deref(), Some("bob"));
+    assert_eq!(get_backend().root(), team.path());
+}
+
+#[test]
+fn stores_select() {
+    let (_personal, team) = setup();
+
+    assert!(matches!(
+        store::select("missing"),
+        Err(Error::UnknownStore(_))
+    ));
+    store::select("team").unwrap();
+    assert!(!store::is_combined());
+    assert_eq!(get_backend().root(), team.path());
+
+    let entry = choose_entry(Some("web/gitlab"), None, false).unwrap();
+    assert_eq!(entry.username.as_deref(), Some("bob"));
+    assert!(choose_entry(Some("personal/web/github"), None, false).is_err());
+}
+
+#[test]
+fn stores_password_store_dir() {
+    std::env::set_var("PASSWORD_STORE_DIR", "/tmp/rpass-store");
+    let root = default_root();
+    std::env::remove_var("PASSWORD_STORE_DIR");
+    assert_eq!(root, PathBuf::from("/tmp/rpass-store"));
+}
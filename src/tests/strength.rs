@@ -0,0 +1,75 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use crate::pass::generator::{Policy, DEFAULT_POLICY};
+use crate::pass::strength::{Pattern, Strength};
+
+#[test]
+fn weak_passwords() {
+    for password in [
+        "password",
+        "hunter2",
+        "P@ssw0rd",
+        "qwerty123",
+        "iloveyou1",
+        "12345678",
+    ] {
+        let strength = Strength::of(password);
+        assert_eq!(strength.score, 0, "{}", password);
+        assert_eq!(
+            strength.patterns[0],
+            Pattern::CommonPassword,
+            "{}",
+            password
+        );
+        assert!(strength.warning().is_some());
+    }
+}
+
+#[test]
+fn patterns_are_found() {
+    let patterns = |password| Strength::of(password).patterns;
+    assert_eq!(patterns("zzzzzzzz"), vec![Pattern::Repeat]);
+    assert_eq!(patterns("lmnopqrs"), vec![Pattern::Sequence]);
+    assert_eq!(patterns("asdfghjk"), vec![Pattern::Keyboard]);
+    assert_eq!(patterns("Ocean1987"), vec![Pattern::Word, Pattern::Year]);
+    assert!(Strength::of("Ocean1987").score < 2);
+    assert_eq!(Strength::of("").score, 0);
+}
+
+#[test]
+fn strong_passwords() {
+    let strength = Strength::of("xK#9vL2!qR7$mN4@");
+    assert_eq!(strength.score, 4);
+    assert!(strength.bits > 90.0);
+    assert_eq!(strength.warning(), None);
+
+    // generated passwords and passphrases are always strong
+    for name in [DEFAULT_POLICY, "alphanumeric", "passphrase"] {
+        let password = Policy::get(name).unwrap().generate().unwrap();
+        assert_eq!(Strength::of(&password).score, 4, "{}", password);
+    }
+    assert!(Strength::of(&Policy::get("pin").unwrap().generate().unwrap()).score < 2);
+}
+
+#[test]
+fn longer_is_stronger() {
+    let short = Strength::of("kq8vz");
+    let long = Strength::of("kq8vzw3x");
+    assert!(long.bits > short.bits);
+    assert!(Strength::of("Ocean").bits > Strength::of("ocean").bits);
+    assert!(Strength::of("0cean").bits > Strength::of("ocean").bits);
+}
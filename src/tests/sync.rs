@@ -0,0 +1,267 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::{alias, attach, delete, insert, mv, sync};
+use crate::errors::Error;
+use crate::pass::index;
+
+/// Create a store with two entries, and initialize the sync folder.
+fn setup() -> TestStore {
+    let store = TestStore::new();
+    store.write(".gpg-id", "rpass@localhost\n");
+    store.git("", &["init", "--quiet"]);
+    insert(
+        Some("web/github"),
+        Some("alice"),
+        Some("pw1"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    insert(
+        Some("mail"),
+        Some("bob"),
+        Some("pw2"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "initial"]);
+
+    sync::init().unwrap();
+    store
+}
+
+#[test]
+fn sync_init_mirrors_entries() {
+    let store = setup();
+    let github = store.entry("web/github");
+    let mail = store.entry("mail");
+
+    assert_eq!(
+        store.read(".sync/web/github.txt"),
+        store.entry_file(github.uuid)
+    );
+    assert_eq!(store.read(".sync/mail.txt"), store.entry_file(mail.uuid));
+    assert!(store.read(".gitignore").lines().any(|l| l == ".sync"));
+    assert_eq!(store.read(".sync/.gpg-id"), "rpass@localhost\n");
+    assert_eq!(store.read(".sync/.sync_commit").lines().count(), 2);
+}
+
+#[test]
+fn sync_master_to_slave() {
+    let store = setup();
+
+    insert(
+        Some("new/entry"),
+        Some("carol"),
+        Some("pw3"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    mv(Some("mail"), None, Some("email"), false).unwrap();
+    delete(Some("web/github"), None, true, false, false).unwrap();
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "changes"]);
+
+    sync::sync(true).unwrap();
+
+    let new = store.entry("new/entry");
+    let email = store.entry("email");
+    assert_eq!(
+        store.read(".sync/new/entry.txt"),
+        store.entry_file(new.uuid)
+    );
+    assert_eq!(store.read(".sync/email.txt"), store.entry_file(email.uuid));
+    assert!(!store.root().join(".sync/mail.txt").exists());
+    assert!(!store.root().join(".sync/web").exists());
+}
+
+#[test]
+fn sync_slave_to_master() {
+    let store = setup();
+
+    store.write(".sync/phone/pin.txt", "1234\nuser: me\n");
+    store.git(".sync", &["add", "--all"]);
+    store.git(".sync", &["commit", "--quiet", "-m", "from phone"]);
+
+    sync::sync(true).unwrap();
+
+    assert_eq!(store.paths(), vec!["mail", "phone/pin", "web/github"]);
+    let pin = store.entry("phone/pin");
+    assert_eq!(pin.password, "1234");
+    assert_eq!(pin.username.as_deref(), Some("me"));
+    // the slave must now contain the full entry
+    assert_eq!(
+        store.read(".sync/phone/pin.txt"),
+        store.entry_file(pin.uuid)
+    );
+}
+
+#[test]
+fn sync_without_changes() {
+    let store = setup();
+    sync::sync(true).unwrap();
+    assert_eq!(store.paths(), vec!["mail", "web/github"]);
+}
+
+#[test]
+fn sync_mirrors_attachments() {
+    let store = setup();
+    store.write("files/scan.pdf", "scan");
+    let file = store.root().join("files/scan.pdf");
+    attach(Some("mail"), None, file.to_str().unwrap(), None).unwrap();
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "attach"]);
+    sync::sync(true).unwrap();
+
+    let mail = store.entry("mail");
+    let blob = format!("{}/scan.pdf.txt", index::attachments_folder(mail.uuid));
+    assert_eq!(
+        store.read(".sync/mail.attachments/scan.pdf.txt"),
+        store.read(&blob)
+    );
+    assert_eq!(store.read(".sync/mail.txt"), store.entry_file(mail.uuid));
+
+    // the attachments follow the entry
+    mv(Some("mail"), None, Some("email"), false).unwrap();
+    sync::sync(true).unwrap();
+    assert!(!store.root().join(".sync/mail.attachments").exists());
+    assert_eq!(
+        store.read(".sync/email.attachments/scan.pdf.txt"),
+        store.read(&blob)
+    );
+
+    // moving the entry to the trash is committed right away
+    delete(Some("email"), None, true, false, false).unwrap();
+    sync::sync(true).unwrap();
+    assert!(!store.root().join(".sync/email.attachments").exists());
+    assert_eq!(store.paths(), vec!["web/github"]);
+}
+
+#[test]
+fn sync_mirrors_aliases() {
+    let store = setup();
+    alias::add(Some("mail"), None, "work/mail").unwrap();
+    alias::add(Some("mail"), None, "old/mail").unwrap();
+    sync::sync(true).unwrap();
+    let mail = store.entry("mail");
+    assert_eq!(
+        store.read(".sync/work/mail.txt"),
+        store.entry_file(mail.uuid)
+    );
+
+    // editing an alias on the slave changes the entry, and all its copies
+    let edited = store
+        .read(".sync/work/mail.txt")
+        .replacen("pw2", "changed", 1);
+    store.write(".sync/work/mail.txt", &edited);
+    store.git(".sync", &["rm", "--quiet", "old/mail.txt"]);
+    store.git(".sync", &["commit", "--quiet", "--all", "-m", "from phone"]);
+    sync::sync(true).unwrap();
+    assert_eq!(store.entry("mail").password, "changed");
+    assert_eq!(store.read(".sync/mail.txt"), store.entry_file(mail.uuid));
+    // removing the copy of an alias only removes the alias
+    assert_eq!(store.paths(), vec!["mail", "web/github", "work/mail"]);
+
+    alias::remove("work/mail").unwrap();
+    sync::sync(true).unwrap();
+    assert!(!store.root().join(".sync/work").exists());
+    assert_eq!(store.read(".sync/mail.txt"), store.entry_file(mail.uuid));
+}
+
+#[test]
+fn failed_sync_restores_the_slave() {
+    let store = setup();
+    let github = store.entry("web/github");
+
+    insert(
+        Some("new/entry"),
+        Some("carol"),
+        Some("pw3"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    mv(Some("mail"), None, Some("email"), false).unwrap();
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "changes"]);
+
+    // the slave breaks an entry, so its changes cannot be applied to the master
+    let broken = store
+        .read(".sync/web/github.txt")
+        .replace(&github.uuid.to_string(), &uuid::Uuid::new_v4().to_string());
+    store.write(".sync/web/github.txt", &broken);
+    let mail = store.read(".sync/mail.txt");
+
+    assert!(sync::sync(true).is_err());
+    assert!(!store.root().join(".sync/new").exists());
+    assert!(!store.root().join(".sync/email.txt").exists());
+    assert_eq!(store.read(".sync/mail.txt"), mail);
+    assert_eq!(store.read(".sync/web/github.txt"), broken);
+    assert_eq!(store.paths(), vec!["email", "new/entry", "web/github"]);
+}
+
+#[test]
+fn sync_fails_for_entries_missing_in_the_index() {
+    let store = setup();
+    let mail = store.entry("mail");
+
+    insert(
+        Some("new/entry"),
+        Some("carol"),
+        Some("pw3"),
+        Some("url"),
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    // the master contains an entry, which is not part of the index
+    let orphan = uuid::Uuid::new_v4();
+    store.write(
+        format!("uuids/{}.txt", orphan),
+        &store.entry_file(mail.uuid),
+    );
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "changes"]);
+
+    assert!(matches!(sync::sync(true), Err(Error::Sync(_))));
+    assert!(!store.root().join(".sync/new").exists());
+    assert_eq!(store.read(".sync/.sync_commit").lines().count(), 2);
+    assert_eq!(store.paths(), vec!["mail", "new/entry", "web/github"]);
+}
@@ -0,0 +1,144 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::insert;
+use crate::config::{ConfigBuilder, FieldFormat};
+use crate::errors::Error;
+use crate::pass::index;
+use crate::pass::template::Template;
+
+fn insert_card(number: &str, expiry: &str) -> crate::errors::Result<()> {
+    insert(
+        Some("cards/visa"),
+        None,
+        Some("1234"),
+        None,
+        false,
+        None,
+        false,
+        Some("card"),
+        &[
+            "cardholder=Alice Doe",
+            &format!("card number={}", number),
+            &format!("expiry={}", expiry),
+            "cvv=123",
+            "bank=Example Bank",
+        ],
+    )
+}
+
+#[test]
+fn cards_are_masked() {
+    let store = TestStore::new();
+    insert_card("4111 1111 1111 1111", "3/2031").unwrap();
+    let entry = store.entry("cards/visa");
+    assert_eq!(entry.entry_type(), Some("card"));
+    assert_eq!(
+        store.entry_file(entry.uuid),
+        format!(
+            "1234\npath: cards/visa\ntype: card\ncardholder: Alice Doe\n\
+             card number: 4111111111111111\nexpiry: 03/31\ncvv: 123\nbank: Example Bank\n\
+             uuid: {}\n",
+            entry.uuid
+        )
+    );
+
+    // card numbers only show the last 4 digits, and secrets are never stored in the index
+    let lines = entry.get_rofi_lines().join("\n");
+    assert!(lines.contains("************1111"));
+    assert!(!lines.contains("4111111111111111"));
+    assert!(!lines.contains("123\n"));
+    assert!(!lines.contains("user:"));
+    let record = index::get_records()
+        .unwrap()
+        .into_iter()
+        .find(|r| r.uuid == entry.uuid)
+        .unwrap();
+    assert_eq!(record.entry_type.as_deref(), Some("card"));
+    let fields = record.fields.unwrap();
+    assert_eq!(fields["cardholder"], "Alice Doe");
+    assert!(!fields.contains_key("card number"));
+    assert!(!fields.contains_key("cvv"));
+
+    let mut revealed = entry.clone();
+    revealed.hidden = false;
+    assert!(format!("{}", revealed).contains("card number: 4111111111111111"));
+}
+
+#[test]
+fn invalid_fields_are_rejected() {
+    let store = TestStore::new();
+    assert!(matches!(
+        insert_card("4111 1111 1111 1112", "03/31"),
+        Err(Error::InvalidField(..))
+    ));
+    assert!(matches!(
+        insert_card("4111 1111 1111 1111", "13/31"),
+        Err(Error::InvalidField(..))
+    ));
+    assert!(matches!(
+        insert(None, None, None, None, false, None, false, Some("car"), &[]),
+        Err(Error::UnknownType(_))
+    ));
+    assert!(store.paths().is_empty());
+}
+
+#[test]
+fn field_formats() {
+    let _store = TestStore::new();
+    assert_eq!(
+        FieldFormat::Card.validate("5555-5555-5555-4444"),
+        Ok("5555555555554444".to_string())
+    );
+    assert!(FieldFormat::Card.validate("1234").is_err());
+    assert_eq!(
+        FieldFormat::Expiry.validate("12/2030"),
+        Ok("12/30".to_string())
+    );
+    assert!(FieldFormat::Expiry.validate("1230").is_err());
+    assert!(FieldFormat::Digits.validate("12a").is_err());
+    assert_eq!(
+        FieldFormat::Card.mask("5555555555554444"),
+        "************4444"
+    );
+    assert_eq!(FieldFormat::Digits.mask("123"), "***");
+
+    let names: Vec<String> = Template::all().into_iter().map(|t| t.name).collect();
+    assert_eq!(names, vec!["login", "card", "ssh", "wifi", "api"]);
+    let wifi = Template::get("WiFi").unwrap();
+    assert!(!wifi.username);
+    assert_eq!(wifi.field("SSID").unwrap().format, FieldFormat::Text);
+}
+
+#[test]
+fn config_templates() {
+    let config = toml::from_str::<ConfigBuilder>(
+        "[templates.server]\nurl = false\n\
+         fields = [{ name = \"host\" }, { name = \"port\", format = \"digits\", optional = true }]\n",
+    )
+    .unwrap()
+    .build();
+    let server = &config.templates[0];
+    assert_eq!(server.name, "server");
+    assert!(server.username);
+    assert!(!server.url);
+    assert_eq!(server.password, "password");
+    assert_eq!(server.fields[1].name, "port");
+    assert_eq!(server.fields[1].format, FieldFormat::Digits);
+    assert!(server.fields[1].optional);
+    assert!(!server.fields[0].optional);
+}
@@ -0,0 +1,107 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use std::process::Command;
+
+use uuid::Uuid;
+
+use super::TestStore;
+use crate::commands::bulk_rename::apply_renames;
+use crate::commands::insert;
+use crate::pass::index::Transaction;
+
+/// Store with the entries `a` and `b`, committed to git.
+fn setup() -> TestStore {
+    let store = TestStore::new();
+    store.git("", &["init", "--quiet"]);
+    for path in ["a", "b"].iter() {
+        insert(
+            Some(path),
+            Some("user"),
+            Some("pw"),
+            Some("url"),
+            false,
+            None,
+            false,
+            None,
+            &[],
+        )
+        .unwrap();
+    }
+    store.git("", &["add", "--all"]);
+    store.git("", &["commit", "--quiet", "-m", "initial"]);
+    store
+}
+
+fn git_output(store: &TestStore, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(store.root())
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn transaction_is_one_commit() {
+    let store = setup();
+    let a = store.entry("a").uuid;
+    let b = store.entry("b").uuid;
+
+    apply_renames(vec![
+        (a, "a".to_string(), "x/a".to_string()),
+        (b, "b".to_string(), "x/b".to_string()),
+    ])
+    .unwrap();
+
+    assert_eq!(store.paths(), vec!["x/a", "x/b"]);
+    assert_eq!(store.entry("x/a").path.as_deref(), Some("x/a"));
+    assert_eq!(
+        git_output(&store, &["log", "--format=%s"]),
+        "Rename 2 entries.\ninitial\n"
+    );
+    assert_eq!(git_output(&store, &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn transaction_rolls_back() {
+    let store = setup();
+    let a = store.entry("a");
+    let index = store.read("uuids/index.txt");
+    let entry = store.entry_file(a.uuid);
+
+    let mut tx = Transaction::begin("broken").unwrap();
+    let mut moved = a.clone();
+    moved.change_path_in("x/a".to_string(), &mut tx);
+    // deleting an entry which does not exist fails
+    tx.remove(Uuid::new_v4()).unwrap();
+    assert!(tx.commit().is_err());
+
+    assert_eq!(store.read("uuids/index.txt"), index);
+    assert_eq!(store.entry_file(a.uuid), entry);
+    assert_eq!(store.paths(), vec!["a", "b"]);
+    assert_eq!(git_output(&store, &["log", "--format=%s"]), "initial\n");
+    assert_eq!(git_output(&store, &["status", "--porcelain"]), "");
+}
+
+#[test]
+fn transaction_without_changes() {
+    let store = setup();
+    let index = store.read("uuids/index.txt");
+    Transaction::begin("nothing").unwrap().commit().unwrap();
+    assert_eq!(store.read("uuids/index.txt"), index);
+    assert_eq!(git_output(&store, &["log", "--format=%s"]), "initial\n");
+}
@@ -0,0 +1,140 @@
+// rpass: a password manager based on pass, written in rust
+// Copyright (C) 2020, Tibor Schneider
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see http://www.gnu.org/licenses/
+
+use super::TestStore;
+use crate::commands::fix_index::check_index;
+use crate::commands::{attach, delete, insert, trash};
+use crate::errors::Error;
+use crate::pass::entry::Entry;
+use crate::pass::expiry::parse_duration;
+use crate::pass::index;
+
+fn insert_entry(store: &TestStore, path: &str) -> Entry {
+    insert(
+        Some(path),
+        Some("alice"),
+        Some("pw"),
+        None,
+        false,
+        None,
+        false,
+        None,
+        &[],
+    )
+    .unwrap();
+    store.entry(path)
+}
+
+#[test]
+fn deleted_entries_can_be_restored() {
+    let store = TestStore::new();
+    let entry = insert_entry(&store, "web/github");
+    store.write("scan.pdf", "scan");
+    let file = store.root().join("scan.pdf");
+    attach(Some("web/github"), None, file.to_str().unwrap(), None).unwrap();
+    let content = store.entry_file(entry.uuid);
+    let long_ago = Some(index::now() - 100 * 24 * 60 * 60);
+    index::update(entry.uuid, |r| r.password_changed = long_ago).unwrap();
+
+    delete(Some("web/github"), None, true, false, false).unwrap();
+    assert!(store.paths().is_empty());
+    assert!(Entry::get(entry.uuid).is_err());
+    assert!(index::attachment_blobs(entry.uuid).unwrap().is_empty());
+    let records = crate::pass::trash::records().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].path, "web/github");
+    assert!(check_index().unwrap().iter().all(|(_, p)| p.is_none()));
+
+    trash::restore(None, None).unwrap();
+    assert_eq!(store.paths(), vec!["web/github"]);
+    assert_eq!(store.entry_file(entry.uuid), content);
+    assert_eq!(index::get_records().unwrap()[0].password_changed, long_ago);
+    assert_eq!(
+        store.entry("web/github").extract("scan.pdf").unwrap(),
+        b"scan"
+    );
+    assert!(crate::pass::trash::records().unwrap().is_empty());
+    assert!(!store.root().join("uuids/trash").exists());
+}
+
+#[test]
+fn restore_never_overwrites() {
+    let store = TestStore::new();
+    insert_entry(&store, "mail");
+    delete(Some("mail"), None, true, false, false).unwrap();
+    insert_entry(&store, "mail");
+    assert!(trash::restore(Some("mail"), None).is_err());
+    assert_eq!(store.paths(), vec!["mail"]);
+    assert_eq!(crate::pass::trash::records().unwrap().len(), 1);
+}
+
+#[test]
+fn empty_the_trash() {
+    let store = TestStore::new();
+    let a = insert_entry(&store, "a");
+    insert_entry(&store, "b");
+    delete(Some("a"), None, true, false, false).unwrap();
+    delete(Some("b"), None, true, false, false).unwrap();
+
+    // nothing was deleted 30 days ago
+    trash::empty(Some("30d")).unwrap();
+    assert_eq!(crate::pass::trash::records().unwrap().len(), 2);
+    assert!(crate::pass::trash::empty(u64::MAX).unwrap().is_empty());
+    assert_eq!(crate::pass::trash::records().unwrap().len(), 2);
+
+    trash::empty(None).unwrap();
+    assert!(crate::pass::trash::records().unwrap().is_empty());
+    assert!(!store.root().join("uuids/trash").exists());
+    assert!(trash::restore(None, Some(&a.uuid.to_string())).is_err());
+
+    // permanently deleted entries never end up in the trash
+    insert_entry(&store, "c");
+    delete(Some("c"), None, true, true, false).unwrap();
+    assert!(crate::pass::trash::records().unwrap().is_empty());
+}
+
+#[test]
+fn corrupted_trash_index_is_reported() {
+    let store = TestStore::new();
+    insert_entry(&store, "a");
+    insert_entry(&store, "b");
+    delete(Some("a"), None, true, false, false).unwrap();
+    let trash_index = store.root().join("uuids/trash/index.txt");
+    let mut content = std::fs::read_to_string(&trash_index).unwrap();
+    content.push_str("{\"uuid\": \"not a uuid\"}\n");
+    std::fs::write(&trash_index, content).unwrap();
+
+    assert!(matches!(
+        crate::pass::trash::records(),
+        Err(Error::TrashIndex(_))
+    ));
+    // the broken record is never dropped from the trash index
+    assert!(delete(Some("b"), None, true, false, false).is_err());
+    assert!(trash::empty(None).is_err());
+    assert_eq!(store.paths(), vec!["b"]);
+    assert!(std::fs::read_to_string(&trash_index)
+        .unwrap()
+        .contains("not a uuid"));
+}
+
+#[test]
+fn durations() {
+    assert_eq!(parse_duration("30d").unwrap(), 30 * 24 * 60 * 60);
+    assert_eq!(parse_duration("2w").unwrap(), 14 * 24 * 60 * 60);
+    assert_eq!(parse_duration("90m").unwrap(), 90 * 60);
+    assert!(parse_duration("30").is_err());
+    assert!(parse_duration("d").is_err());
+}